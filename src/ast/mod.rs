@@ -1,11 +1,13 @@
-use crate::token::{get_literal, Token};
+use crate::token::{get_literal, Position, Token};
+
+pub mod visit;
 
 pub trait Inspect {
     fn token_literal(&self) -> String;
     fn to_string(&self) -> String;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ArrayLiteral {
     token: Token,
     elements: Vec<Expression>,
@@ -27,10 +29,12 @@ impl Inspect for ArrayLiteral {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ImportStatement {
     pub token: Token,
     pub namespace: StringLiteral,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for ImportStatement {
@@ -39,15 +43,17 @@ impl Inspect for ImportStatement {
     }
 
     fn to_string(&self) -> String {
-        return format!("import {}", self.namespace.value);
+        return format!("import {}", self.namespace.to_string());
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CallExpression {
     pub token: Token,
     pub function: Box<Expression>,
-    pub arguments: Vec<Box<Expression>>,
+    pub arguments: Vec<Expression>,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for CallExpression {
@@ -56,19 +62,18 @@ impl Inspect for CallExpression {
     }
 
     fn to_string(&self) -> String {
-        let mut params = String::new();
-        self.arguments.iter().for_each(|p| {
-            params += format!("{}, ", p.to_string()).as_str();
-        });
-        return format!("{}({})", self.function.to_string(), params);
+        let args: Vec<String> = self.arguments.iter().map(|a| a.to_string()).collect();
+        return format!("{}({})", self.function.to_string(), args.join(", "));
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FunctionLiteral {
     pub token: Token,
     pub parameters: Vec<Identifier>,
     pub body: BlockStatement,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for FunctionLiteral {
@@ -77,19 +82,18 @@ impl Inspect for FunctionLiteral {
     }
 
     fn to_string(&self) -> String {
-        let mut params = String::new();
-        self.parameters.iter().for_each(|p| {
-            params += format!("{}, ", p.to_string()).as_str();
-        });
-
-        return format!("{} ({}) {}", self.token_literal(), params, self.body.to_string());
+        let params: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
+        return format!("{}({}) {{ {} }}", self.token_literal(), params.join(", "), self.body.to_string());
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BlockStatement {
     pub token: Token,
     pub statements: Vec<Statement>,
+    /// Where the opening and closing braces are.
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for BlockStatement {
@@ -98,20 +102,19 @@ impl Inspect for BlockStatement {
     }
 
     fn to_string(&self) -> String {
-        let mut out = String::new();
-        self.statements.iter().for_each(|s| {
-            out += &s.to_string();
-        });
-        return out;
+        let statements: Vec<String> = self.statements.iter().map(|s| s.to_string()).collect();
+        return statements.join("; ");
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IfExpression {
     pub token: Token,
     pub condition: Box<Expression>,
     pub consequence: BlockStatement,
     pub alternative: Option<BlockStatement>,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for IfExpression {
@@ -121,16 +124,18 @@ impl Inspect for IfExpression {
 
     fn to_string(&self) -> String {
         return match &self.alternative {
-            Some(alt) => format!("if {:?} {:?} else {:?}", self.condition.to_string(), self.consequence.to_string(), &alt.to_string()),
-            None => format!("if {:?} {:?}", self.condition.to_string(), self.consequence.to_string()),
+            Some(alt) => format!("if ({}) {{ {} }} else {{ {} }}", self.condition.to_string(), self.consequence.to_string(), alt.to_string()),
+            None => format!("if ({}) {{ {} }}", self.condition.to_string(), self.consequence.to_string()),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BooleanLiteral {
     pub token: Token,
     pub value: bool,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for BooleanLiteral {
@@ -143,10 +148,13 @@ impl Inspect for BooleanLiteral {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Identifier {
     pub token: Token,
     pub value: String,
+    /// Where the name starts, and just past where it ends.
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for Identifier {
@@ -159,10 +167,12 @@ impl Inspect for Identifier {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IntegerLiteral {
     pub token: Token,
     pub value: i32,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for IntegerLiteral {
@@ -175,10 +185,12 @@ impl Inspect for IntegerLiteral {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct StringLiteral {
     pub token: Token,
     pub value: String,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for StringLiteral {
@@ -187,15 +199,17 @@ impl Inspect for StringLiteral {
     }
 
     fn to_string(&self) -> String {
-        return format!("{:?}", self.value);
+        return format!("\"{}\"", self.value);
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PrefixExpression {
     pub token: Token,
     pub operator: String,
     pub right: Box<Expression>,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for PrefixExpression {
@@ -208,12 +222,14 @@ impl Inspect for PrefixExpression {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct InfixExpression {
     pub token: Token,
     pub left: Box<Expression>,
     pub right: Box<Expression>,
     pub operator: String,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for InfixExpression {
@@ -226,7 +242,57 @@ impl Inspect for InfixExpression {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TryExpression {
+    pub token: Token,
+    pub body: BlockStatement,
+    pub parameter: Option<Identifier>,
+    pub handler: Option<BlockStatement>,
+    pub finalizer: Option<BlockStatement>,
+    pub position: Position,
+    pub end: Position,
+}
+
+impl Inspect for TryExpression {
+    fn token_literal(&self) -> String {
+        return get_literal(&self.token);
+    }
+
+    fn to_string(&self) -> String {
+        let mut out = format!("try {{ {} }}", self.body.to_string());
+        if let Some(handler) = &self.handler {
+            match &self.parameter {
+                Some(param) => out += format!(" catch ({}) {{ {} }}", param.to_string(), handler.to_string()).as_str(),
+                None => out += format!(" catch {{ {} }}", handler.to_string()).as_str(),
+            }
+        }
+        if let Some(finalizer) = &self.finalizer {
+            out += format!(" finally {{ {} }}", finalizer.to_string()).as_str();
+        }
+        return out;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberExpression {
+    pub token: Token,
+    pub object: Box<Expression>,
+    pub property: Identifier,
+    pub position: Position,
+    pub end: Position,
+}
+
+impl Inspect for MemberExpression {
+    fn token_literal(&self) -> String {
+        return get_literal(&self.token);
+    }
+
+    fn to_string(&self) -> String {
+        return format!("{}.{}", self.object.to_string(), self.property.to_string());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     Empty,
     Identifier(Identifier),
@@ -238,6 +304,67 @@ pub enum Expression {
     IfExpression(IfExpression), 
     FunctionLiteral(FunctionLiteral),
     CallExpression(CallExpression),
+    TryExpression(TryExpression),
+    MemberExpression(MemberExpression),
+}
+
+impl Expression {
+    /// Where the expression starts, and just past where it ends, counting
+    /// any parentheses around it. `Empty` has no position.
+    pub fn span(&self) -> Option<(Position, Position)> {
+        return match self {
+            Expression::Empty => None,
+            Expression::Identifier(i) => Some((i.position, i.end)),
+            Expression::IntegerLiteral(il) => Some((il.position, il.end)),
+            Expression::StringLiteral(sl) => Some((sl.position, sl.end)),
+            Expression::PrefixExpression(pe) => Some((pe.position, pe.end)),
+            Expression::InfixExpression(ie) => Some((ie.position, ie.end)),
+            Expression::Boolean(b) => Some((b.position, b.end)),
+            Expression::IfExpression(ie) => Some((ie.position, ie.end)),
+            Expression::FunctionLiteral(fl) => Some((fl.position, fl.end)),
+            Expression::CallExpression(ce) => Some((ce.position, ce.end)),
+            Expression::TryExpression(te) => Some((te.position, te.end)),
+            Expression::MemberExpression(me) => Some((me.position, me.end)),
+        };
+    }
+
+    pub fn set_span(&mut self, start: Position, finish: Position) {
+        let (position, end) = match self {
+            Expression::Empty => return,
+            Expression::Identifier(i) => (&mut i.position, &mut i.end),
+            Expression::IntegerLiteral(il) => (&mut il.position, &mut il.end),
+            Expression::StringLiteral(sl) => (&mut sl.position, &mut sl.end),
+            Expression::PrefixExpression(pe) => (&mut pe.position, &mut pe.end),
+            Expression::InfixExpression(ie) => (&mut ie.position, &mut ie.end),
+            Expression::Boolean(b) => (&mut b.position, &mut b.end),
+            Expression::IfExpression(ie) => (&mut ie.position, &mut ie.end),
+            Expression::FunctionLiteral(fl) => (&mut fl.position, &mut fl.end),
+            Expression::CallExpression(ce) => (&mut ce.position, &mut ce.end),
+            Expression::TryExpression(te) => (&mut te.position, &mut te.end),
+            Expression::MemberExpression(me) => (&mut me.position, &mut me.end),
+        };
+        *position = start;
+        *end = finish;
+    }
+
+    /// The token this expression starts with, not counting any parentheses
+    /// around it.
+    pub fn first_token(&self) -> Token {
+        return match self {
+            Expression::Empty => Token::EOF,
+            Expression::InfixExpression(ie) => ie.left.first_token(),
+            Expression::CallExpression(ce) => ce.function.first_token(),
+            Expression::MemberExpression(me) => me.object.first_token(),
+            Expression::Identifier(i) => i.token.clone(),
+            Expression::IntegerLiteral(il) => il.token.clone(),
+            Expression::StringLiteral(sl) => sl.token.clone(),
+            Expression::PrefixExpression(pe) => pe.token.clone(),
+            Expression::Boolean(b) => b.token.clone(),
+            Expression::IfExpression(ie) => ie.token.clone(),
+            Expression::FunctionLiteral(fl) => fl.token.clone(),
+            Expression::TryExpression(te) => te.token.clone(),
+        };
+    }
 }
 
 impl Inspect for Expression {
@@ -253,6 +380,8 @@ impl Inspect for Expression {
             Expression::IfExpression(ie) => get_literal(&ie.token),
             Expression::FunctionLiteral(fl) => get_literal(&fl.token),
             Expression::CallExpression(ce) => get_literal(&ce.token),
+            Expression::TryExpression(te) => get_literal(&te.token),
+            Expression::MemberExpression(me) => get_literal(&me.token),
         }
     }
 
@@ -268,15 +397,19 @@ impl Inspect for Expression {
             Expression::IfExpression(ie) => ie.to_string(),
             Expression::FunctionLiteral(fl) => fl.to_string(),
             Expression::CallExpression(ce) => ce.to_string(),
+            Expression::TryExpression(te) => te.to_string(),
+            Expression::MemberExpression(me) => me.to_string(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LetStatement {
     pub token: Token,
     pub name: Identifier,
     pub value: Option<Expression>,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for LetStatement {
@@ -292,10 +425,12 @@ impl Inspect for LetStatement {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ReturnStatement {
     pub token: Token,
     pub return_val: Expression,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for ReturnStatement {
@@ -304,14 +439,17 @@ impl Inspect for ReturnStatement {
     }
 
     fn to_string(&self) -> String {
-        return String::from("return statement");
+        return format!("return {}", self.return_val.to_string());
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ExpressionStatement {
+    /// The expression's first token, see `Expression::first_token`.
     pub token: Token,
     pub expression: Expression,
+    pub position: Position,
+    pub end: Position,
 }
 
 impl Inspect for ExpressionStatement {
@@ -320,17 +458,36 @@ impl Inspect for ExpressionStatement {
     }
 
     fn to_string(&self) -> String {
-        // TODO
+        return self.expression.to_string();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrowStatement {
+    pub token: Token,
+    pub value: Expression,
+    pub position: Position,
+    pub end: Position,
+}
+
+impl Inspect for ThrowStatement {
+    fn token_literal(&self) -> String {
         return get_literal(&self.token);
     }
+
+    fn to_string(&self) -> String {
+        return format!("throw {}", self.value.to_string());
+    }
 }
 
-#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     LetStatement(LetStatement),
     ReturnStatement(ReturnStatement),
     ExpressionStatement(ExpressionStatement),
     ImportStatement(ImportStatement),
+    ThrowStatement(ThrowStatement),
 }
 
 impl Inspect for Statement {
@@ -344,29 +501,50 @@ impl Inspect for Statement {
             Statement::ReturnStatement(rs) => rs.to_string(),
             Statement::ExpressionStatement(es) => es.to_string(),
             Statement::ImportStatement(is) => is.to_string(),
+            Statement::ThrowStatement(ts) => ts.to_string(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+impl Statement {
+    pub fn position(&self) -> Position {
+        return match self {
+            Statement::LetStatement(ls) => ls.position,
+            Statement::ReturnStatement(rs) => rs.position,
+            Statement::ExpressionStatement(es) => es.position,
+            Statement::ImportStatement(is) => is.position,
+            Statement::ThrowStatement(ts) => ts.position,
+        }
+    }
+
+    /// Where the statement ends, just past its last token.
+    pub fn end(&self) -> Position {
+        return match self {
+            Statement::LetStatement(ls) => ls.end,
+            Statement::ReturnStatement(rs) => rs.end,
+            Statement::ExpressionStatement(es) => es.end,
+            Statement::ImportStatement(is) => is.end,
+            Statement::ThrowStatement(ts) => ts.end,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub statements: Vec<Statement>,
 }
 
 impl Inspect for Program {
     fn token_literal(&self) -> String {
-        if self.statements.len() > 0 {
+        if !self.statements.is_empty() {
             return self.statements.first().unwrap().token_literal();
         }
         return String::from("");
     }
 
     fn to_string(&self) -> String {
-        let mut out = String::new();
-        self.statements.iter().for_each(|s| {
-            out += &s.to_string();
-        });
-        return out;
+        let statements: Vec<String> = self.statements.iter().map(|s| s.to_string()).collect();
+        return statements.join("; ");
     }
 }
 
@@ -387,8 +565,10 @@ mod tests {
             statements: vec![
                 Statement::LetStatement(LetStatement {
                     token: Token::LET,
-                    name: Identifier { token: Token::IDENT(String::from("x")), value: String::from("x") },
+                    name: Identifier { token: Token::IDENT(String::from("x")), value: String::from("x"), position: Position::default(), end: Position::default() },
                     value: None,
+                    position: Position::default(),
+                    end: Position::default(),
                 }),
             ],
         };
@@ -403,11 +583,15 @@ mod tests {
             statements: vec![
                 Statement::LetStatement(LetStatement {
                     token: Token::LET,
-                    name: Identifier { token: Token::IDENT(String::from("x")), value: String::from("x") },
+                    name: Identifier { token: Token::IDENT(String::from("x")), value: String::from("x"), position: Position::default(), end: Position::default() },
                     value: Some(Expression::IntegerLiteral(IntegerLiteral {
                         token: Token::INT(String::from("5")),
                         value: 5,
+                        position: Position::default(),
+                        end: Position::default(),
                     })),
+                    position: Position::default(),
+                    end: Position::default(),
                 }),
             ],
         };
@@ -415,4 +599,165 @@ mod tests {
         println!("{}", program.clone().to_string());
         assert_eq!(program.to_string(), String::from("let x = 5"));
     }
+
+    /// Resets the source positions in a tree, which printing doesn't preserve.
+    struct ClearPositions;
+
+    impl visit::MutVisitor for ClearPositions {
+        fn visit_statement_mut(&mut self, s: &mut Statement) {
+            let (position, end) = match s {
+                Statement::LetStatement(ls) => (&mut ls.position, &mut ls.end),
+                Statement::ReturnStatement(rs) => (&mut rs.position, &mut rs.end),
+                Statement::ExpressionStatement(es) => (&mut es.position, &mut es.end),
+                Statement::ImportStatement(is) => (&mut is.position, &mut is.end),
+                Statement::ThrowStatement(ts) => (&mut ts.position, &mut ts.end),
+            };
+            *position = Position::default();
+            *end = Position::default();
+            visit::walk_statement_mut(self, s);
+        }
+
+        fn visit_block_mut(&mut self, b: &mut BlockStatement) {
+            b.position = Position::default();
+            b.end = Position::default();
+            visit::walk_block_mut(self, b);
+        }
+
+        fn visit_expression_mut(&mut self, e: &mut Expression) {
+            e.set_span(Position::default(), Position::default());
+            visit::walk_expression_mut(self, e);
+        }
+
+        fn visit_binding_mut(&mut self, name: &mut Identifier) {
+            name.position = Position::default();
+            name.end = Position::default();
+        }
+
+        fn visit_member_expression_mut(&mut self, me: &mut MemberExpression) {
+            me.property.position = Position::default();
+            me.property.end = Position::default();
+            visit::walk_member_expression_mut(self, me);
+        }
+    }
+
+    fn reparse(source: &str) -> Program {
+        use crate::lexer::Lexer;
+        use crate::parser::Parser;
+
+        let mut p = Parser::new(Lexer::new(source));
+        let mut program = p.parse_program().unwrap();
+        assert!(p.errors.is_empty(), "errors parsing {}: {:?}", source, p.errors);
+        visit::MutVisitor::visit_program_mut(&mut ClearPositions, &mut program);
+        return program;
+    }
+
+    #[test]
+    fn to_string_round_trip() {
+        let input = "import \"std.string\"; let f = fn(x, y) { if (x < y) { return -x; } else { x * (y + 1) } }; \
+            let r = try { throw \"bad\" } catch (e) { e.message } finally { f(1, 2).z }; !f(3)(4); fn() { }";
+        let program = reparse(input);
+        assert_eq!(program, reparse(&program.to_string()));
+    }
+
+    #[test]
+    fn to_string_round_trip_random_programs() {
+        use crate::fuzz::{generate, Rng};
+
+        for seed in 1..=500u64 {
+            let mut rng = Rng::new(seed);
+            let program = generate::program(&mut rng, 4);
+            let source = program.to_string();
+            assert_eq!(program, reparse(&source), "seed {}: {}", seed, source);
+        }
+    }
+
+    #[test]
+    fn visitor_walks_every_node() {
+        use crate::ast::visit::{walk_function_literal, Visitor};
+
+        #[derive(Default)]
+        struct Names {
+            bindings: Vec<String>,
+            uses: Vec<String>,
+            functions: usize,
+        }
+
+        impl Visitor for Names {
+            fn visit_binding(&mut self, name: &Identifier) {
+                self.bindings.push(name.value.clone());
+            }
+
+            fn visit_identifier(&mut self, i: &Identifier) {
+                self.uses.push(i.value.clone());
+            }
+
+            fn visit_function_literal(&mut self, fl: &FunctionLiteral) {
+                self.functions += 1;
+                walk_function_literal(self, fl);
+            }
+        }
+
+        let program = reparse("let f = fn(x) { if (x) { g(x) } else { -y } }; \
+            try { throw z } catch (e) { e.w } finally { f(1) }");
+        let mut names = Names::default();
+        names.visit_program(&program);
+        assert_eq!(vec!["x", "f", "e"], names.bindings);
+        assert_eq!(vec!["x", "g", "x", "y", "z", "e", "f"], names.uses);
+        assert_eq!(1, names.functions);
+    }
+
+    #[test]
+    fn mut_visitor_edits_in_place() {
+        use crate::ast::visit::MutVisitor;
+
+        struct Rename;
+
+        impl MutVisitor for Rename {
+            fn visit_identifier_mut(&mut self, i: &mut Identifier) {
+                if i.value == "a" {
+                    i.value = String::from("b");
+                    i.token = Token::IDENT(String::from("b"));
+                }
+            }
+        }
+
+        let mut program = reparse("a; let c = fn(a) { a + 1 }(a);");
+        Rename.visit_program_mut(&mut program);
+        assert_eq!(reparse("b; let c = fn(a) { b + 1 }(b);"), program);
+    }
+
+    #[test]
+    fn folder_replaces_nodes() {
+        use crate::ast::visit::{walk_fold_infix_expression, Folder};
+
+        /// Folds integer arithmetic on literals into a single literal.
+        struct ConstantFolder;
+
+        impl Folder for ConstantFolder {
+            fn fold_infix_expression(&mut self, ie: InfixExpression) -> Expression {
+                let folded = walk_fold_infix_expression(self, ie);
+                if let Expression::InfixExpression(ie) = &folded {
+                    if let (Expression::IntegerLiteral(l), Expression::IntegerLiteral(r)) = (&*ie.left, &*ie.right) {
+                        let value = match ie.operator.as_str() {
+                            "+" => l.value.checked_add(r.value),
+                            "*" => l.value.checked_mul(r.value),
+                            _ => None,
+                        };
+                        if let Some(value) = value {
+                            return Expression::IntegerLiteral(IntegerLiteral {
+                                token: Token::INT(value.to_string()),
+                                value,
+                                position: Position::default(),
+                                end: Position::default(),
+                            });
+                        }
+                    }
+                }
+                return folded;
+            }
+        }
+
+        let program = ConstantFolder.fold_program(reparse("let x = 1 + 2 * 3; (4 + 5) * y; fn() { return 2 * 2 - 1 }"));
+        assert_eq!(reparse("let x = 7; 9 * y; fn() { return 4 - 1 }"), program);
+    }
 }
@@ -0,0 +1,553 @@
+use super::{
+    BlockStatement, BooleanLiteral, CallExpression, Expression, ExpressionStatement, FunctionLiteral, Identifier,
+    IfExpression, ImportStatement, InfixExpression, IntegerLiteral, LetStatement, MemberExpression, PrefixExpression,
+    Program, ReturnStatement, Statement, StringLiteral, ThrowStatement, TryExpression,
+};
+
+// Traversals over the AST. Each trait has a method per node type whose
+// default walks into the node's children, so an implementation only
+// overrides the nodes it cares about and calls the matching `walk_*`
+// function to keep descending.
+//
+// Identifiers that introduce a name (`let` names, function parameters and
+// `catch` parameters) go to `*_binding`; identifiers used as expressions go
+// to `*_identifier`. Member properties are neither.
+
+/// A read-only traversal.
+pub trait Visitor {
+    fn visit_program(&mut self, p: &Program) {
+        walk_program(self, p);
+    }
+
+    fn visit_statement(&mut self, s: &Statement) {
+        walk_statement(self, s);
+    }
+
+    fn visit_let_statement(&mut self, s: &LetStatement) {
+        walk_let_statement(self, s);
+    }
+
+    fn visit_return_statement(&mut self, s: &ReturnStatement) {
+        walk_return_statement(self, s);
+    }
+
+    fn visit_expression_statement(&mut self, s: &ExpressionStatement) {
+        walk_expression_statement(self, s);
+    }
+
+    fn visit_import_statement(&mut self, _s: &ImportStatement) {}
+
+    fn visit_throw_statement(&mut self, s: &ThrowStatement) {
+        walk_throw_statement(self, s);
+    }
+
+    fn visit_block(&mut self, b: &BlockStatement) {
+        walk_block(self, b);
+    }
+
+    fn visit_binding(&mut self, _name: &Identifier) {}
+
+    fn visit_expression(&mut self, e: &Expression) {
+        walk_expression(self, e);
+    }
+
+    fn visit_identifier(&mut self, _i: &Identifier) {}
+
+    fn visit_integer_literal(&mut self, _il: &IntegerLiteral) {}
+
+    fn visit_string_literal(&mut self, _sl: &StringLiteral) {}
+
+    fn visit_boolean(&mut self, _b: &BooleanLiteral) {}
+
+    fn visit_prefix_expression(&mut self, pe: &PrefixExpression) {
+        walk_prefix_expression(self, pe);
+    }
+
+    fn visit_infix_expression(&mut self, ie: &InfixExpression) {
+        walk_infix_expression(self, ie);
+    }
+
+    fn visit_if_expression(&mut self, ie: &IfExpression) {
+        walk_if_expression(self, ie);
+    }
+
+    fn visit_function_literal(&mut self, fl: &FunctionLiteral) {
+        walk_function_literal(self, fl);
+    }
+
+    fn visit_call_expression(&mut self, ce: &CallExpression) {
+        walk_call_expression(self, ce);
+    }
+
+    fn visit_try_expression(&mut self, te: &TryExpression) {
+        walk_try_expression(self, te);
+    }
+
+    fn visit_member_expression(&mut self, me: &MemberExpression) {
+        walk_member_expression(self, me);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(v: &mut V, p: &Program) {
+    p.statements.iter().for_each(|s| v.visit_statement(s));
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(v: &mut V, s: &Statement) {
+    match s {
+        Statement::LetStatement(ls) => v.visit_let_statement(ls),
+        Statement::ReturnStatement(rs) => v.visit_return_statement(rs),
+        Statement::ExpressionStatement(es) => v.visit_expression_statement(es),
+        Statement::ImportStatement(is) => v.visit_import_statement(is),
+        Statement::ThrowStatement(ts) => v.visit_throw_statement(ts),
+    }
+}
+
+/// Visits the value before the name, which is the order they're evaluated in.
+pub fn walk_let_statement<V: Visitor + ?Sized>(v: &mut V, s: &LetStatement) {
+    if let Some(value) = &s.value {
+        v.visit_expression(value);
+    }
+    v.visit_binding(&s.name);
+}
+
+pub fn walk_return_statement<V: Visitor + ?Sized>(v: &mut V, s: &ReturnStatement) {
+    v.visit_expression(&s.return_val);
+}
+
+pub fn walk_expression_statement<V: Visitor + ?Sized>(v: &mut V, s: &ExpressionStatement) {
+    v.visit_expression(&s.expression);
+}
+
+pub fn walk_throw_statement<V: Visitor + ?Sized>(v: &mut V, s: &ThrowStatement) {
+    v.visit_expression(&s.value);
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(v: &mut V, b: &BlockStatement) {
+    b.statements.iter().for_each(|s| v.visit_statement(s));
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(v: &mut V, e: &Expression) {
+    match e {
+        Expression::Empty => (),
+        Expression::Identifier(i) => v.visit_identifier(i),
+        Expression::IntegerLiteral(il) => v.visit_integer_literal(il),
+        Expression::StringLiteral(sl) => v.visit_string_literal(sl),
+        Expression::Boolean(b) => v.visit_boolean(b),
+        Expression::PrefixExpression(pe) => v.visit_prefix_expression(pe),
+        Expression::InfixExpression(ie) => v.visit_infix_expression(ie),
+        Expression::IfExpression(ie) => v.visit_if_expression(ie),
+        Expression::FunctionLiteral(fl) => v.visit_function_literal(fl),
+        Expression::CallExpression(ce) => v.visit_call_expression(ce),
+        Expression::TryExpression(te) => v.visit_try_expression(te),
+        Expression::MemberExpression(me) => v.visit_member_expression(me),
+    }
+}
+
+pub fn walk_prefix_expression<V: Visitor + ?Sized>(v: &mut V, pe: &PrefixExpression) {
+    v.visit_expression(&pe.right);
+}
+
+pub fn walk_infix_expression<V: Visitor + ?Sized>(v: &mut V, ie: &InfixExpression) {
+    v.visit_expression(&ie.left);
+    v.visit_expression(&ie.right);
+}
+
+pub fn walk_if_expression<V: Visitor + ?Sized>(v: &mut V, ie: &IfExpression) {
+    v.visit_expression(&ie.condition);
+    v.visit_block(&ie.consequence);
+    if let Some(alt) = &ie.alternative {
+        v.visit_block(alt);
+    }
+}
+
+pub fn walk_function_literal<V: Visitor + ?Sized>(v: &mut V, fl: &FunctionLiteral) {
+    fl.parameters.iter().for_each(|p| v.visit_binding(p));
+    v.visit_block(&fl.body);
+}
+
+pub fn walk_call_expression<V: Visitor + ?Sized>(v: &mut V, ce: &CallExpression) {
+    v.visit_expression(&ce.function);
+    ce.arguments.iter().for_each(|a| v.visit_expression(a));
+}
+
+pub fn walk_try_expression<V: Visitor + ?Sized>(v: &mut V, te: &TryExpression) {
+    v.visit_block(&te.body);
+    if let Some(param) = &te.parameter {
+        v.visit_binding(param);
+    }
+    if let Some(handler) = &te.handler {
+        v.visit_block(handler);
+    }
+    if let Some(finalizer) = &te.finalizer {
+        v.visit_block(finalizer);
+    }
+}
+
+pub fn walk_member_expression<V: Visitor + ?Sized>(v: &mut V, me: &MemberExpression) {
+    v.visit_expression(&me.object);
+}
+
+/// A traversal that edits nodes in place.
+pub trait MutVisitor {
+    fn visit_program_mut(&mut self, p: &mut Program) {
+        walk_program_mut(self, p);
+    }
+
+    fn visit_statement_mut(&mut self, s: &mut Statement) {
+        walk_statement_mut(self, s);
+    }
+
+    fn visit_let_statement_mut(&mut self, s: &mut LetStatement) {
+        walk_let_statement_mut(self, s);
+    }
+
+    fn visit_return_statement_mut(&mut self, s: &mut ReturnStatement) {
+        walk_return_statement_mut(self, s);
+    }
+
+    fn visit_expression_statement_mut(&mut self, s: &mut ExpressionStatement) {
+        walk_expression_statement_mut(self, s);
+    }
+
+    fn visit_import_statement_mut(&mut self, _s: &mut ImportStatement) {}
+
+    fn visit_throw_statement_mut(&mut self, s: &mut ThrowStatement) {
+        walk_throw_statement_mut(self, s);
+    }
+
+    fn visit_block_mut(&mut self, b: &mut BlockStatement) {
+        walk_block_mut(self, b);
+    }
+
+    fn visit_binding_mut(&mut self, _name: &mut Identifier) {}
+
+    fn visit_expression_mut(&mut self, e: &mut Expression) {
+        walk_expression_mut(self, e);
+    }
+
+    fn visit_identifier_mut(&mut self, _i: &mut Identifier) {}
+
+    fn visit_integer_literal_mut(&mut self, _il: &mut IntegerLiteral) {}
+
+    fn visit_string_literal_mut(&mut self, _sl: &mut StringLiteral) {}
+
+    fn visit_boolean_mut(&mut self, _b: &mut BooleanLiteral) {}
+
+    fn visit_prefix_expression_mut(&mut self, pe: &mut PrefixExpression) {
+        walk_prefix_expression_mut(self, pe);
+    }
+
+    fn visit_infix_expression_mut(&mut self, ie: &mut InfixExpression) {
+        walk_infix_expression_mut(self, ie);
+    }
+
+    fn visit_if_expression_mut(&mut self, ie: &mut IfExpression) {
+        walk_if_expression_mut(self, ie);
+    }
+
+    fn visit_function_literal_mut(&mut self, fl: &mut FunctionLiteral) {
+        walk_function_literal_mut(self, fl);
+    }
+
+    fn visit_call_expression_mut(&mut self, ce: &mut CallExpression) {
+        walk_call_expression_mut(self, ce);
+    }
+
+    fn visit_try_expression_mut(&mut self, te: &mut TryExpression) {
+        walk_try_expression_mut(self, te);
+    }
+
+    fn visit_member_expression_mut(&mut self, me: &mut MemberExpression) {
+        walk_member_expression_mut(self, me);
+    }
+}
+
+pub fn walk_program_mut<V: MutVisitor + ?Sized>(v: &mut V, p: &mut Program) {
+    p.statements.iter_mut().for_each(|s| v.visit_statement_mut(s));
+}
+
+pub fn walk_statement_mut<V: MutVisitor + ?Sized>(v: &mut V, s: &mut Statement) {
+    match s {
+        Statement::LetStatement(ls) => v.visit_let_statement_mut(ls),
+        Statement::ReturnStatement(rs) => v.visit_return_statement_mut(rs),
+        Statement::ExpressionStatement(es) => v.visit_expression_statement_mut(es),
+        Statement::ImportStatement(is) => v.visit_import_statement_mut(is),
+        Statement::ThrowStatement(ts) => v.visit_throw_statement_mut(ts),
+    }
+}
+
+pub fn walk_let_statement_mut<V: MutVisitor + ?Sized>(v: &mut V, s: &mut LetStatement) {
+    if let Some(value) = &mut s.value {
+        v.visit_expression_mut(value);
+    }
+    v.visit_binding_mut(&mut s.name);
+}
+
+pub fn walk_return_statement_mut<V: MutVisitor + ?Sized>(v: &mut V, s: &mut ReturnStatement) {
+    v.visit_expression_mut(&mut s.return_val);
+}
+
+/// Keeps the statement's token in step with an expression that was edited.
+pub fn walk_expression_statement_mut<V: MutVisitor + ?Sized>(v: &mut V, s: &mut ExpressionStatement) {
+    v.visit_expression_mut(&mut s.expression);
+    s.token = s.expression.first_token();
+}
+
+pub fn walk_throw_statement_mut<V: MutVisitor + ?Sized>(v: &mut V, s: &mut ThrowStatement) {
+    v.visit_expression_mut(&mut s.value);
+}
+
+pub fn walk_block_mut<V: MutVisitor + ?Sized>(v: &mut V, b: &mut BlockStatement) {
+    b.statements.iter_mut().for_each(|s| v.visit_statement_mut(s));
+}
+
+pub fn walk_expression_mut<V: MutVisitor + ?Sized>(v: &mut V, e: &mut Expression) {
+    match e {
+        Expression::Empty => (),
+        Expression::Identifier(i) => v.visit_identifier_mut(i),
+        Expression::IntegerLiteral(il) => v.visit_integer_literal_mut(il),
+        Expression::StringLiteral(sl) => v.visit_string_literal_mut(sl),
+        Expression::Boolean(b) => v.visit_boolean_mut(b),
+        Expression::PrefixExpression(pe) => v.visit_prefix_expression_mut(pe),
+        Expression::InfixExpression(ie) => v.visit_infix_expression_mut(ie),
+        Expression::IfExpression(ie) => v.visit_if_expression_mut(ie),
+        Expression::FunctionLiteral(fl) => v.visit_function_literal_mut(fl),
+        Expression::CallExpression(ce) => v.visit_call_expression_mut(ce),
+        Expression::TryExpression(te) => v.visit_try_expression_mut(te),
+        Expression::MemberExpression(me) => v.visit_member_expression_mut(me),
+    }
+}
+
+pub fn walk_prefix_expression_mut<V: MutVisitor + ?Sized>(v: &mut V, pe: &mut PrefixExpression) {
+    v.visit_expression_mut(&mut pe.right);
+}
+
+pub fn walk_infix_expression_mut<V: MutVisitor + ?Sized>(v: &mut V, ie: &mut InfixExpression) {
+    v.visit_expression_mut(&mut ie.left);
+    v.visit_expression_mut(&mut ie.right);
+}
+
+pub fn walk_if_expression_mut<V: MutVisitor + ?Sized>(v: &mut V, ie: &mut IfExpression) {
+    v.visit_expression_mut(&mut ie.condition);
+    v.visit_block_mut(&mut ie.consequence);
+    if let Some(alt) = &mut ie.alternative {
+        v.visit_block_mut(alt);
+    }
+}
+
+pub fn walk_function_literal_mut<V: MutVisitor + ?Sized>(v: &mut V, fl: &mut FunctionLiteral) {
+    fl.parameters.iter_mut().for_each(|p| v.visit_binding_mut(p));
+    v.visit_block_mut(&mut fl.body);
+}
+
+pub fn walk_call_expression_mut<V: MutVisitor + ?Sized>(v: &mut V, ce: &mut CallExpression) {
+    v.visit_expression_mut(&mut ce.function);
+    ce.arguments.iter_mut().for_each(|a| v.visit_expression_mut(a));
+}
+
+pub fn walk_try_expression_mut<V: MutVisitor + ?Sized>(v: &mut V, te: &mut TryExpression) {
+    v.visit_block_mut(&mut te.body);
+    if let Some(param) = &mut te.parameter {
+        v.visit_binding_mut(param);
+    }
+    if let Some(handler) = &mut te.handler {
+        v.visit_block_mut(handler);
+    }
+    if let Some(finalizer) = &mut te.finalizer {
+        v.visit_block_mut(finalizer);
+    }
+}
+
+pub fn walk_member_expression_mut<V: MutVisitor + ?Sized>(v: &mut V, me: &mut MemberExpression) {
+    v.visit_expression_mut(&mut me.object);
+}
+
+/// A traversal that takes each node and returns its replacement. Statements
+/// and expressions may be replaced by a different kind of statement or
+/// expression. The `walk_fold_*` functions fold a node's children, so an
+/// override that calls one first gets the node with its children folded.
+pub trait Folder {
+    fn fold_program(&mut self, p: Program) -> Program {
+        return walk_fold_program(self, p);
+    }
+
+    fn fold_statement(&mut self, s: Statement) -> Statement {
+        return walk_fold_statement(self, s);
+    }
+
+    fn fold_let_statement(&mut self, s: LetStatement) -> Statement {
+        return walk_fold_let_statement(self, s);
+    }
+
+    fn fold_return_statement(&mut self, s: ReturnStatement) -> Statement {
+        return walk_fold_return_statement(self, s);
+    }
+
+    fn fold_expression_statement(&mut self, s: ExpressionStatement) -> Statement {
+        return walk_fold_expression_statement(self, s);
+    }
+
+    fn fold_import_statement(&mut self, s: ImportStatement) -> Statement {
+        return Statement::ImportStatement(s);
+    }
+
+    fn fold_throw_statement(&mut self, s: ThrowStatement) -> Statement {
+        return walk_fold_throw_statement(self, s);
+    }
+
+    fn fold_block(&mut self, b: BlockStatement) -> BlockStatement {
+        return walk_fold_block(self, b);
+    }
+
+    fn fold_binding(&mut self, name: Identifier) -> Identifier {
+        return name;
+    }
+
+    fn fold_expression(&mut self, e: Expression) -> Expression {
+        return walk_fold_expression(self, e);
+    }
+
+    fn fold_identifier(&mut self, i: Identifier) -> Expression {
+        return Expression::Identifier(i);
+    }
+
+    fn fold_integer_literal(&mut self, il: IntegerLiteral) -> Expression {
+        return Expression::IntegerLiteral(il);
+    }
+
+    fn fold_string_literal(&mut self, sl: StringLiteral) -> Expression {
+        return Expression::StringLiteral(sl);
+    }
+
+    fn fold_boolean(&mut self, b: BooleanLiteral) -> Expression {
+        return Expression::Boolean(b);
+    }
+
+    fn fold_prefix_expression(&mut self, pe: PrefixExpression) -> Expression {
+        return walk_fold_prefix_expression(self, pe);
+    }
+
+    fn fold_infix_expression(&mut self, ie: InfixExpression) -> Expression {
+        return walk_fold_infix_expression(self, ie);
+    }
+
+    fn fold_if_expression(&mut self, ie: IfExpression) -> Expression {
+        return walk_fold_if_expression(self, ie);
+    }
+
+    fn fold_function_literal(&mut self, fl: FunctionLiteral) -> Expression {
+        return walk_fold_function_literal(self, fl);
+    }
+
+    fn fold_call_expression(&mut self, ce: CallExpression) -> Expression {
+        return walk_fold_call_expression(self, ce);
+    }
+
+    fn fold_try_expression(&mut self, te: TryExpression) -> Expression {
+        return walk_fold_try_expression(self, te);
+    }
+
+    fn fold_member_expression(&mut self, me: MemberExpression) -> Expression {
+        return walk_fold_member_expression(self, me);
+    }
+}
+
+pub fn walk_fold_program<F: Folder + ?Sized>(f: &mut F, p: Program) -> Program {
+    return Program { statements: p.statements.into_iter().map(|s| f.fold_statement(s)).collect() };
+}
+
+pub fn walk_fold_statement<F: Folder + ?Sized>(f: &mut F, s: Statement) -> Statement {
+    return match s {
+        Statement::LetStatement(ls) => f.fold_let_statement(ls),
+        Statement::ReturnStatement(rs) => f.fold_return_statement(rs),
+        Statement::ExpressionStatement(es) => f.fold_expression_statement(es),
+        Statement::ImportStatement(is) => f.fold_import_statement(is),
+        Statement::ThrowStatement(ts) => f.fold_throw_statement(ts),
+    };
+}
+
+pub fn walk_fold_let_statement<F: Folder + ?Sized>(f: &mut F, s: LetStatement) -> Statement {
+    let value = s.value.map(|v| f.fold_expression(v));
+    let name = f.fold_binding(s.name);
+    return Statement::LetStatement(LetStatement { name, value, ..s });
+}
+
+pub fn walk_fold_return_statement<F: Folder + ?Sized>(f: &mut F, s: ReturnStatement) -> Statement {
+    let return_val = f.fold_expression(s.return_val);
+    return Statement::ReturnStatement(ReturnStatement { return_val, ..s });
+}
+
+pub fn walk_fold_expression_statement<F: Folder + ?Sized>(f: &mut F, s: ExpressionStatement) -> Statement {
+    let expression = f.fold_expression(s.expression);
+    return Statement::ExpressionStatement(ExpressionStatement { token: expression.first_token(), expression, ..s });
+}
+
+pub fn walk_fold_throw_statement<F: Folder + ?Sized>(f: &mut F, s: ThrowStatement) -> Statement {
+    let value = f.fold_expression(s.value);
+    return Statement::ThrowStatement(ThrowStatement { value, ..s });
+}
+
+pub fn walk_fold_block<F: Folder + ?Sized>(f: &mut F, b: BlockStatement) -> BlockStatement {
+    let statements = b.statements.into_iter().map(|s| f.fold_statement(s)).collect();
+    return BlockStatement { statements, ..b };
+}
+
+pub fn walk_fold_expression<F: Folder + ?Sized>(f: &mut F, e: Expression) -> Expression {
+    return match e {
+        Expression::Empty => Expression::Empty,
+        Expression::Identifier(i) => f.fold_identifier(i),
+        Expression::IntegerLiteral(il) => f.fold_integer_literal(il),
+        Expression::StringLiteral(sl) => f.fold_string_literal(sl),
+        Expression::Boolean(b) => f.fold_boolean(b),
+        Expression::PrefixExpression(pe) => f.fold_prefix_expression(pe),
+        Expression::InfixExpression(ie) => f.fold_infix_expression(ie),
+        Expression::IfExpression(ie) => f.fold_if_expression(ie),
+        Expression::FunctionLiteral(fl) => f.fold_function_literal(fl),
+        Expression::CallExpression(ce) => f.fold_call_expression(ce),
+        Expression::TryExpression(te) => f.fold_try_expression(te),
+        Expression::MemberExpression(me) => f.fold_member_expression(me),
+    };
+}
+
+pub fn walk_fold_prefix_expression<F: Folder + ?Sized>(f: &mut F, pe: PrefixExpression) -> Expression {
+    let right = Box::new(f.fold_expression(*pe.right));
+    return Expression::PrefixExpression(PrefixExpression { right, ..pe });
+}
+
+pub fn walk_fold_infix_expression<F: Folder + ?Sized>(f: &mut F, ie: InfixExpression) -> Expression {
+    let left = Box::new(f.fold_expression(*ie.left));
+    let right = Box::new(f.fold_expression(*ie.right));
+    return Expression::InfixExpression(InfixExpression { left, right, ..ie });
+}
+
+pub fn walk_fold_if_expression<F: Folder + ?Sized>(f: &mut F, ie: IfExpression) -> Expression {
+    let condition = Box::new(f.fold_expression(*ie.condition));
+    let consequence = f.fold_block(ie.consequence);
+    let alternative = ie.alternative.map(|alt| f.fold_block(alt));
+    return Expression::IfExpression(IfExpression { condition, consequence, alternative, ..ie });
+}
+
+pub fn walk_fold_function_literal<F: Folder + ?Sized>(f: &mut F, fl: FunctionLiteral) -> Expression {
+    let parameters = fl.parameters.into_iter().map(|p| f.fold_binding(p)).collect();
+    let body = f.fold_block(fl.body);
+    return Expression::FunctionLiteral(FunctionLiteral { parameters, body, ..fl });
+}
+
+pub fn walk_fold_call_expression<F: Folder + ?Sized>(f: &mut F, ce: CallExpression) -> Expression {
+    let function = Box::new(f.fold_expression(*ce.function));
+    let arguments = ce.arguments.into_iter().map(|a| f.fold_expression(a)).collect();
+    return Expression::CallExpression(CallExpression { function, arguments, ..ce });
+}
+
+pub fn walk_fold_try_expression<F: Folder + ?Sized>(f: &mut F, te: TryExpression) -> Expression {
+    let body = f.fold_block(te.body);
+    let parameter = te.parameter.map(|p| f.fold_binding(p));
+    let handler = te.handler.map(|h| f.fold_block(h));
+    let finalizer = te.finalizer.map(|fin| f.fold_block(fin));
+    return Expression::TryExpression(TryExpression { body, parameter, handler, finalizer, ..te });
+}
+
+pub fn walk_fold_member_expression<F: Folder + ?Sized>(f: &mut F, me: MemberExpression) -> Expression {
+    let object = Box::new(f.fold_expression(*me.object));
+    return Expression::MemberExpression(MemberExpression { object, ..me });
+}
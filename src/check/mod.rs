@@ -0,0 +1,430 @@
+use std::io::Write;
+use crate::ast::visit::{self, Visitor};
+use crate::ast::{
+    BlockStatement, CallExpression, Expression, FunctionLiteral, Identifier, ImportStatement, LetStatement, Program,
+    Statement, TryExpression,
+};
+use crate::evaluator::{StdFunction, STD_FUNCTIONS};
+use crate::json::Json;
+use crate::lexer::Lexer;
+use crate::parser::{with_nesting_stack, Parser};
+use crate::testing;
+use crate::token::Position;
+
+#[cfg(test)]
+mod tests;
+
+// A static linter. Scopes follow the evaluator: calls and catch handlers get
+// their own environment, while `if` and `try` blocks share the one they're
+// in. Imports only apply to the environment they're made in.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// A short name for the kind of problem, like `unused-binding`.
+    pub code: &'static str,
+    pub message: String,
+    pub position: Position,
+}
+
+impl Diagnostic {
+    fn error(code: &'static str, message: String, position: Position) -> Self {
+        return Self { severity: Severity::Error, code, message, position };
+    }
+
+    fn warning(code: &'static str, message: String, position: Position) -> Self {
+        return Self { severity: Severity::Warning, code, message, position };
+    }
+
+    pub fn to_json(&self) -> Json {
+        return Json::object(vec![
+            ("severity", Json::str(self.severity.name())),
+            ("code", Json::str(self.code)),
+            ("message", Json::str(&self.message)),
+            ("line", Json::Number(self.position.line as f64)),
+            ("column", Json::Number(self.position.column as f64)),
+        ]);
+    }
+}
+
+/// A name in the source, either where it's bound or where it's used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub name: String,
+    pub position: Position,
+    /// Where the name was bound, if it refers to a binding in the program.
+    pub definition: Option<Position>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScopeKind {
+    Global,
+    Function,
+    Catch,
+}
+
+struct Binding {
+    name: String,
+    position: Position,
+    /// Where evaluation has bound the name: just after its `let` statement,
+    /// or the start of the scope for parameters.
+    visible_from: Position,
+    parameter: bool,
+    /// How many parameters it takes, if it's bound to a function literal.
+    arity: Option<usize>,
+    uses: usize,
+}
+
+struct Scope {
+    kind: ScopeKind,
+    bindings: Vec<Binding>,
+    imports: Vec<(String, Position)>,
+}
+
+/// What an identifier refers to.
+enum Resolved {
+    /// A binding, as the index of its scope and its index in that scope.
+    Binding(usize, usize),
+    Std(&'static StdFunction),
+}
+
+/// Collects the `let` statements that bind names in a scope, which includes
+/// those in nested `if` and `try` blocks but not in functions or handlers.
+struct Lets<'a> {
+    lets: Vec<&'a LetStatement>,
+}
+
+impl<'a> Lets<'a> {
+    fn collect(statements: &'a [Statement]) -> Vec<&'a LetStatement> {
+        let mut lets = Lets { lets: Vec::new() };
+        statements.iter().for_each(|s| lets.statement(s));
+        return lets.lets;
+    }
+
+    fn statement(&mut self, s: &'a Statement) {
+        if let Statement::LetStatement(ls) = s {
+            self.lets.push(ls);
+        }
+        let expressions = match s {
+            Statement::LetStatement(ls) => ls.value.iter().collect(),
+            Statement::ReturnStatement(rs) => vec![&rs.return_val],
+            Statement::ExpressionStatement(es) => vec![&es.expression],
+            Statement::ThrowStatement(ts) => vec![&ts.value],
+            Statement::ImportStatement(_) => vec![],
+        };
+        expressions.into_iter().for_each(|e| self.expression(e));
+    }
+
+    fn block(&mut self, b: &'a BlockStatement) {
+        b.statements.iter().for_each(|s| self.statement(s));
+    }
+
+    fn expression(&mut self, e: &'a Expression) {
+        match e {
+            Expression::PrefixExpression(pe) => self.expression(&pe.right),
+            Expression::InfixExpression(ie) => {
+                self.expression(&ie.left);
+                self.expression(&ie.right);
+            },
+            Expression::IfExpression(ie) => {
+                self.expression(&ie.condition);
+                self.block(&ie.consequence);
+                ie.alternative.iter().for_each(|alt| self.block(alt));
+            },
+            Expression::CallExpression(ce) => {
+                self.expression(&ce.function);
+                ce.arguments.iter().for_each(|a| self.expression(a));
+            },
+            Expression::TryExpression(te) => {
+                self.block(&te.body);
+                te.finalizer.iter().for_each(|f| self.block(f));
+            },
+            Expression::MemberExpression(me) => self.expression(&me.object),
+            _ => (),
+        }
+    }
+}
+
+struct Checker {
+    scopes: Vec<Scope>,
+    diagnostics: Vec<Diagnostic>,
+    references: Vec<Reference>,
+    /// Where the statement being checked starts, for problems with
+    /// expressions, which don't have positions of their own.
+    statement: Position,
+}
+
+impl Checker {
+    fn resolve(&self, name: &str, position: Position) -> Option<Resolved> {
+        // code in a function body runs when it's called, after everything
+        // in the scopes around it has been bound
+        let mut deferred = false;
+        for (i, scope) in self.scopes.iter().enumerate().rev() {
+            let found = scope.bindings.iter().rposition(|b| b.name == name && (deferred || b.visible_from <= position));
+            if let Some(j) = found {
+                return Some(Resolved::Binding(i, j));
+            }
+            if i == self.scopes.len() - 1 {
+                let imported = |f: &&StdFunction| scope.imports.iter().any(|(ns, p)| ns == f.namespace && *p < position);
+                if let Some(f) = STD_FUNCTIONS.iter().filter(imported).find(|f| f.name == name) {
+                    return Some(Resolved::Std(f));
+                }
+            }
+            deferred = deferred || scope.kind == ScopeKind::Function;
+        }
+        return None;
+    }
+
+    /// Whether `name` is bound in any scope around the current one.
+    fn bound_outside(&self, name: &str) -> bool {
+        let outer = &self.scopes[..self.scopes.len() - 1];
+        return outer.iter().any(|s| s.bindings.iter().any(|b| b.name == name));
+    }
+
+    fn bind(&mut self, name: &Identifier, visible_from: Position, parameter: bool, arity: Option<usize>) {
+        if self.bound_outside(&name.value) {
+            self.diagnostics.push(Diagnostic::warning(
+                "shadowed-name",
+                format!("{} shadows a binding in an outer scope", name.value),
+                name.position,
+            ));
+        }
+        self.references.push(Reference { name: name.value.clone(), position: name.position, definition: Some(name.position) });
+        self.scopes.last_mut().unwrap().bindings.push(Binding {
+            name: name.value.clone(),
+            position: name.position,
+            visible_from,
+            parameter,
+            arity,
+            uses: 0,
+        });
+    }
+
+    /// Starts a scope, binding the `let`s in `statements` up front so that
+    /// functions can refer to names bound after them.
+    fn push_scope(&mut self, kind: ScopeKind, statements: &[Statement]) {
+        self.scopes.push(Scope { kind, bindings: Vec::new(), imports: Vec::new() });
+        for ls in Lets::collect(statements) {
+            let arity = match &ls.value {
+                Some(Expression::FunctionLiteral(fl)) => Some(fl.parameters.len()),
+                _ => None,
+            };
+            self.bind(&ls.name, ls.end, false, arity);
+        }
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        // top-level tests are used by the test runner
+        let test = |b: &Binding| scope.kind == ScopeKind::Global && b.name.starts_with(testing::PREFIX);
+        for b in scope.bindings.iter().filter(|b| b.uses == 0 && !b.name.starts_with('_') && !test(b)) {
+            let what = if b.parameter { "parameter" } else { "variable" };
+            self.diagnostics.push(Diagnostic::warning("unused-binding", format!("unused {} {}", what, b.name), b.position));
+        }
+    }
+
+    /// Warns about the first statement after a `return` or `throw`.
+    fn check_reachable(&mut self, statements: &[Statement]) {
+        let exit = statements.iter().position(|s| matches!(s, Statement::ReturnStatement(_) | Statement::ThrowStatement(_)));
+        if let Some(next) = exit.and_then(|i| statements.get(i + 1)) {
+            self.diagnostics.push(Diagnostic::warning("unreachable-code", String::from("unreachable statement"), next.position()));
+        }
+    }
+
+    fn check_arity(&mut self, name: &str, expected: usize, got: usize, position: Position) {
+        if expected != got {
+            self.diagnostics.push(Diagnostic::error(
+                "wrong-arity",
+                format!("{} takes {} argument{}, but is called with {}", name, expected, if expected == 1 { "" } else { "s" }, got),
+                position,
+            ));
+        }
+    }
+}
+
+impl Visitor for Checker {
+    fn visit_statement(&mut self, s: &Statement) {
+        self.statement = s.position();
+        visit::walk_statement(self, s);
+    }
+
+    /// The name was bound when the scope was started.
+    fn visit_let_statement(&mut self, s: &LetStatement) {
+        if let Some(value) = &s.value {
+            self.visit_expression(value);
+        }
+    }
+
+    fn visit_import_statement(&mut self, s: &ImportStatement) {
+        self.scopes.last_mut().unwrap().imports.push((s.namespace.value.clone(), s.position));
+    }
+
+    fn visit_block(&mut self, b: &BlockStatement) {
+        self.check_reachable(&b.statements);
+        visit::walk_block(self, b);
+    }
+
+    fn visit_binding(&mut self, name: &Identifier) {
+        self.bind(name, Position::default(), true, None);
+    }
+
+    fn visit_identifier(&mut self, i: &Identifier) {
+        let resolved = self.resolve(&i.value, i.position);
+        let definition = match resolved {
+            Some(Resolved::Binding(scope, j)) => Some(self.scopes[scope].bindings[j].position),
+            _ => None,
+        };
+        self.references.push(Reference { name: i.value.clone(), position: i.position, definition });
+        match resolved {
+            Some(Resolved::Binding(scope, j)) => self.scopes[scope].bindings[j].uses += 1,
+            Some(Resolved::Std(_)) => (),
+            None => match STD_FUNCTIONS.iter().find(|f| f.name == i.value) {
+                Some(f) => self.diagnostics.push(Diagnostic::error(
+                    "missing-import",
+                    format!("{} is in {}, which isn't imported in this scope", f.name, f.namespace),
+                    i.position,
+                )),
+                None => self.diagnostics.push(Diagnostic::error(
+                    "unknown-identifier",
+                    format!("unknown identifier {}", i.value),
+                    i.position,
+                )),
+            },
+        }
+    }
+
+    fn visit_function_literal(&mut self, fl: &FunctionLiteral) {
+        let statement = self.statement;
+        self.push_scope(ScopeKind::Function, &fl.body.statements);
+        visit::walk_function_literal(self, fl);
+        self.pop_scope();
+        self.statement = statement;
+    }
+
+    fn visit_call_expression(&mut self, ce: &CallExpression) {
+        let got = ce.arguments.len();
+        match &*ce.function {
+            Expression::Identifier(i) => match self.resolve(&i.value, i.position) {
+                Some(Resolved::Binding(scope, j)) => {
+                    if let Some(arity) = self.scopes[scope].bindings[j].arity {
+                        self.check_arity(&i.value, arity, got, i.position);
+                    }
+                },
+                Some(Resolved::Std(f)) => self.check_arity(f.name, f.arity, got, i.position),
+                None => (),
+            },
+            Expression::FunctionLiteral(fl) => self.check_arity("function", fl.parameters.len(), got, self.statement),
+            _ => (),
+        }
+        visit::walk_call_expression(self, ce);
+    }
+
+    fn visit_try_expression(&mut self, te: &TryExpression) {
+        self.visit_block(&te.body);
+        if let Some(handler) = &te.handler {
+            let statement = self.statement;
+            self.push_scope(ScopeKind::Catch, &handler.statements);
+            if let Some(param) = &te.parameter {
+                self.visit_binding(param);
+            }
+            self.visit_block(handler);
+            self.pop_scope();
+            self.statement = statement;
+        }
+        if let Some(finalizer) = &te.finalizer {
+            self.visit_block(finalizer);
+        }
+    }
+}
+
+fn run(program: &Program) -> Checker {
+    let mut c = Checker { scopes: Vec::new(), diagnostics: Vec::new(), references: Vec::new(), statement: Position::default() };
+    c.push_scope(ScopeKind::Global, &program.statements);
+    c.check_reachable(&program.statements);
+    c.visit_program(program);
+    c.pop_scope();
+    return c;
+}
+
+/// Every problem found in `program`, in source order.
+pub fn check_program(program: &Program) -> Vec<Diagnostic> {
+    let mut diagnostics = run(program).diagnostics;
+    diagnostics.sort_by_key(|d| d.position);
+    return diagnostics;
+}
+
+/// Every identifier in `program` apart from member properties, in source
+/// order, with the binding each one refers to.
+pub fn references(program: &Program) -> Vec<Reference> {
+    let mut references = run(program).references;
+    references.sort_by_key(|r| r.position);
+    return references;
+}
+
+/// The parse errors in `source`, or if there are none, the problems the
+/// linter finds.
+pub fn check_source(source: &str) -> Vec<Diagnostic> {
+    return with_nesting_stack(|| check_text(source))
+        .unwrap_or_else(|e| vec![Diagnostic::error("syntax", e, Position::default())]);
+}
+
+fn check_text(source: &str) -> Vec<Diagnostic> {
+    let mut p = Parser::new(Lexer::new(source));
+    let program = p.parse_program();
+    if !p.errors.is_empty() {
+        return p.errors.iter().map(|e| Diagnostic::error("syntax", e.message.clone(), e.position)).collect();
+    }
+    return match program {
+        Some(program) => check_program(&program),
+        None => vec![],
+    };
+}
+
+/// Checks each file, writing what's found to `out` as `path:line:column:`
+/// lines or, with `json`, as one JSON array. Returns whether there were no
+/// errors; warnings alone don't fail.
+pub fn check_files(paths: &[&str], json: bool, out: &mut dyn Write) -> std::io::Result<bool> {
+    let mut ok = true;
+    let mut results: Vec<Json> = Vec::new();
+    for path in paths {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("error reading {}: {}", path, e);
+                ok = false;
+                continue;
+            },
+        };
+        for d in check_source(&source) {
+            ok = ok && d.severity != Severity::Error;
+            if json {
+                let mut entry = vec![(String::from("file"), Json::str(path))];
+                if let Json::Object(members) = d.to_json() {
+                    entry.extend(members);
+                }
+                results.push(Json::Object(entry));
+            } else {
+                writeln!(out, "{}:{}:{}: {}: {} [{}]", path, d.position.line, d.position.column, d.severity.name(), d.message, d.code)?;
+            }
+        }
+    }
+    if json {
+        writeln!(out, "{}", Json::Array(results).pretty())?;
+    }
+    return Ok(ok);
+}
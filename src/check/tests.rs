@@ -0,0 +1,111 @@
+#[cfg(test)]
+use crate::check::{check_source, Diagnostic};
+
+/// The code, line and column of each diagnostic.
+#[cfg(test)]
+fn found(source: &str) -> Vec<(&'static str, usize, usize)> {
+    return check_source(source).iter().map(|d: &Diagnostic| (d.code, d.position.line, d.position.column)).collect();
+}
+
+#[test]
+fn check_clean_program() {
+    let input = "import \"std.string\";
+let len = fn(s) { strlen(s) + later() };
+let later = fn() { 1 };
+let r = try { throw \"x\" } catch (e) { e.message } finally { 0 };
+let _ignored = 2;
+len(r)";
+    // `strlen` isn't imported inside the function, just as when it runs
+    assert_eq!(vec![("missing-import", 2, 19)], found(input));
+    assert_eq!(Vec::<(&str, usize, usize)>::new(), found("let f = fn(s) { import \"std.string\"; strlen(s) }; f(\"a\")"));
+}
+
+#[test]
+fn check_names() {
+    assert_eq!(vec![("unknown-identifier", 1, 1)], found("x"));
+    // a name is bound only once its `let` has run
+    assert_eq!(vec![("unknown-identifier", 1, 9)], found("let x = x + 1; x"));
+    assert_eq!(vec![("unknown-identifier", 1, 1)], found("f(); let f = fn() { 1 }; f()"));
+    // `if` blocks bind into the scope around them
+    assert_eq!(Vec::<(&str, usize, usize)>::new(), found("if (true) { let y = 1 }; y"));
+    assert_eq!(vec![("missing-import", 1, 1), ("unknown-identifier", 1, 8)], found("strlen(e); try { 1 } catch (e) { e }"));
+}
+
+#[test]
+fn check_unused_and_shadowed() {
+    let input = "let x = 1;
+let f = fn(x, y) {
+    let z = y;
+    0
+};
+f(1, 2)";
+    assert_eq!(vec![
+        ("unused-binding", 1, 5),
+        ("shadowed-name", 2, 12),
+        ("unused-binding", 2, 12),
+        ("unused-binding", 3, 9),
+    ], found(input));
+}
+
+#[test]
+fn check_unreachable() {
+    let input = "let f = fn() {
+    return 1;
+    2;
+    3
+};
+f();
+throw \"done\";
+f()";
+    assert_eq!(vec![("unreachable-code", 3, 5), ("unreachable-code", 8, 1)], found(input));
+}
+
+#[test]
+fn check_arity() {
+    let input = "import \"std.string\";
+let f = fn(a, b) { a + b };
+f(1);
+strlen();
+fn(a) { a }(1, 2);
+f(1, 2) + strlen(\"a\")";
+    assert_eq!(vec![("wrong-arity", 3, 1), ("wrong-arity", 4, 1), ("wrong-arity", 5, 1)], found(input));
+}
+
+#[test]
+fn check_reports_parse_errors() {
+    use crate::check::Severity;
+
+    let diagnostics = check_source("let x = 1;\nlet = 1;");
+    assert!(!diagnostics.is_empty());
+    assert_eq!("syntax", diagnostics[0].code);
+    assert_eq!(Severity::Error, diagnostics[0].severity);
+    assert_eq!(2, diagnostics[0].position.line);
+}
+
+#[test]
+fn check_files_output() {
+    use crate::check::check_files;
+    use crate::json::{parse, Json};
+
+    let path = std::env::temp_dir().join(format!("waiig_check_{}.mk", std::process::id()));
+    std::fs::write(&path, "let x = 1;\ny").unwrap();
+    let path = path.to_str().unwrap();
+
+    let mut out: Vec<u8> = Vec::new();
+    assert!(!check_files(&[path], false, &mut out).unwrap());
+    let expected = format!("{0}:1:5: warning: unused variable x [unused-binding]\n{0}:2:1: error: unknown identifier y [unknown-identifier]\n", path);
+    assert_eq!(expected, String::from_utf8(out).unwrap());
+
+    let mut out: Vec<u8> = Vec::new();
+    assert!(!check_files(&[path], true, &mut out).unwrap());
+    let json = parse(&String::from_utf8(out).unwrap()).unwrap();
+    let entries = json.as_array().unwrap();
+    assert_eq!(2, entries.len());
+    assert_eq!(Some(path), entries[1].get("file").and_then(Json::as_str));
+    assert_eq!(Some("error"), entries[1].get("severity").and_then(Json::as_str));
+    assert_eq!(Some(2), entries[1].get("line").and_then(Json::as_i64));
+
+    std::fs::write(path, "let x = 1;\nx").unwrap();
+    assert!(check_files(&[path], false, &mut Vec::new()).unwrap());
+    std::fs::remove_file(path).unwrap();
+}
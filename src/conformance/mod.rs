@@ -0,0 +1,124 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use crate::ast::Node;
+use crate::dump::program_sexp;
+use crate::evaluator::eval;
+use crate::lexer::Lexer;
+use crate::object::{Environment, Object};
+use crate::parser::{with_nesting_stack, Parser};
+use crate::run::{run_file, Options};
+
+#[cfg(test)]
+mod tests;
+
+/// Where the golden files live, relative to the crate root.
+pub const DIR: &str = "tests/conformance";
+
+/// What a golden file says should happen to it, read from `// key: value`
+/// comments. Repeating a key adds a line to its value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Expectations {
+    /// `// ast:` each line of the syntax tree as `waiig_rs ast --format sexp`
+    /// prints it.
+    pub ast: Option<String>,
+    /// `// parse error:` each parse error, as `message (line l, column c)`.
+    pub parse_error: Option<String>,
+    /// `// result:` the `inspect()` of the program's value.
+    pub result: Option<String>,
+    /// `// error:` the runtime error the program fails with, as the REPL
+    /// prints it.
+    pub error: Option<String>,
+    /// `// stdout:` each line `waiig_rs run` prints.
+    pub stdout: Option<String>,
+}
+
+impl Expectations {
+    /// The expectations in `source`. Comments that don't start with a known
+    /// key are left alone.
+    pub fn parse(source: &str) -> Self {
+        let mut expected = Self::default();
+        for line in source.lines() {
+            let Some(comment) = line.trim_start().strip_prefix("//") else { continue };
+            let Some((key, value)) = comment.trim_start().split_once(':') else { continue };
+            let field = match key {
+                "ast" => &mut expected.ast,
+                "parse error" => &mut expected.parse_error,
+                "result" => &mut expected.result,
+                "error" => &mut expected.error,
+                "stdout" => &mut expected.stdout,
+                _ => continue,
+            };
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                Some(lines) => {
+                    lines.push('\n');
+                    lines.push_str(value);
+                },
+                None => *field = Some(value.to_string()),
+            }
+        }
+        return expected;
+    }
+}
+
+fn outcome(failures: Vec<String>) -> Result<(), String> {
+    return if failures.is_empty() { Ok(()) } else { Err(failures.join("\n")) };
+}
+
+fn compare(failures: &mut Vec<String>, key: &str, expected: &str, actual: &str) {
+    if expected != actual {
+        failures.push(format!("{} differs\n  expected: {:?}\n    actual: {:?}", key, expected, actual));
+    }
+}
+
+/// Runs the golden file at `path` through the lexer, parser and evaluator,
+/// returning how it differs from its expectations. A file must expect
+/// something, and parse and runtime errors it doesn't expect are failures.
+/// A file that only expects things of its syntax tree isn't run.
+pub fn check_file(path: &Path) -> Result<(), String> {
+    return with_nesting_stack(|| check_golden(path))?;
+}
+
+fn check_golden(path: &Path) -> Result<(), String> {
+    let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let expected = Expectations::parse(&source);
+    if expected == Expectations::default() {
+        return Err(String::from("no expectations"));
+    }
+
+    let mut failures: Vec<String> = Vec::new();
+    let mut p = Parser::new(Lexer::new(&source));
+    let program = p.parse_program();
+    let errors: Vec<String> = p.errors.iter()
+        .map(|e| format!("{} (line {}, column {})", e.message, e.position.line, e.position.column))
+        .collect();
+    compare(&mut failures, "parse error", expected.parse_error.as_deref().unwrap_or(""), &errors.join("\n"));
+    let program = match program {
+        Some(program) if errors.is_empty() => program,
+        _ => return outcome(failures),
+    };
+    if let Some(ast) = &expected.ast {
+        compare(&mut failures, "ast", ast, program_sexp(&program).trim_end());
+    }
+
+    if expected.result.is_none() && expected.error.is_none() && expected.stdout.is_none() {
+        return outcome(failures);
+    }
+
+    let env = Arc::new(Mutex::new(Environment::new()));
+    match eval(Node::Program(program), env) {
+        Object::Error(err) => compare(&mut failures, "error", expected.error.as_deref().unwrap_or(""), &err.inspect()),
+        obj => {
+            compare(&mut failures, "error", expected.error.as_deref().unwrap_or(""), "");
+            if let Some(result) = &expected.result {
+                compare(&mut failures, "result", result, &obj.inspect());
+            }
+        },
+    }
+    if let Some(stdout) = &expected.stdout {
+        let mut out: Vec<u8> = Vec::new();
+        run_file(&path.display().to_string(), &Options::default(), &mut out).map_err(|e| e.to_string())?;
+        compare(&mut failures, "stdout", &format!("{}\n", stdout), &String::from_utf8_lossy(&out));
+    }
+    return outcome(failures);
+}
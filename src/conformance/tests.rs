@@ -0,0 +1,55 @@
+#[test]
+fn golden_files() {
+    use std::path::Path;
+    use crate::conformance::{check_file, DIR};
+    use crate::testing::collect_files;
+
+    let mut files = Vec::new();
+    collect_files(&Path::new(env!("CARGO_MANIFEST_DIR")).join(DIR), &mut files).unwrap();
+    assert!(!files.is_empty());
+    let failures: Vec<String> = files.iter()
+        .filter_map(|file| check_file(file).err().map(|e| format!("{}: {}", file.display(), e)))
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n\n"));
+}
+
+#[test]
+fn expectations_from_comments() {
+    use crate::conformance::Expectations;
+
+    let source = "// the value of the program
+let x = 1; // result: not at the start of a line
+// result: 2
+//stdout:  2
+// error: first
+// error: second
+x + 1";
+    let expected = Expectations {
+        result: Some(String::from("2")),
+        error: Some(String::from("first\nsecond")),
+        stdout: Some(String::from(" 2")),
+        ..Expectations::default()
+    };
+    assert_eq!(expected, Expectations::parse(source));
+}
+
+#[test]
+fn check_file_reports_differences() {
+    use crate::conformance::check_file;
+
+    let path = std::env::temp_dir().join(format!("waiig_conformance_{}.mk", std::process::id()));
+    std::fs::write(&path, "// result: 3\n// stdout: 2\n1 + 2").unwrap();
+    assert_eq!(Err(String::from("stdout differs\n  expected: \"2\\n\"\n    actual: \"3\\n\"")), check_file(&path));
+
+    // errors nobody expected are differences too
+    std::fs::write(&path, "// result: 1\n1 / 0").unwrap();
+    assert_eq!(
+        Err(String::from("error differs\n  expected: \"\"\n    actual: \"ZeroDivisionError: division by zero: 1 / 0 (line 2, column 1)\"")),
+        check_file(&path),
+    );
+    std::fs::write(&path, "// ast: x\nlet = 1").unwrap();
+    assert!(check_file(&path).unwrap_err().starts_with("parse error differs"));
+    std::fs::write(&path, "1 + 2").unwrap();
+    assert_eq!(Err(String::from("no expectations")), check_file(&path));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use crate::ast::visit::{self, Visitor};
+use crate::ast::{BlockStatement, IfExpression, Node, Program, Statement};
+use crate::evaluator::{eval_with_hook, Hook, Limits, Shared};
+use crate::object::{Environment, Object};
+use crate::token::Position;
+
+#[cfg(test)]
+mod tests;
+
+/// Which statements and `if` branches of a program ran, and how often.
+/// Every statement and `if` is known up front, so those that never ran are
+/// reported too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coverage {
+    // statements run, by the line they start on
+    lines: BTreeMap<usize, u64>,
+    // each `if` by where its consequence starts, with how often the
+    // consequence and the alternative ran. An `if` without an `else` still
+    // has an alternative: doing nothing.
+    branches: BTreeMap<Position, [u64; 2]>,
+}
+
+/// Finds the statements and `if`s a program has, including those in
+/// function bodies.
+struct Collector<'a>(&'a mut Coverage);
+
+impl Visitor for Collector<'_> {
+    fn visit_statement(&mut self, s: &Statement) {
+        self.0.lines.insert(s.position().line, 0);
+        visit::walk_statement(self, s);
+    }
+
+    fn visit_if_expression(&mut self, ie: &IfExpression) {
+        self.0.branches.insert(ie.consequence.position, [0, 0]);
+        visit::walk_if_expression(self, ie);
+    }
+}
+
+impl Coverage {
+    pub fn new(program: &Program) -> Self {
+        let mut coverage = Self { lines: BTreeMap::new(), branches: BTreeMap::new() };
+        Collector(&mut coverage).visit_program(program);
+        return coverage;
+    }
+
+    /// Each line with a statement on it, with how many statements on it ran.
+    pub fn lines(&self) -> Vec<(usize, u64)> {
+        return self.lines.iter().map(|(line, hits)| (*line, *hits)).collect();
+    }
+
+    /// Each `if` in order, by its line, with how often its consequence and
+    /// its alternative ran.
+    pub fn branches(&self) -> Vec<(usize, [u64; 2])> {
+        return self.branches.iter().map(|(p, taken)| (p.line, *taken)).collect();
+    }
+
+    /// How many lines ran out of those with statements, and how many
+    /// branches ran out of all of them.
+    pub fn summary(&self) -> ((usize, usize), (usize, usize)) {
+        let lines = self.lines.values().filter(|hits| **hits > 0).count();
+        let branches = self.branches.values().flatten().filter(|taken| **taken > 0).count();
+        return ((lines, self.lines.len()), (branches, self.branches.len() * 2));
+    }
+
+    /// This coverage as an LCOV record for the source file at `path`.
+    pub fn lcov(&self, path: &str) -> String {
+        let mut lcov = format!("TN:\nSF:{}\n", path);
+        for (block, (line, taken)) in self.branches().iter().enumerate() {
+            let reached = taken[0] + taken[1] > 0;
+            for (branch, count) in taken.iter().enumerate() {
+                let count = if reached { count.to_string() } else { String::from("-") };
+                lcov += &format!("BRDA:{},{},{},{}\n", line, block, branch, count);
+            }
+        }
+        let ((lines_hit, lines_found), (branches_hit, branches_found)) = self.summary();
+        lcov += &format!("BRF:{}\nBRH:{}\n", branches_found, branches_hit);
+        for (line, hits) in self.lines() {
+            lcov += &format!("DA:{},{}\n", line, hits);
+        }
+        lcov += &format!("LF:{}\nLH:{}\nend_of_record\n", lines_found, lines_hit);
+        return lcov;
+    }
+}
+
+impl Hook for Coverage {
+    fn statement(&mut self, s: &Statement, _env: &Arc<Mutex<Environment<'static>>>) -> Result<(), String> {
+        *self.lines.entry(s.position().line).or_default() += 1;
+        return Ok(());
+    }
+
+    fn branch(&mut self, consequence: &BlockStatement, taken: bool) {
+        let counts = self.branches.entry(consequence.position).or_default();
+        counts[if taken { 0 } else { 1 }] += 1;
+    }
+}
+
+/// Evaluates `program` in `env`, recording its coverage.
+pub fn cover(program: Program, env: Arc<Mutex<Environment<'static>>>) -> (Object<'static>, Coverage) {
+    let coverage = Arc::new(Mutex::new(Coverage::new(&program)));
+    let (result, hook) = eval_with_hook(Node::Program(program), env, Limits::default(), Box::new(Shared(coverage.clone())));
+    drop(hook);
+    return (result, Shared::into_inner(coverage));
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+const PROGRAM: &str = "let sign = fn(n) {
+    if (n < 0) {
+        return -1;
+    }
+    if (n == 0) { 0 } else { 1 }
+};
+let unused = fn() {
+    \"never\"
+};
+sign(5) + sign(0)";
+
+#[cfg(test)]
+fn cover_source(source: &str) -> (crate::object::Object<'static>, crate::coverage::Coverage) {
+    use std::sync::{Arc, Mutex};
+    use crate::lexer::Lexer;
+    use crate::object::Environment;
+    use crate::parser::Parser;
+
+    let program = Parser::new(Lexer::new(source)).parse_program().unwrap();
+    return crate::coverage::cover(program, Arc::new(Mutex::new(Environment::new())));
+}
+
+#[test]
+fn coverage_lines_and_branches() {
+    use crate::object::Object;
+
+    let (result, coverage) = cover_source(PROGRAM);
+    assert_eq!(Object::Integer(1), result);
+    // statements that never ran are still listed; line 5 counts the `if`
+    // and the statement in whichever branch ran
+    assert_eq!(vec![(1, 1), (2, 2), (3, 0), (5, 4), (7, 1), (8, 0), (10, 1)], coverage.lines());
+    // the first `if` has no `else`, but not taking it is still a branch
+    assert_eq!(vec![(2, [0, 2]), (5, [1, 1])], coverage.branches());
+    assert_eq!(((5, 7), (3, 4)), coverage.summary());
+
+    // an `if` that's never reached has neither branch taken
+    let (_, coverage) = cover_source("let f = fn(x) { if (x) { 1 } };\n0");
+    assert_eq!(vec![(1, [0, 0])], coverage.branches());
+}
+
+#[test]
+fn coverage_lcov() {
+    let (_, coverage) = cover_source(PROGRAM);
+    let expected = "TN:
+SF:rules/sign.mk
+BRDA:2,0,0,0
+BRDA:2,0,1,2
+BRDA:5,1,0,1
+BRDA:5,1,1,1
+BRF:4
+BRH:3
+DA:1,1
+DA:2,2
+DA:3,0
+DA:5,4
+DA:7,1
+DA:8,0
+DA:10,1
+LF:7
+LH:5
+end_of_record
+";
+    assert_eq!(expected, coverage.lcov("rules/sign.mk"));
+
+    let (_, coverage) = cover_source("let f = fn(x) { if (x) { 1 } };\n0");
+    assert!(coverage.lcov("f.mk").contains("BRDA:1,0,0,-\nBRDA:1,0,1,-\nBRF:2\nBRH:0\n"));
+}
@@ -0,0 +1,438 @@
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use crate::ast::{Node, Program, Statement};
+use crate::debugger::{self, Step, Stepper, QUIT};
+use crate::evaluator::{eval_with_hook, Hook, Limits};
+use crate::json::{self, Json};
+use crate::lsp::{read_message, write_message};
+use crate::object::{ErrorKind, Environment, Function, Object};
+use crate::parser::parse_source;
+use crate::repl::pretty::format_result;
+
+#[cfg(test)]
+mod tests;
+
+// The Debug Adapter Protocol frames messages like LSP, but has its own
+// envelope: every message has a `seq`, requests name a `command`, and
+// responses say which request they answer and whether it succeeded. Programs
+// are single threaded, so there is one thread with id 1, and stack frame ids
+// are depths in the call stack with the main frame at 0.
+
+const THREAD_ID: i64 = 1;
+
+/// Numbers and writes the messages sent to the client. It is shared with the
+/// thread running the program.
+struct Outbox {
+    out: Arc<Mutex<dyn Write + Send>>,
+    seq: i64,
+}
+
+impl Outbox {
+    fn send(&mut self, kind: &str, mut members: Vec<(&str, Json)>) {
+        self.seq += 1;
+        members.insert(0, ("seq", Json::Number(self.seq as f64)));
+        members.insert(1, ("type", Json::str(kind)));
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // a client that has gone away can't be told anything
+        let _ = write_message(&mut *out, &Json::object(members));
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) {
+        self.send("response", vec![
+            ("request_seq", request.get("seq").cloned().unwrap_or(Json::Null)),
+            ("success", Json::Bool(true)),
+            ("command", request.get("command").cloned().unwrap_or(Json::Null)),
+            ("body", body),
+        ]);
+    }
+
+    fn fail(&mut self, request: &Json, message: &str) {
+        self.send("response", vec![
+            ("request_seq", request.get("seq").cloned().unwrap_or(Json::Null)),
+            ("success", Json::Bool(false)),
+            ("command", request.get("command").cloned().unwrap_or(Json::Null)),
+            ("message", Json::str(message)),
+        ]);
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        self.send("event", vec![("event", Json::str(event)), ("body", body)]);
+    }
+}
+
+fn send(outbox: &Arc<Mutex<Outbox>>) -> std::sync::MutexGuard<'_, Outbox> {
+    return outbox.lock().unwrap_or_else(|e| e.into_inner());
+}
+
+fn arguments(request: &Json) -> Json {
+    return request.get("arguments").cloned().unwrap_or(Json::Object(Vec::new()));
+}
+
+/// What the client sends the running program.
+enum Message {
+    Request(Json),
+    Breakpoints(Vec<usize>),
+}
+
+/// The hook that runs on the program's thread. Between statements it picks
+/// up new breakpoints; while paused it answers the client's requests.
+struct Adapter {
+    stepper: Stepper,
+    messages: Receiver<Message>,
+    outbox: Arc<Mutex<Outbox>>,
+    source: Json,
+    // the environments handed out as variable references, which only last
+    // until the program resumes
+    references: Vec<Arc<Mutex<Environment<'static>>>>,
+}
+
+impl Adapter {
+    fn frame_env(&self, args: &Json) -> Option<Arc<Mutex<Environment<'static>>>> {
+        let frames = &self.stepper.frames;
+        let id = args.get("frameId").and_then(Json::as_i64).unwrap_or(frames.len() as i64 - 1);
+        return frames.get(usize::try_from(id).ok()?).map(|f| f.env.clone());
+    }
+
+    fn reference(&mut self, env: Arc<Mutex<Environment<'static>>>) -> Json {
+        self.references.push(env);
+        return Json::Number(self.references.len() as f64);
+    }
+
+    fn stack_trace(&self) -> Json {
+        let frames: Vec<Json> = self.stepper.frames.iter().enumerate().rev().map(|(id, frame)| Json::object(vec![
+            ("id", Json::Number(id as f64)),
+            ("name", Json::str(&frame.name)),
+            ("source", self.source.clone()),
+            ("line", Json::Number(frame.line as f64)),
+            ("column", Json::Number(1.0)),
+        ])).collect();
+        let total = Json::Number(frames.len() as f64);
+        return Json::object(vec![("stackFrames", Json::Array(frames)), ("totalFrames", total)]);
+    }
+
+    fn scopes(&mut self, env: Arc<Mutex<Environment<'static>>>) -> Json {
+        let chain = debugger::chain(&env);
+        let count = chain.len();
+        let mut scopes = Vec::new();
+        for (i, env) in chain.into_iter().enumerate() {
+            let name = if i + 1 == count { "Globals" } else if i == 0 { "Locals" } else { "Closure" };
+            let reference = self.reference(env);
+            scopes.push(Json::object(vec![
+                ("name", Json::str(name)),
+                ("variablesReference", reference),
+                ("expensive", Json::Bool(false)),
+            ]));
+        }
+        return Json::object(vec![("scopes", Json::Array(scopes))]);
+    }
+
+    fn variables(&self, env: &Arc<Mutex<Environment<'static>>>) -> Json {
+        let variables: Vec<Json> = debugger::bindings(env).into_iter().map(|(name, value, kind)| Json::object(vec![
+            ("name", Json::str(&name)),
+            ("value", Json::str(&value)),
+            ("type", Json::str(&kind)),
+            ("variablesReference", Json::Number(0.0)),
+        ])).collect();
+        return Json::object(vec![("variables", Json::Array(variables))]);
+    }
+
+    /// Handles a message from the client. Returns Some once the program
+    /// should carry on, with an error if it should stop.
+    fn handle(&mut self, message: Message) -> Option<Result<(), String>> {
+        let request = match message {
+            Message::Breakpoints(lines) => {
+                self.stepper.breakpoints = lines;
+                return None;
+            },
+            Message::Request(request) => request,
+        };
+        let args = arguments(&request);
+        let step = match request.get("command").and_then(Json::as_str).unwrap_or("") {
+            "continue" => Step::Continue,
+            "next" => Step::Over,
+            "stepIn" => Step::Into,
+            "stepOut" => Step::Out,
+            "disconnect" | "terminate" => return Some(Err(String::from(QUIT))),
+            "pause" => {
+                self.stepper.pause();
+                send(&self.outbox).respond(&request, Json::Null);
+                return None;
+            },
+            "stackTrace" => {
+                let body = self.stack_trace();
+                send(&self.outbox).respond(&request, body);
+                return None;
+            },
+            "scopes" => {
+                match self.frame_env(&args) {
+                    Some(env) => {
+                        let body = self.scopes(env);
+                        send(&self.outbox).respond(&request, body);
+                    },
+                    None => send(&self.outbox).fail(&request, "no such frame"),
+                }
+                return None;
+            },
+            "variables" => {
+                let reference = args.get("variablesReference").and_then(Json::as_i64).unwrap_or(0);
+                match usize::try_from(reference - 1).ok().and_then(|i| self.references.get(i)) {
+                    Some(env) => {
+                        let body = self.variables(env);
+                        send(&self.outbox).respond(&request, body);
+                    },
+                    None => send(&self.outbox).fail(&request, "no such variables reference"),
+                }
+                return None;
+            },
+            "evaluate" => {
+                let expression = args.get("expression").and_then(Json::as_str).unwrap_or("");
+                let result = match self.frame_env(&args) {
+                    Some(env) => debugger::evaluate(expression, &env),
+                    None => Err(String::from("no such frame")),
+                };
+                match result {
+                    Ok(Object::Error(e)) => send(&self.outbox).fail(&request, &e.inspect()),
+                    Ok(obj) => {
+                        let body = Json::object(vec![
+                            ("result", Json::str(&format_result(&obj, false))),
+                            ("variablesReference", Json::Number(0.0)),
+                        ]);
+                        send(&self.outbox).respond(&request, body);
+                    },
+                    Err(e) => send(&self.outbox).fail(&request, &e),
+                }
+                return None;
+            },
+            command => {
+                send(&self.outbox).fail(&request, &format!("unsupported request {}", command));
+                return None;
+            },
+        };
+        self.stepper.resume(step);
+        self.references.clear();
+        send(&self.outbox).respond(&request, Json::object(vec![("allThreadsContinued", Json::Bool(true))]));
+        return Some(Ok(()));
+    }
+}
+
+impl Hook for Adapter {
+    fn statement(&mut self, s: &Statement, env: &Arc<Mutex<Environment<'static>>>) -> Result<(), String> {
+        loop {
+            match self.messages.try_recv() {
+                Ok(message) => {
+                    if let Some(Err(e)) = self.handle(message) {
+                        return Err(e);
+                    }
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Err(String::from(QUIT)),
+            }
+        }
+        let Some(stop) = self.stepper.statement(s.position().line, env) else { return Ok(()) };
+        send(&self.outbox).event("stopped", Json::object(vec![
+            ("reason", Json::str(stop.name())),
+            ("threadId", Json::Number(THREAD_ID as f64)),
+            ("allThreadsStopped", Json::Bool(true)),
+        ]));
+        loop {
+            let Ok(message) = self.messages.recv() else { return Err(String::from(QUIT)) };
+            if let Some(result) = self.handle(message) {
+                return result;
+            }
+        }
+    }
+
+    fn enter_call(&mut self, name: &str, _f: &Function<'static>, env: &Arc<Mutex<Environment<'static>>>) {
+        self.stepper.enter(name, env);
+    }
+
+    fn exit_call(&mut self) {
+        self.stepper.exit();
+    }
+}
+
+/// A program that has been launched but not started yet.
+struct Launch {
+    path: String,
+    lines: usize,
+    program: Program,
+    stop_on_entry: bool,
+}
+
+/// A debugging session with one client, which may launch one program.
+pub struct Session {
+    outbox: Arc<Mutex<Outbox>>,
+    launch: Option<Launch>,
+    breakpoints: Vec<usize>,
+    messages: Option<Sender<Message>>,
+    program: Option<JoinHandle<()>>,
+    disconnected: bool,
+}
+
+impl Session {
+    pub fn new(output: Arc<Mutex<dyn Write + Send>>) -> Self {
+        return Self {
+            outbox: Arc::new(Mutex::new(Outbox { out: output, seq: 0 })),
+            launch: None,
+            breakpoints: Vec::new(),
+            messages: None,
+            program: None,
+            disconnected: false,
+        };
+    }
+
+    /// Whether the client has disconnected.
+    pub fn disconnected(&self) -> bool {
+        return self.disconnected;
+    }
+
+    /// Handles a message from the client. Responses and events are written
+    /// to the output, some of them later from the program's thread.
+    pub fn handle(&mut self, request: &Json) {
+        let args = arguments(request);
+        match request.get("command").and_then(Json::as_str).unwrap_or("") {
+            "initialize" => {
+                let capabilities = Json::object(vec![
+                    ("supportsConfigurationDoneRequest", Json::Bool(true)),
+                    ("supportsEvaluateForHovers", Json::Bool(true)),
+                    ("supportsTerminateRequest", Json::Bool(true)),
+                ]);
+                send(&self.outbox).respond(request, capabilities);
+                send(&self.outbox).event("initialized", Json::Null);
+            },
+            "launch" => match self.load(&args) {
+                Ok(launch) => {
+                    self.launch = Some(launch);
+                    send(&self.outbox).respond(request, Json::Null);
+                },
+                Err(e) => send(&self.outbox).fail(request, &e),
+            },
+            "setBreakpoints" => {
+                let lines = self.launch.as_ref().map(|l| l.lines).unwrap_or(0);
+                let requested: Vec<i64> = args.get("breakpoints").and_then(Json::as_array).map(|b| {
+                    b.iter().filter_map(|b| b.get("line").and_then(Json::as_i64)).collect()
+                }).unwrap_or_default();
+                let verified = |line: i64| line >= 1 && line as usize <= lines;
+                self.breakpoints = requested.iter().filter(|l| verified(**l)).map(|l| *l as usize).collect();
+                if let Some(messages) = &self.messages {
+                    let _ = messages.send(Message::Breakpoints(self.breakpoints.clone()));
+                }
+                let breakpoints: Vec<Json> = requested.iter().map(|line| Json::object(vec![
+                    ("verified", Json::Bool(verified(*line))),
+                    ("line", Json::Number(*line as f64)),
+                ])).collect();
+                send(&self.outbox).respond(request, Json::object(vec![("breakpoints", Json::Array(breakpoints))]));
+            },
+            "configurationDone" => {
+                send(&self.outbox).respond(request, Json::Null);
+                self.start();
+            },
+            "threads" => {
+                let thread = Json::object(vec![("id", Json::Number(THREAD_ID as f64)), ("name", Json::str("main"))]);
+                send(&self.outbox).respond(request, Json::object(vec![("threads", Json::Array(vec![thread]))]));
+            },
+            "disconnect" => {
+                self.stop(request);
+                self.disconnected = true;
+                send(&self.outbox).respond(request, Json::Null);
+            },
+            "terminate" => {
+                self.stop(request);
+                send(&self.outbox).respond(request, Json::Null);
+            },
+            _ => {
+                // everything else is about the running program, which
+                // answers once it's between statements
+                let sent = self.messages.as_ref().is_some_and(|m| m.send(Message::Request(request.clone())).is_ok());
+                if !sent {
+                    send(&self.outbox).fail(request, "the program isn't running");
+                }
+            },
+        }
+    }
+
+    fn load(&self, args: &Json) -> Result<Launch, String> {
+        let path = args.get("program").and_then(Json::as_str).ok_or("launch needs a program")?;
+        let source = std::fs::read_to_string(path).map_err(|e| format!("error reading {}: {}", path, e))?;
+        return Ok(Launch {
+            path: path.to_string(),
+            lines: source.lines().count(),
+            program: parse_source(&source)?,
+            stop_on_entry: args.get("stopOnEntry").and_then(Json::as_bool).unwrap_or(false),
+        });
+    }
+
+    /// Starts the launched program on its own thread.
+    fn start(&mut self) {
+        let Some(launch) = self.launch.take() else { return };
+        let (sender, receiver) = channel();
+        self.messages = Some(sender);
+        let env = Arc::new(Mutex::new(Environment::new()));
+        let mut stepper = if launch.stop_on_entry { Stepper::new(env.clone()) } else { Stepper::running(env.clone()) };
+        stepper.breakpoints = self.breakpoints.clone();
+        let name = std::path::Path::new(&launch.path).file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+        let adapter = Adapter {
+            stepper,
+            messages: receiver,
+            outbox: self.outbox.clone(),
+            source: Json::object(vec![("name", Json::str(&name)), ("path", Json::str(&launch.path))]),
+            references: Vec::new(),
+        };
+        let outbox = self.outbox.clone();
+        self.program = Some(std::thread::spawn(move || {
+            let (result, _) = eval_with_hook(Node::Program(launch.program), env, Limits::default(), Box::new(adapter));
+            let mut outbox = send(&outbox);
+            let failed = match &result {
+                Object::Error(e) if e.kind == ErrorKind::InterruptedError && e.message == QUIT => {
+                    // stopped by the client, so there's no exit code to report
+                    outbox.event("terminated", Json::Null);
+                    return;
+                },
+                Object::Error(_) => {
+                    let text = format!("{}\n", format_result(&result, false));
+                    outbox.event("output", Json::object(vec![("category", Json::str("stderr")), ("output", Json::str(&text))]));
+                    true
+                },
+                _ => {
+                    let text = format!("program finished: {}\n", format_result(&result, false));
+                    outbox.event("output", Json::object(vec![("category", Json::str("console")), ("output", Json::str(&text))]));
+                    false
+                },
+            };
+            outbox.event("exited", Json::object(vec![("exitCode", Json::Number(if failed { 1.0 } else { 0.0 }))]));
+            outbox.event("terminated", Json::Null);
+        }));
+    }
+
+    /// Stops the program, if it's running, and waits for it.
+    fn stop(&mut self, request: &Json) {
+        if let Some(messages) = self.messages.take() {
+            let _ = messages.send(Message::Request(request.clone()));
+        }
+        if let Some(program) = self.program.take() {
+            let _ = program.join();
+        }
+    }
+}
+
+/// Serves a client on `input` and `output` until it disconnects or input
+/// ends.
+pub fn run(input: &mut dyn BufRead, output: Arc<Mutex<dyn Write + Send>>) -> std::io::Result<()> {
+    let mut session = Session::new(output);
+    while let Some(body) = read_message(input)? {
+        match json::parse(&body) {
+            Ok(request) => session.handle(&request),
+            Err(e) => send(&session.outbox).event("output", Json::object(vec![
+                ("category", Json::str("stderr")),
+                ("output", Json::str(&format!("invalid message: {}\n", e))),
+            ])),
+        }
+        if session.disconnected() {
+            return Ok(());
+        }
+    }
+    session.stop(&Json::object(vec![("command", Json::str("disconnect"))]));
+    return Ok(());
+}
@@ -0,0 +1,285 @@
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+#[cfg(test)]
+use crate::dap::Session;
+#[cfg(test)]
+use crate::json::Json;
+
+#[cfg(test)]
+const PROGRAM: &str = "let add = fn(a, b) {
+    let sum = a + b;
+    sum
+};
+let x = 1;
+let y = add(x, 2);
+y * 2";
+
+/// A scripted client talking to a session in the same process. It reads
+/// the session's output as it's written, since the program runs on another
+/// thread.
+#[cfg(test)]
+struct Client {
+    session: Session,
+    output: Arc<Mutex<Vec<u8>>>,
+    read: usize,
+    seq: i64,
+    received: Vec<Json>,
+}
+
+#[cfg(test)]
+impl Client {
+    fn new() -> Self {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        return Self { session: Session::new(output.clone()), output, read: 0, seq: 0, received: Vec::new() };
+    }
+
+    /// Waits for a message the session has sent that `wanted` matches, and
+    /// takes it out of those received.
+    fn wait(&mut self, wanted: impl Fn(&Json) -> bool) -> Json {
+        use crate::lsp::read_message;
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        loop {
+            {
+                let output = self.output.lock().unwrap();
+                let mut unread = &output[self.read..];
+                while let Some(body) = read_message(&mut unread).unwrap() {
+                    self.received.push(crate::json::parse(&body).unwrap());
+                }
+                self.read = output.len() - unread.len();
+            }
+            if let Some(i) = self.received.iter().position(&wanted) {
+                return self.received.remove(i);
+            }
+            assert!(std::time::Instant::now() < deadline, "timed out; received {:?}", self.received);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    /// Sends a request and waits for its response.
+    fn send(&mut self, command: &str, arguments: Json) -> Json {
+        self.seq += 1;
+        let seq = self.seq;
+        self.session.handle(&Json::object(vec![
+            ("seq", Json::Number(seq as f64)),
+            ("type", Json::str("request")),
+            ("command", Json::str(command)),
+            ("arguments", arguments),
+        ]));
+        return self.wait(|m| m.get("type").and_then(Json::as_str) == Some("response") && m.get("request_seq").and_then(Json::as_i64) == Some(seq));
+    }
+
+    /// Sends a request that should succeed and returns the response body.
+    fn request(&mut self, command: &str, arguments: Json) -> Json {
+        let response = self.send(command, arguments);
+        assert_eq!(Some(true), response.get("success").and_then(Json::as_bool), "{}", response);
+        return response.get("body").cloned().unwrap_or(Json::Null);
+    }
+
+    fn event(&mut self, event: &str) -> Json {
+        let body = self.wait(|m| m.get("event").and_then(Json::as_str) == Some(event));
+        return body.get("body").cloned().unwrap_or(Json::Null);
+    }
+
+    fn stopped(&mut self) -> String {
+        return self.event("stopped").get("reason").and_then(Json::as_str).unwrap().to_string();
+    }
+
+    /// The name, line and id of each frame on the stack, innermost first.
+    fn frames(&mut self) -> Vec<(String, i64, i64)> {
+        let trace = self.request("stackTrace", Json::object(vec![("threadId", Json::Number(1.0))]));
+        return trace.get("stackFrames").and_then(Json::as_array).unwrap().iter().map(|f| (
+            f.get("name").and_then(Json::as_str).unwrap().to_string(),
+            f.get("line").and_then(Json::as_i64).unwrap(),
+            f.get("id").and_then(Json::as_i64).unwrap(),
+        )).collect();
+    }
+
+    /// Launches `source` from a file and starts it.
+    fn launch(&mut self, name: &str, source: &str, stop_on_entry: bool, breakpoints: &[i64]) -> String {
+        let path = std::env::temp_dir().join(format!("waiig_dap_{}_{}.mk", std::process::id(), name));
+        std::fs::write(&path, source).unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        self.request("initialize", Json::object(vec![("adapterID", Json::str("monkey"))]));
+        self.event("initialized");
+        self.request("launch", Json::object(vec![("program", Json::str(&path)), ("stopOnEntry", Json::Bool(stop_on_entry))]));
+        let lines = breakpoints.iter().map(|l| Json::object(vec![("line", Json::Number(*l as f64))])).collect();
+        let source = Json::object(vec![("path", Json::str(&path))]);
+        self.request("setBreakpoints", Json::object(vec![("source", source), ("breakpoints", Json::Array(lines))]));
+        self.request("configurationDone", Json::Null);
+        return path;
+    }
+}
+
+#[test]
+fn dap_breakpoints_and_variables() {
+    let mut client = Client::new();
+    let body = client.request("initialize", Json::object(vec![]));
+    assert_eq!(Some(true), body.get("supportsConfigurationDoneRequest").and_then(Json::as_bool));
+    client.event("initialized");
+
+    let path = std::env::temp_dir().join(format!("waiig_dap_{}_variables.mk", std::process::id()));
+    std::fs::write(&path, PROGRAM).unwrap();
+    let path = path.to_str().unwrap();
+    client.request("launch", Json::object(vec![("program", Json::str(path)), ("stopOnEntry", Json::Bool(true))]));
+    let source = Json::object(vec![("path", Json::str(path))]);
+    let lines = vec![Json::object(vec![("line", Json::Number(3.0))]), Json::object(vec![("line", Json::Number(99.0))])];
+    let body = client.request("setBreakpoints", Json::object(vec![("source", source), ("breakpoints", Json::Array(lines))]));
+    let verified: Vec<Option<bool>> = body.get("breakpoints").and_then(Json::as_array).unwrap().iter().map(|b| b.get("verified").and_then(Json::as_bool)).collect();
+    assert_eq!(vec![Some(true), Some(false)], verified);
+
+    client.request("configurationDone", Json::Null);
+    assert_eq!("entry", client.stopped());
+    let threads = client.request("threads", Json::Null);
+    assert_eq!(1, threads.get("threads").and_then(Json::as_array).unwrap().len());
+
+    client.request("continue", Json::object(vec![("threadId", Json::Number(1.0))]));
+    assert_eq!("breakpoint", client.stopped());
+    let frames = client.frames();
+    assert_eq!(vec![(String::from("add"), 3, 1), (String::from("<main>"), 6, 0)], frames);
+
+    let scopes = client.request("scopes", Json::object(vec![("frameId", Json::Number(1.0))]));
+    let scopes = scopes.get("scopes").and_then(Json::as_array).unwrap().clone();
+    let names: Vec<&str> = scopes.iter().filter_map(|s| s.get("name").and_then(Json::as_str)).collect();
+    assert_eq!(vec!["Locals", "Globals"], names);
+    let reference = scopes[0].get("variablesReference").cloned().unwrap();
+    let variables = client.request("variables", Json::object(vec![("variablesReference", reference)]));
+    let variables: Vec<(String, String, String)> = variables.get("variables").and_then(Json::as_array).unwrap().iter().map(|v| (
+        v.get("name").and_then(Json::as_str).unwrap().to_string(),
+        v.get("value").and_then(Json::as_str).unwrap().to_string(),
+        v.get("type").and_then(Json::as_str).unwrap().to_string(),
+    )).collect();
+    assert_eq!(vec![
+        (String::from("a"), String::from("1"), String::from("INTEGER")),
+        (String::from("b"), String::from("2"), String::from("INTEGER")),
+        (String::from("sum"), String::from("3"), String::from("INTEGER")),
+    ], variables);
+
+    let result = client.request("evaluate", Json::object(vec![("expression", Json::str("sum * 10")), ("frameId", Json::Number(1.0))]));
+    assert_eq!(Some("30"), result.get("result").and_then(Json::as_str));
+    let result = client.request("evaluate", Json::object(vec![("expression", Json::str("x")), ("frameId", Json::Number(0.0))]));
+    assert_eq!(Some("1"), result.get("result").and_then(Json::as_str));
+    let failed = client.send("evaluate", Json::object(vec![("expression", Json::str("nope"))]));
+    assert_eq!(Some(false), failed.get("success").and_then(Json::as_bool));
+
+    client.request("stepOut", Json::object(vec![("threadId", Json::Number(1.0))]));
+    assert_eq!("step", client.stopped());
+    assert_eq!(vec![(String::from("<main>"), 7, 0)], client.frames());
+
+    client.request("continue", Json::object(vec![("threadId", Json::Number(1.0))]));
+    let output = client.event("output");
+    assert_eq!(Some("program finished: 6\n"), output.get("output").and_then(Json::as_str));
+    assert_eq!(Some(0), client.event("exited").get("exitCode").and_then(Json::as_i64));
+    client.event("terminated");
+    let failed = client.send("stackTrace", Json::Null);
+    assert_eq!(Some(false), failed.get("success").and_then(Json::as_bool));
+
+    client.request("disconnect", Json::Null);
+    assert!(client.session.disconnected());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn dap_stepping_and_pausing() {
+    let mut client = Client::new();
+    let path = client.launch("stepping", PROGRAM, true, &[]);
+    assert_eq!("entry", client.stopped());
+    client.request("next", Json::Null);
+    assert_eq!("step", client.stopped());
+    client.request("next", Json::Null);
+    assert_eq!("step", client.stopped());
+    assert_eq!(6, client.frames()[0].1);
+    client.request("stepIn", Json::Null);
+    assert_eq!("step", client.stopped());
+    assert_eq!((String::from("add"), 2), (client.frames()[0].0.clone(), client.frames()[0].1));
+
+    client.request("continue", Json::Null);
+    assert_eq!(Some(0), client.event("exited").get("exitCode").and_then(Json::as_i64));
+    std::fs::remove_file(path).unwrap();
+
+    let mut client = Client::new();
+    let forever = "let count = fn(n) {\n    count(n + 1)\n};\ncount(0)";
+    let path = client.launch("pausing", forever, false, &[]);
+    client.request("pause", Json::Null);
+    assert_eq!("pause", client.stopped());
+    // it may pause before `count` is first called, or anywhere in it
+    let line = client.frames()[0].1;
+    assert!((1..=4).contains(&line), "{}", line);
+    client.request("continue", Json::Null);
+    client.request("pause", Json::Null);
+    assert_eq!("pause", client.stopped());
+    let frames = client.frames();
+    assert_eq!((String::from("count"), 2), (frames[0].0.clone(), frames[0].1));
+    let n = client.request("evaluate", Json::object(vec![("expression", Json::str("n > 0"))]));
+    assert_eq!(Some("true"), n.get("result").and_then(Json::as_str));
+
+    // disconnecting stops the program without it finishing, but the client
+    // is still told it has ended
+    client.request("disconnect", Json::Null);
+    client.event("terminated");
+    assert!(client.received.iter().all(|m| m.get("event").and_then(Json::as_str) != Some("exited")));
+    std::fs::remove_file(path).unwrap();
+
+    let mut client = Client::new();
+    let path = client.launch("terminated", PROGRAM, true, &[]);
+    assert_eq!("entry", client.stopped());
+    client.request("terminate", Json::Null);
+    client.event("terminated");
+    assert!(client.received.iter().all(|m| m.get("event").and_then(Json::as_str) != Some("exited")));
+    std::fs::remove_file(path).unwrap();
+
+    let mut client = Client::new();
+    let path = client.launch("failing", "let x = 1;\nx + true", false, &[]);
+    let output = client.event("output");
+    assert_eq!(Some("stderr"), output.get("category").and_then(Json::as_str));
+    assert_eq!(Some(1), client.event("exited").get("exitCode").and_then(Json::as_i64));
+    std::fs::remove_file(path).unwrap();
+
+    let mut client = Client::new();
+    let failed = client.send("launch", Json::object(vec![("program", Json::str("/nonexistent/program.mk"))]));
+    assert_eq!(Some(false), failed.get("success").and_then(Json::as_bool));
+}
+
+#[test]
+fn dap_over_stdio() {
+    use std::io::Write;
+    use crate::dap::run;
+    use crate::lsp::read_message;
+
+    let path = std::env::temp_dir().join(format!("waiig_dap_{}_stdio.mk", std::process::id()));
+    std::fs::write(&path, "1 + 1").unwrap();
+    let launch = format!(r#"{{"seq": 2, "type": "request", "command": "launch", "arguments": {{"program": {}}}}}"#, Json::str(path.to_str().unwrap()));
+    let messages = [
+        r#"{"seq": 1, "type": "request", "command": "initialize", "arguments": {}}"#,
+        &launch,
+        "not json",
+        r#"{"seq": 3, "type": "request", "command": "configurationDone"}"#,
+        r#"{"seq": 4, "type": "request", "command": "disconnect"}"#,
+        r#"{"seq": 5, "type": "request", "command": "threads"}"#,
+    ];
+    let mut input: Vec<u8> = Vec::new();
+    for message in messages {
+        write!(input, "Content-Length: {}\r\n\r\n{}", message.len(), message).unwrap();
+    }
+
+    let output = Arc::new(Mutex::new(Vec::<u8>::new()));
+    run(&mut input.as_slice(), output.clone()).unwrap();
+
+    let output = output.lock().unwrap();
+    let mut reader = output.as_slice();
+    let mut responses: Vec<i64> = Vec::new();
+    let mut seqs: Vec<i64> = Vec::new();
+    while let Some(body) = read_message(&mut reader).unwrap() {
+        let message = crate::json::parse(&body).unwrap();
+        seqs.push(message.get("seq").and_then(Json::as_i64).unwrap());
+        if let Some(seq) = message.get("request_seq").and_then(Json::as_i64) {
+            assert_eq!(Some(true), message.get("success").and_then(Json::as_bool));
+            responses.push(seq);
+        }
+    }
+    // nothing is read after the disconnect
+    assert_eq!(vec![1, 2, 3, 4], responses);
+    assert_eq!((1..=seqs.len() as i64).collect::<Vec<i64>>(), seqs);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,425 @@
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use crate::ast::{Node, Statement};
+use crate::evaluator::{self, eval_with_hook, Hook, Limits};
+use crate::object::{get_type, ErrorKind, Environment, Function, Object};
+use crate::parser::parse_source;
+use crate::repl::pretty::format_result;
+
+#[cfg(test)]
+mod tests;
+
+/// The reason a debugger that quits gives for stopping the program.
+pub const QUIT: &str = "debugger quit";
+
+/// How to carry on from a pause.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    /// Run until a breakpoint.
+    Continue,
+    /// Stop at the next statement, including ones in functions it calls.
+    Into,
+    /// Stop at the next statement in this function or one it returns to.
+    Over,
+    /// Stop once this function has returned.
+    Out,
+}
+
+/// Why the program paused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stop {
+    Entry,
+    Breakpoint,
+    Step,
+    Pause,
+}
+
+impl Stop {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Stop::Entry => "entry",
+            Stop::Breakpoint => "breakpoint",
+            Stop::Step => "step",
+            Stop::Pause => "pause",
+        };
+    }
+}
+
+/// A call in progress: the function as written at its call site, the line
+/// it's on and the environment its current statement runs in.
+pub struct Frame {
+    pub name: String,
+    pub line: usize,
+    pub env: Arc<Mutex<Environment<'static>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Continue,
+    Into,
+    Over(usize),
+    Out(usize),
+    Pause,
+}
+
+/// Tracks the call stack as a program runs and decides where it should
+/// pause. It knows nothing about how pauses are shown to the user.
+pub struct Stepper {
+    pub breakpoints: Vec<usize>,
+    pub frames: Vec<Frame>,
+    mode: Mode,
+    entry: bool,
+    // the line and depth of the last statement, so that statements sharing
+    // a line only pause once
+    last: (usize, usize),
+}
+
+impl Stepper {
+    /// Starts a stepper that pauses at the first statement.
+    pub fn new(env: Arc<Mutex<Environment<'static>>>) -> Self {
+        return Self {
+            breakpoints: Vec::new(),
+            frames: vec![Frame { name: String::from("<main>"), line: 0, env }],
+            mode: Mode::Into,
+            entry: true,
+            last: (0, 0),
+        };
+    }
+
+    /// Starts a stepper that only pauses at breakpoints.
+    pub fn running(env: Arc<Mutex<Environment<'static>>>) -> Self {
+        let mut stepper = Self::new(env);
+        stepper.mode = Mode::Continue;
+        stepper.entry = false;
+        return stepper;
+    }
+
+    fn depth(&self) -> usize {
+        return self.frames.len() - 1;
+    }
+
+    pub fn top(&self) -> &Frame {
+        return self.frames.last().expect("the main frame is never popped");
+    }
+
+    /// Records that a statement on `line` is about to run in `env`, and
+    /// returns why the program should pause there, if it should.
+    pub fn statement(&mut self, line: usize, env: &Arc<Mutex<Environment<'static>>>) -> Option<Stop> {
+        let depth = self.depth();
+        if let Some(frame) = self.frames.last_mut() {
+            frame.line = line;
+            frame.env = env.clone();
+        }
+        let moved = self.last != (line, depth);
+        self.last = (line, depth);
+        let stepped = match self.mode {
+            Mode::Continue => false,
+            Mode::Pause => return Some(Stop::Pause),
+            Mode::Into => moved,
+            Mode::Over(d) => moved && depth <= d,
+            Mode::Out(d) => depth < d,
+        };
+        if stepped {
+            if self.entry {
+                self.entry = false;
+                return Some(Stop::Entry);
+            }
+            return Some(Stop::Step);
+        }
+        if moved && self.breakpoints.contains(&line) {
+            return Some(Stop::Breakpoint);
+        }
+        return None;
+    }
+
+    pub fn enter(&mut self, name: &str, env: &Arc<Mutex<Environment<'static>>>) {
+        let line = self.top().line;
+        self.frames.push(Frame { name: name.to_string(), line, env: env.clone() });
+    }
+
+    pub fn exit(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    /// Pauses at the next statement, wherever it is.
+    pub fn pause(&mut self) {
+        self.mode = Mode::Pause;
+    }
+
+    /// Carries on from a pause.
+    pub fn resume(&mut self, step: Step) {
+        let depth = self.depth();
+        self.entry = false;
+        self.mode = match step {
+            Step::Continue => Mode::Continue,
+            Step::Into => Mode::Into,
+            Step::Over => Mode::Over(depth),
+            Step::Out => Mode::Out(depth),
+        };
+    }
+}
+
+/// `env` and each environment enclosing it, innermost first.
+pub fn chain(env: &Arc<Mutex<Environment<'static>>>) -> Vec<Arc<Mutex<Environment<'static>>>> {
+    let mut chain = vec![env.clone()];
+    // the paused statement never holds a lock, but a caller might
+    while let Some(parent) = chain.last().and_then(|env| env.try_lock().ok()).and_then(|env| env.parent()) {
+        chain.push(parent);
+    }
+    return chain;
+}
+
+/// The bindings in `env` as names, values formatted for display and types.
+pub fn bindings(env: &Arc<Mutex<Environment<'static>>>) -> Vec<(String, String, String)> {
+    let Ok(env) = env.try_lock() else { return Vec::new() };
+    return env.bindings().iter().map(|(name, obj)| (name.to_string(), format_result(obj, false), get_type(obj))).collect();
+}
+
+/// Evaluates `source` in `env`, as if it were the paused statement.
+pub fn evaluate(source: &str, env: &Arc<Mutex<Environment<'static>>>) -> Result<Object<'static>, String> {
+    let program = parse_source(source)?;
+    return Ok(evaluator::eval(Node::Program(program), env.clone()));
+}
+
+const HELP: &str = "\
+break, b <line>      pause when the program reaches a line
+delete <line>        remove the breakpoint on a line
+breakpoints          list breakpoints
+continue, c          run until the next breakpoint
+step, s              run to the next statement, going into calls
+next, n              run to the next statement in this function
+finish, out, o       run until this function returns
+env                  print the bindings in scope, innermost first
+print, p <expr>      evaluate an expression where the program is paused
+backtrace, bt        print the call stack
+list, l              print the source around the paused line
+quit, q              stop the program
+help                 print this";
+
+/// A debugger that reads commands from `input` whenever the program pauses.
+pub struct Debugger {
+    input: Box<dyn BufRead + Send>,
+    output: Arc<Mutex<dyn Write + Send>>,
+    lines: Vec<String>,
+    stepper: Stepper,
+    last_command: String,
+}
+
+impl Debugger {
+    pub fn new(
+        source: &str,
+        env: Arc<Mutex<Environment<'static>>>,
+        input: Box<dyn BufRead + Send>,
+        output: Arc<Mutex<dyn Write + Send>>,
+    ) -> Self {
+        return Self {
+            input,
+            output,
+            lines: source.lines().map(String::from).collect(),
+            stepper: Stepper::new(env),
+            last_command: String::new(),
+        };
+    }
+
+    fn say(&self, text: &str) {
+        let mut out = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(out, "{}", text);
+    }
+
+    fn source_line(&self, line: usize) -> String {
+        let text = self.lines.get(line.wrapping_sub(1)).map(String::as_str).unwrap_or("");
+        let marker = if line == self.stepper.top().line { ">" } else { " " };
+        let breakpoint = if self.stepper.breakpoints.contains(&line) { "*" } else { " " };
+        return format!("{}{}{:>4} | {}", marker, breakpoint, line, text);
+    }
+
+    fn list(&self) {
+        let line = self.stepper.top().line;
+        let first = line.saturating_sub(3).max(1);
+        let last = (line + 3).min(self.lines.len());
+        for n in first..=last {
+            self.say(&self.source_line(n));
+        }
+    }
+
+    fn env(&self) {
+        let chain = chain(&self.stepper.top().env);
+        let count = chain.len();
+        for (i, env) in chain.iter().enumerate() {
+            self.say(if i + 1 == count { "global:" } else if i == 0 { "local:" } else { "enclosing:" });
+            for (name, value, _) in bindings(env) {
+                self.say(&format!("  {} = {}", name, value));
+            }
+        }
+    }
+
+    fn backtrace(&self) {
+        for (i, frame) in self.stepper.frames.iter().rev().enumerate() {
+            self.say(&format!("#{} {} at line {}", i, frame.name, frame.line));
+        }
+    }
+
+    fn line_argument(&self, arg: &str) -> Option<usize> {
+        let line = arg.trim().parse::<usize>().ok()?;
+        if line == 0 || line > self.lines.len() {
+            return None;
+        }
+        return Some(line);
+    }
+
+    /// Reads and runs commands until one resumes the program. Errors if the
+    /// user quits or input runs out.
+    fn pause(&mut self, stop: Stop) -> Result<(), String> {
+        let frame = self.stepper.top();
+        self.say(&format!("stopped at {} in {}, line {}", stop.name(), frame.name, frame.line));
+        self.say(&self.source_line(frame.line));
+        loop {
+            {
+                let mut out = self.output.lock().unwrap_or_else(|e| e.into_inner());
+                let _ = write!(out, "(debug) ");
+                let _ = out.flush();
+            }
+            let mut line = String::new();
+            if self.input.read_line(&mut line).unwrap_or(0) == 0 {
+                self.say("");
+                return Err(String::from(QUIT));
+            }
+            let mut command = line.trim().to_string();
+            if command.is_empty() {
+                command = self.last_command.clone();
+            }
+            self.last_command = command.clone();
+            let (word, arg) = command.split_once(' ').unwrap_or((&command, ""));
+            let step = match word {
+                "" => None,
+                "continue" | "c" => Some(Step::Continue),
+                "step" | "s" => Some(Step::Into),
+                "next" | "n" => Some(Step::Over),
+                "finish" | "out" | "o" => Some(Step::Out),
+                "quit" | "q" => return Err(String::from(QUIT)),
+                "break" | "b" => {
+                    match self.line_argument(arg) {
+                        Some(line) if self.stepper.breakpoints.contains(&line) => self.say(&format!("breakpoint already set at line {}", line)),
+                        Some(line) => {
+                            self.stepper.breakpoints.push(line);
+                            self.stepper.breakpoints.sort();
+                            self.say(&format!("breakpoint set at line {}", line));
+                        },
+                        None => self.say(&format!("no line {} in the program", arg.trim())),
+                    }
+                    None
+                },
+                "delete" => {
+                    let before = self.stepper.breakpoints.len();
+                    let line = self.line_argument(arg);
+                    self.stepper.breakpoints.retain(|b| Some(*b) != line);
+                    if self.stepper.breakpoints.len() == before {
+                        self.say(&format!("no breakpoint at line {}", arg.trim()));
+                    }
+                    None
+                },
+                "breakpoints" => {
+                    if self.stepper.breakpoints.is_empty() {
+                        self.say("no breakpoints");
+                    }
+                    for line in &self.stepper.breakpoints {
+                        self.say(&self.source_line(*line));
+                    }
+                    None
+                },
+                "env" => {
+                    self.env();
+                    None
+                },
+                "print" | "p" => {
+                    match evaluate(arg, &self.stepper.top().env) {
+                        Ok(obj) => self.say(&format_result(&obj, false)),
+                        Err(e) => self.say(&e),
+                    }
+                    None
+                },
+                "backtrace" | "bt" => {
+                    self.backtrace();
+                    None
+                },
+                "list" | "l" => {
+                    self.list();
+                    None
+                },
+                "help" | "h" => {
+                    self.say(HELP);
+                    None
+                },
+                other => {
+                    self.say(&format!("unknown command {}; try help", other));
+                    None
+                },
+            };
+            if let Some(step) = step {
+                self.stepper.resume(step);
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Hook for Debugger {
+    fn statement(&mut self, s: &Statement, env: &Arc<Mutex<Environment<'static>>>) -> Result<(), String> {
+        return match self.stepper.statement(s.position().line, env) {
+            Some(stop) => self.pause(stop),
+            None => Ok(()),
+        };
+    }
+
+    fn enter_call(&mut self, name: &str, _f: &Function<'static>, env: &Arc<Mutex<Environment<'static>>>) {
+        self.stepper.enter(name, env);
+    }
+
+    fn exit_call(&mut self) {
+        self.stepper.exit();
+    }
+}
+
+/// Runs `source` under the debugger, pausing at its first statement.
+/// Returns false if it fails to parse or ends with an error.
+pub fn debug(source: &str, input: Box<dyn BufRead + Send>, output: Arc<Mutex<dyn Write + Send>>) -> bool {
+    let say = |text: &str| {
+        let mut out = output.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(out, "{}", text);
+    };
+    let program = match parse_source(source) {
+        Ok(program) => program,
+        Err(e) => {
+            say(&e);
+            return false;
+        },
+    };
+
+    let env = Arc::new(Mutex::new(Environment::new()));
+    let debugger = Debugger::new(source, env.clone(), input, output.clone());
+    let (result, _) = eval_with_hook(Node::Program(program), env, Limits::default(), Box::new(debugger));
+    return match result {
+        Object::Error(e) if e.kind == ErrorKind::InterruptedError && e.message == QUIT => true,
+        Object::Error(_) => {
+            say(&format_result(&result, false));
+            false
+        },
+        _ => {
+            say(&format!("program finished: {}", format_result(&result, false)));
+            true
+        },
+    };
+}
+
+/// Reads the program at `path` and debugs it. See `debug`.
+pub fn debug_file(path: &str, input: Box<dyn BufRead + Send>, output: Arc<Mutex<dyn Write + Send>>) -> bool {
+    return match std::fs::read_to_string(path) {
+        Ok(source) => debug(&source, input, output),
+        Err(e) => {
+            eprintln!("error reading {}: {}", path, e);
+            false
+        },
+    };
+}
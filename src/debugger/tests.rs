@@ -0,0 +1,84 @@
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+const PROGRAM: &str = "let add = fn(a, b) {
+    let sum = a + b;
+    sum
+};
+let x = 1;
+let y = add(x, 2);
+y * 2";
+
+/// Runs `source` under the debugger with `commands` as its input, returning
+/// whether it succeeded and everything it printed.
+#[cfg(test)]
+fn session(source: &str, commands: &str) -> (bool, String) {
+    use crate::debugger::debug;
+
+    let output = Arc::new(Mutex::new(Vec::<u8>::new()));
+    let input = Box::new(std::io::Cursor::new(commands.as_bytes().to_vec()));
+    let ok = debug(source, input, output.clone());
+    let printed = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+    return (ok, printed);
+}
+
+#[test]
+fn debugger_breakpoints_and_inspection() {
+    let (ok, printed) = session(PROGRAM, "b 3\nc\nbt\nenv\np sum + 1\np nope\nc\n");
+    assert!(ok);
+    let expected = "stopped at entry in <main>, line 1
+>    1 | let add = fn(a, b) {
+(debug) breakpoint set at line 3
+(debug) stopped at breakpoint in add, line 3
+>*   3 |     sum
+(debug) #0 add at line 3
+#1 <main> at line 6
+(debug) local:
+  a = 1
+  b = 2
+  sum = 3
+global:
+  add = fn(a, b)
+  x = 1
+(debug) 4
+(debug) NameError: unknown identifier: nope (line 1, column 1)
+(debug) program finished: 6
+";
+    assert_eq!(expected, printed);
+}
+
+#[test]
+fn debugger_stepping() {
+    let (ok, printed) = session(PROGRAM, "n\nn\ns\n\no\nq\n");
+    assert!(ok);
+    let stops: Vec<&str> = printed.lines().filter(|l| l.contains("stopped at")).collect();
+    assert_eq!(vec![
+        "stopped at entry in <main>, line 1",
+        "(debug) stopped at step in <main>, line 5",
+        "(debug) stopped at step in <main>, line 6",
+        "(debug) stopped at step in add, line 2",
+        "(debug) stopped at step in add, line 3",
+        "(debug) stopped at step in <main>, line 7",
+    ], stops);
+    assert!(!printed.contains("program finished"));
+}
+
+#[test]
+fn debugger_errors_and_end_of_input() {
+    let (ok, printed) = session("let x = 1;\nx + true", "l\nb 9\nfoo\nc\n");
+    assert!(!ok);
+    assert!(printed.contains(">    1 | let x = 1;\n     2 | x + true\n"), "{}", printed);
+    assert!(printed.contains("no line 9 in the program"), "{}", printed);
+    assert!(printed.contains("unknown command foo"), "{}", printed);
+    assert!(printed.ends_with("TypeError: type mismatch: INTEGER + BOOLEAN (line 2, column 1)\n"), "{}", printed);
+
+    // running out of commands stops the program
+    let (ok, printed) = session("1", "");
+    assert!(ok);
+    assert!(!printed.contains("program finished"));
+
+    let (ok, printed) = session("let = 1", "");
+    assert!(!ok);
+    assert!(printed.contains("^"), "{}", printed);
+}
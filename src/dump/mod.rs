@@ -0,0 +1,370 @@
+use std::io::Write;
+use crate::ast::{BlockStatement, Expression, Program, Statement};
+use crate::json::Json;
+use crate::lexer::Lexer;
+use crate::parser::{parse_source, with_nesting_stack};
+use crate::token::{get_literal, Position, Token};
+
+#[cfg(test)]
+mod tests;
+
+// Machine-readable dumps of the token stream and the AST, as JSON or as
+// S-expressions. Tokens, statements and blocks carry their source spans;
+// expressions don't track positions yet.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Sexp,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        return match name {
+            "json" => Some(Format::Json),
+            "sexp" => Some(Format::Sexp),
+            _ => None,
+        };
+    }
+}
+
+/// A token's variant name, without any payload.
+pub fn token_kind(t: &Token) -> String {
+    let name = format!("{:?}", t);
+    return match name.split_once('(') {
+        Some((kind, _)) => kind.to_string(),
+        None => name,
+    };
+}
+
+/// Every token in `source` up to EOF, with where it starts and ends.
+pub fn tokens(source: &str) -> Vec<(Token, Position, Position)> {
+    let mut l = Lexer::new(source);
+    let mut tokens = Vec::new();
+    loop {
+        let t = l.next_token();
+        if t == Token::EOF {
+            return tokens;
+        }
+        tokens.push((t, l.token_position, l.token_end));
+    }
+}
+
+fn position_json(p: Position) -> Json {
+    return Json::object(vec![("line", Json::Number(p.line as f64)), ("column", Json::Number(p.column as f64))]);
+}
+
+fn span_json(start: Position, end: Position) -> Json {
+    return Json::object(vec![("start", position_json(start)), ("end", position_json(end))]);
+}
+
+pub fn tokens_json(source: &str) -> Json {
+    return Json::Array(tokens(source).into_iter().map(|(t, start, end)| Json::object(vec![
+        ("kind", Json::String(token_kind(&t))),
+        ("literal", Json::String(get_literal(&t))),
+        ("span", span_json(start, end)),
+    ])).collect());
+}
+
+/// One `(KIND "literal" span)` list per line.
+pub fn tokens_sexp(source: &str) -> String {
+    let mut out = String::new();
+    for (t, start, end) in tokens(source) {
+        out += &format!("({} {} {})\n", token_kind(&t), Json::String(get_literal(&t)), span_atom(start, end));
+    }
+    return out;
+}
+
+fn block_end(b: &BlockStatement) -> Position {
+    return Position { line: b.end.line, column: b.end.column + 1 };
+}
+
+fn block_json(b: &BlockStatement) -> Json {
+    return Json::object(vec![
+        ("type", Json::str("BlockStatement")),
+        ("span", span_json(b.position, block_end(b))),
+        ("statements", Json::Array(b.statements.iter().map(statement_json).collect())),
+    ]);
+}
+
+fn statement_json(s: &Statement) -> Json {
+    let span = ("span", span_json(s.position(), s.end()));
+    return match s {
+        Statement::LetStatement(ls) => Json::object(vec![
+            ("type", Json::str("LetStatement")),
+            span,
+            ("name", Json::str(&ls.name.value)),
+            ("value", ls.value.as_ref().map_or(Json::Null, expression_json)),
+        ]),
+        Statement::ReturnStatement(rs) => Json::object(vec![
+            ("type", Json::str("ReturnStatement")),
+            span,
+            ("value", expression_json(&rs.return_val)),
+        ]),
+        Statement::ThrowStatement(ts) => Json::object(vec![
+            ("type", Json::str("ThrowStatement")),
+            span,
+            ("value", expression_json(&ts.value)),
+        ]),
+        Statement::ImportStatement(is) => Json::object(vec![
+            ("type", Json::str("ImportStatement")),
+            span,
+            ("namespace", Json::str(&is.namespace.value)),
+        ]),
+        Statement::ExpressionStatement(es) => Json::object(vec![
+            ("type", Json::str("ExpressionStatement")),
+            span,
+            ("expression", expression_json(&es.expression)),
+        ]),
+    };
+}
+
+fn expression_json(e: &Expression) -> Json {
+    let node = |name: &str, mut members: Vec<(&str, Json)>| {
+        members.insert(0, ("type", Json::str(name)));
+        if let Some((start, end)) = e.span() {
+            members.insert(1, ("span", span_json(start, end)));
+        }
+        Json::object(members)
+    };
+    return match e {
+        Expression::Empty => Json::Null,
+        Expression::Identifier(i) => node("Identifier", vec![("name", Json::str(&i.value))]),
+        Expression::IntegerLiteral(il) => node("IntegerLiteral", vec![("value", Json::Number(il.value as f64))]),
+        Expression::StringLiteral(sl) => node("StringLiteral", vec![("value", Json::str(&sl.value))]),
+        Expression::Boolean(b) => node("BooleanLiteral", vec![("value", Json::Bool(b.value))]),
+        Expression::PrefixExpression(pe) => node("PrefixExpression", vec![
+            ("operator", Json::str(&pe.operator)),
+            ("right", expression_json(&pe.right)),
+        ]),
+        Expression::InfixExpression(ie) => node("InfixExpression", vec![
+            ("operator", Json::str(&ie.operator)),
+            ("left", expression_json(&ie.left)),
+            ("right", expression_json(&ie.right)),
+        ]),
+        Expression::IfExpression(ie) => node("IfExpression", vec![
+            ("condition", expression_json(&ie.condition)),
+            ("consequence", block_json(&ie.consequence)),
+            ("alternative", ie.alternative.as_ref().map_or(Json::Null, block_json)),
+        ]),
+        Expression::FunctionLiteral(fl) => node("FunctionLiteral", vec![
+            ("parameters", Json::Array(fl.parameters.iter().map(|p| Json::str(&p.value)).collect())),
+            ("body", block_json(&fl.body)),
+        ]),
+        Expression::CallExpression(ce) => node("CallExpression", vec![
+            ("function", expression_json(&ce.function)),
+            ("arguments", Json::Array(ce.arguments.iter().map(expression_json).collect())),
+        ]),
+        Expression::TryExpression(te) => node("TryExpression", vec![
+            ("body", block_json(&te.body)),
+            ("parameter", te.parameter.as_ref().map_or(Json::Null, |p| Json::str(&p.value))),
+            ("handler", te.handler.as_ref().map_or(Json::Null, block_json)),
+            ("finalizer", te.finalizer.as_ref().map_or(Json::Null, block_json)),
+        ]),
+        Expression::MemberExpression(me) => node("MemberExpression", vec![
+            ("object", expression_json(&me.object)),
+            ("property", Json::str(&me.property.value)),
+        ]),
+    };
+}
+
+pub fn program_json(program: &Program) -> Json {
+    return Json::object(vec![
+        ("type", Json::str("Program")),
+        ("statements", Json::Array(program.statements.iter().map(statement_json).collect())),
+    ]);
+}
+
+enum Sexp {
+    Atom(String),
+    List(Vec<Sexp>),
+}
+
+fn atom(s: &str) -> Sexp {
+    return Sexp::Atom(s.to_string());
+}
+
+fn list(name: &str, mut items: Vec<Sexp>) -> Sexp {
+    items.insert(0, atom(name));
+    return Sexp::List(items);
+}
+
+fn span_atom(start: Position, end: Position) -> String {
+    return format!("{}:{}-{}:{}", start.line, start.column, end.line, end.column);
+}
+
+impl Sexp {
+    fn flat(&self) -> String {
+        return match self {
+            Sexp::Atom(a) => a.clone(),
+            Sexp::List(items) => format!("({})", items.iter().map(Sexp::flat).collect::<Vec<String>>().join(" ")),
+        };
+    }
+
+    /// The length of `flat`, measured only as far as it takes to pass
+    /// `limit`, so deep trees aren't flattened once per level.
+    fn flat_len(&self, limit: usize) -> usize {
+        return match self {
+            Sexp::Atom(a) => a.len(),
+            Sexp::List(items) => {
+                // the parentheses and the spaces between items
+                let mut len = items.len().max(1) + 1;
+                for item in items {
+                    if len > limit {
+                        break;
+                    }
+                    len += item.flat_len(limit - len);
+                }
+                len
+            },
+        };
+    }
+
+    /// Lists that don't fit in 80 columns keep their leading atoms on the
+    /// first line and put each remaining item on its own line.
+    fn write(&self, out: &mut String, depth: usize) {
+        match self {
+            Sexp::List(items) if depth * 2 + self.flat_len(80) > 80 => {
+                let head = items.iter().take_while(|item| matches!(item, Sexp::Atom(_))).count();
+                out.push('(');
+                out.push_str(&items[..head].iter().map(Sexp::flat).collect::<Vec<String>>().join(" "));
+                for item in &items[head..] {
+                    out.push('\n');
+                    out.push_str(&"  ".repeat(depth + 1));
+                    item.write(out, depth + 1);
+                }
+                out.push(')');
+            },
+            _ => out.push_str(&self.flat()),
+        }
+    }
+}
+
+fn block_sexp(b: &BlockStatement) -> Sexp {
+    let mut items = vec![atom(&span_atom(b.position, block_end(b)))];
+    items.extend(b.statements.iter().map(statement_sexp));
+    return list("block", items);
+}
+
+fn statement_sexp(s: &Statement) -> Sexp {
+    let span = atom(&span_atom(s.position(), s.end()));
+    return match s {
+        Statement::LetStatement(ls) => list("let", vec![
+            span,
+            atom(&ls.name.value),
+            ls.value.as_ref().map_or(atom("nil"), expression_sexp),
+        ]),
+        Statement::ReturnStatement(rs) => list("return", vec![span, expression_sexp(&rs.return_val)]),
+        Statement::ThrowStatement(ts) => list("throw", vec![span, expression_sexp(&ts.value)]),
+        Statement::ImportStatement(is) => list("import", vec![span, Sexp::Atom(Json::str(&is.namespace.value).to_string())]),
+        Statement::ExpressionStatement(es) => list("expr", vec![span, expression_sexp(&es.expression)]),
+    };
+}
+
+fn expression_sexp(e: &Expression) -> Sexp {
+    let node = |name: &str, mut items: Vec<Sexp>| {
+        if let Some((start, end)) = e.span() {
+            items.insert(0, atom(&span_atom(start, end)));
+        }
+        list(name, items)
+    };
+    return match e {
+        Expression::Empty => atom("nil"),
+        Expression::Identifier(i) => node("ident", vec![atom(&i.value)]),
+        Expression::IntegerLiteral(il) => node("int", vec![atom(&il.value.to_string())]),
+        Expression::StringLiteral(sl) => node("string", vec![Sexp::Atom(Json::str(&sl.value).to_string())]),
+        Expression::Boolean(b) => node("bool", vec![atom(&b.value.to_string())]),
+        Expression::PrefixExpression(pe) => node("prefix", vec![atom(&pe.operator), expression_sexp(&pe.right)]),
+        Expression::InfixExpression(ie) => node("infix", vec![
+            atom(&ie.operator),
+            expression_sexp(&ie.left),
+            expression_sexp(&ie.right),
+        ]),
+        Expression::IfExpression(ie) => {
+            let mut items = vec![expression_sexp(&ie.condition), block_sexp(&ie.consequence)];
+            items.extend(ie.alternative.iter().map(block_sexp));
+            node("if", items)
+        },
+        Expression::FunctionLiteral(fl) => node("fn", vec![
+            list("params", fl.parameters.iter().map(|p| atom(&p.value)).collect()),
+            block_sexp(&fl.body),
+        ]),
+        Expression::CallExpression(ce) => {
+            let mut items = vec![expression_sexp(&ce.function)];
+            items.extend(ce.arguments.iter().map(expression_sexp));
+            node("call", items)
+        },
+        Expression::TryExpression(te) => {
+            let mut items = vec![block_sexp(&te.body)];
+            if let Some(handler) = &te.handler {
+                let mut catch: Vec<Sexp> = te.parameter.iter().map(|p| atom(&p.value)).collect();
+                catch.push(block_sexp(handler));
+                items.push(list("catch", catch));
+            }
+            if let Some(finalizer) = &te.finalizer {
+                items.push(list("finally", vec![block_sexp(finalizer)]));
+            }
+            node("try", items)
+        },
+        Expression::MemberExpression(me) => node("member", vec![expression_sexp(&me.object), atom(&me.property.value)]),
+    };
+}
+
+/// The program as an S-expression, with each statement on its own line.
+pub fn program_sexp(program: &Program) -> String {
+    let mut out = String::from("(program");
+    for s in program.statements.iter() {
+        out.push_str("\n  ");
+        statement_sexp(s).write(&mut out, 1);
+    }
+    out.push_str(")\n");
+    return out;
+}
+
+/// Dumps the tokens of the file at `path` to `out`. Returns whether the file
+/// could be read.
+pub fn dump_tokens(path: &str, format: Format, out: &mut dyn Write) -> std::io::Result<bool> {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error reading {}: {}", path, e);
+            return Ok(false);
+        },
+    };
+    match format {
+        Format::Json => writeln!(out, "{}", tokens_json(&source).pretty())?,
+        Format::Sexp => write!(out, "{}", tokens_sexp(&source))?,
+    }
+    return Ok(true);
+}
+
+/// Dumps the AST of the file at `path` to `out`. Returns whether the file
+/// could be read and parsed.
+pub fn dump_ast(path: &str, format: Format, out: &mut dyn Write) -> std::io::Result<bool> {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error reading {}: {}", path, e);
+            return Ok(false);
+        },
+    };
+    let program = match parse_source(&source) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(false);
+        },
+    };
+    let dumped = with_nesting_stack(|| match format {
+        Format::Json => format!("{}\n", program_json(&program).pretty()),
+        Format::Sexp => program_sexp(&program),
+    });
+    match dumped {
+        Ok(dumped) => write!(out, "{}", dumped)?,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(false);
+        },
+    }
+    return Ok(true);
+}
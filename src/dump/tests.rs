@@ -0,0 +1,88 @@
+#[cfg(test)]
+use crate::dump::{program_json, program_sexp, tokens_json, tokens_sexp};
+
+#[test]
+fn dump_tokens() {
+    use crate::json::Json;
+
+    let tokens = tokens_json("let x = \"a\";");
+    let tokens = tokens.as_array().unwrap();
+    assert_eq!(5, tokens.len());
+    assert_eq!(Some("LET"), tokens[0].get("kind").and_then(Json::as_str));
+    assert_eq!(Some("let"), tokens[0].get("literal").and_then(Json::as_str));
+    assert_eq!(Some("STRING"), tokens[3].get("kind").and_then(Json::as_str));
+    assert_eq!(Some("a"), tokens[3].get("literal").and_then(Json::as_str));
+    let span = tokens[3].get("span").unwrap();
+    assert_eq!(Some(9), span.get("start").and_then(|s| s.get("column")).and_then(Json::as_i64));
+    assert_eq!(Some(12), span.get("end").and_then(|s| s.get("column")).and_then(Json::as_i64));
+
+    assert_eq!("(INT \"5\" 1:1-1:2)\n(PLUS \"+\" 1:3-1:4)\n(IDENT \"y\" 2:1-2:2)\n", tokens_sexp("5 +\ny"));
+}
+
+#[test]
+fn dump_program_json() {
+    use crate::json::{parse, Json};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let mut p = Parser::new(Lexer::new("let f = fn(a) { a * 2 };\nf(3)"));
+    let program = p.parse_program().unwrap();
+    let json = program_json(&program);
+
+    let statements = json.get("statements").and_then(Json::as_array).unwrap();
+    assert_eq!(2, statements.len());
+    let let_statement = &statements[0];
+    assert_eq!(Some("LetStatement"), let_statement.get("type").and_then(Json::as_str));
+    assert_eq!(Some("f"), let_statement.get("name").and_then(Json::as_str));
+    let span = let_statement.get("span").unwrap();
+    assert_eq!(Some(1), span.get("start").and_then(|s| s.get("column")).and_then(Json::as_i64));
+    assert_eq!(Some(25), span.get("end").and_then(|s| s.get("column")).and_then(Json::as_i64));
+
+    let function = let_statement.get("value").unwrap();
+    assert_eq!(Some("FunctionLiteral"), function.get("type").and_then(Json::as_str));
+    let body = function.get("body").unwrap();
+    assert_eq!(Some(15), body.get("span").and_then(|s| s.get("start")).and_then(|s| s.get("column")).and_then(Json::as_i64));
+    assert_eq!(Some(24), body.get("span").and_then(|s| s.get("end")).and_then(|s| s.get("column")).and_then(Json::as_i64));
+    let infix = body.get("statements").and_then(Json::as_array).unwrap()[0].get("expression").unwrap();
+    assert_eq!(Some("*"), infix.get("operator").and_then(Json::as_str));
+    assert_eq!(Some(2), infix.get("right").and_then(|r| r.get("value")).and_then(Json::as_i64));
+    // expressions have spans too, down to their operands
+    let column = |node: &Json, end: &str| node.get("span").and_then(|s| s.get(end)).and_then(|s| s.get("column")).and_then(Json::as_i64);
+    assert_eq!((Some(17), Some(22)), (column(infix, "start"), column(infix, "end")));
+    let right = infix.get("right").unwrap();
+    assert_eq!((Some(21), Some(22)), (column(right, "start"), column(right, "end")));
+
+    let call = statements[1].get("expression").unwrap();
+    assert_eq!(Some("CallExpression"), call.get("type").and_then(Json::as_str));
+    assert_eq!(Some(2), statements[1].get("span").and_then(|s| s.get("start")).and_then(|s| s.get("line")).and_then(Json::as_i64));
+
+    // the printed form parses back to the same value
+    assert_eq!(Ok(json.clone()), parse(&json.pretty()));
+}
+
+#[test]
+fn dump_program_sexp() {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let mut p = Parser::new(Lexer::new("import \"std\";\nlet x = -1 + strlen(\"λb\");\nif (x) { x } else { try { 1 } catch (e) { e } }"));
+    let program = p.parse_program().unwrap();
+    let expected = "\
+(program
+  (import 1:1-1:14 \"std\")
+  (let 2:1-2:27 x
+    (infix 2:9-2:26 +
+      (prefix 2:9-2:11 - (int 2:10-2:11 1))
+      (call 2:14-2:26 (ident 2:14-2:20 strlen) (string 2:21-2:25 \"λb\"))))
+  (expr 3:1-3:48
+    (if 3:1-3:48
+      (ident 3:5-3:6 x)
+      (block 3:8-3:13 (expr 3:10-3:11 (ident 3:10-3:11 x)))
+      (block 3:19-3:48
+        (expr 3:21-3:46
+          (try 3:21-3:46
+            (block 3:25-3:30 (expr 3:27-3:28 (int 3:27-3:28 1)))
+            (catch e (block 3:41-3:46 (expr 3:43-3:44 (ident 3:43-3:44 e))))))))))
+";
+    assert_eq!(expected, program_sexp(&program));
+}
@@ -0,0 +1,177 @@
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use crate::ast::{BlockStatement, Statement};
+use crate::object::{Environment, Function};
+
+/// Callbacks the evaluator makes as it runs, for debuggers, profilers and
+/// coverage tools.
+pub trait Hook {
+    /// Called before each statement runs, in the environment it runs in.
+    /// Returning an error stops the evaluation with an `InterruptedError`.
+    fn statement(&mut self, _s: &Statement, _env: &Arc<Mutex<Environment<'static>>>) -> Result<(), String> {
+        return Ok(());
+    }
+
+    /// Called once a statement the hook was told about has run, whether
+    /// or not it succeeded.
+    fn end_statement(&mut self) {}
+
+    /// Called when an `if` has chosen a branch, before it runs. The `if` is
+    /// identified by its `consequence`, and `taken` is whether that's the
+    /// branch chosen.
+    fn branch(&mut self, _consequence: &BlockStatement, _taken: bool) {}
+
+    /// Called when the evaluation allocates an environment, a binding or a
+    /// string, with the bytes it takes.
+    fn allocate(&mut self, _bytes: usize) {}
+
+    /// Called when a Monkey function is called, once its arguments are
+    /// bound in `env`. `name` is the function as written at the call site.
+    /// The allocations for `env` are reported just after this.
+    fn enter_call(&mut self, _name: &str, _f: &Function<'static>, _env: &Arc<Mutex<Environment<'static>>>) {}
+
+    /// Called when the most recently entered call returns, whether with a
+    /// value or an error. A tail call exits its caller before entering.
+    fn exit_call(&mut self) {}
+}
+
+/// Lets a hook be read back once the evaluation it's installed for is over.
+pub struct Shared<H>(pub Arc<Mutex<H>>);
+
+impl<H> Shared<H> {
+    /// Takes back the hook in `shared` once nothing else holds it, as when
+    /// the evaluation has handed back the `Shared` it was given.
+    pub fn into_inner(shared: Arc<Mutex<H>>) -> H {
+        let shared = Arc::try_unwrap(shared).ok().expect("hook still installed");
+        return shared.into_inner().unwrap_or_else(|e| e.into_inner());
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, H> {
+        return self.0.lock().unwrap_or_else(|e| e.into_inner());
+    }
+}
+
+impl<H: Hook> Hook for Shared<H> {
+    fn statement(&mut self, s: &Statement, env: &Arc<Mutex<Environment<'static>>>) -> Result<(), String> {
+        return self.lock().statement(s, env);
+    }
+
+    fn end_statement(&mut self) {
+        self.lock().end_statement();
+    }
+
+    fn branch(&mut self, consequence: &BlockStatement, taken: bool) {
+        self.lock().branch(consequence, taken);
+    }
+
+    fn allocate(&mut self, bytes: usize) {
+        self.lock().allocate(bytes);
+    }
+
+    fn enter_call(&mut self, name: &str, f: &Function<'static>, env: &Arc<Mutex<Environment<'static>>>) {
+        self.lock().enter_call(name, f, env);
+    }
+
+    fn exit_call(&mut self) {
+        self.lock().exit_call();
+    }
+}
+
+/// Calls each hook in turn. A statement is stopped by the first that errors.
+impl Hook for Vec<Box<dyn Hook + Send>> {
+    fn statement(&mut self, s: &Statement, env: &Arc<Mutex<Environment<'static>>>) -> Result<(), String> {
+        return self.iter_mut().try_for_each(|h| h.statement(s, env));
+    }
+
+    fn end_statement(&mut self) {
+        self.iter_mut().for_each(|h| h.end_statement());
+    }
+
+    fn branch(&mut self, consequence: &BlockStatement, taken: bool) {
+        self.iter_mut().for_each(|h| h.branch(consequence, taken));
+    }
+
+    fn allocate(&mut self, bytes: usize) {
+        self.iter_mut().for_each(|h| h.allocate(bytes));
+    }
+
+    fn enter_call(&mut self, name: &str, f: &Function<'static>, env: &Arc<Mutex<Environment<'static>>>) {
+        self.iter_mut().for_each(|h| h.enter_call(name, f, env));
+    }
+
+    fn exit_call(&mut self) {
+        self.iter_mut().for_each(|h| h.exit_call());
+    }
+}
+
+thread_local! {
+    static HOOK: RefCell<Option<Box<dyn Hook + Send>>> = RefCell::new(None);
+    /// Allocations held back by `hold_allocations`, while it runs.
+    static HELD: RefCell<Option<Vec<usize>>> = const { RefCell::new(None) };
+}
+
+/// Installs `hook` on the current thread, returning the one it replaces.
+pub fn install(hook: Option<Box<dyn Hook + Send>>) -> Option<Box<dyn Hook + Send>> {
+    return HOOK.with(|h| h.replace(hook));
+}
+
+pub fn active() -> bool {
+    return HOOK.with(|h| h.borrow().is_some());
+}
+
+/// Runs `f` on the installed hook, if there is one. The hook is taken out
+/// while it runs, so evaluation it starts itself isn't hooked.
+fn with_hook<T>(f: impl FnOnce(&mut dyn Hook) -> T) -> Option<T> {
+    let mut hook = HOOK.with(|h| h.borrow_mut().take())?;
+    let ret = f(hook.as_mut());
+    HOOK.with(|h| {
+        h.borrow_mut().get_or_insert(hook);
+    });
+    return Some(ret);
+}
+
+pub fn statement(s: &Statement, env: &Arc<Mutex<Environment<'static>>>) -> Result<(), String> {
+    return with_hook(|h| h.statement(s, env)).unwrap_or(Ok(()));
+}
+
+pub fn end_statement() {
+    with_hook(|h| h.end_statement());
+}
+
+pub fn branch(consequence: &BlockStatement, taken: bool) {
+    with_hook(|h| h.branch(consequence, taken));
+}
+
+pub fn allocate(bytes: usize) {
+    let held = HELD.with(|h| h.borrow_mut().as_mut().map(|held| held.push(bytes)).is_some());
+    if !held {
+        with_hook(|h| h.allocate(bytes));
+    }
+}
+
+/// Runs `f`, holding back the allocations it makes, and returns them with
+/// its result for `report_allocations`. A call's frame is allocated before
+/// the hook can be told of the call, so this lets the call be charged for it.
+pub fn hold_allocations<T>(f: impl FnOnce() -> T) -> (T, Vec<usize>) {
+    if !active() {
+        return (f(), Vec::new());
+    }
+    let outer = HELD.with(|h| h.replace(Some(Vec::new())));
+    let ret = f();
+    let held = HELD.with(|h| h.replace(outer)).unwrap_or_default();
+    return (ret, held);
+}
+
+pub fn report_allocations(held: Vec<usize>) {
+    for bytes in held {
+        allocate(bytes);
+    }
+}
+
+pub fn enter_call(name: &str, f: &Function<'static>, env: &Arc<Mutex<Environment<'static>>>) {
+    with_hook(|h| h.enter_call(name, f, env));
+}
+
+pub fn exit_call() {
+    with_hook(|h| h.exit_call());
+}
@@ -0,0 +1,219 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::parser::{with_stack, NESTING_STACK};
+
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+// Rust stack reserved per Monkey call. A call costs roughly 15KiB in debug
+// builds; the rest is headroom for expressions nested inside the body.
+const STACK_PER_CALL: usize = 64 * 1024;
+// The stack before any calls, which has room for the deepest expression
+// that parses.
+const BASE_STACK: usize = NESTING_STACK;
+// The largest stack an evaluator thread is given. Deeper limits are lowered
+// to what fits in it.
+const MAX_STACK: usize = 1024 * 1024 * 1024;
+
+// reading the clock on every step is measurably slow, so the deadline is only
+// checked every this many steps
+const DEADLINE_CHECK_INTERVAL: u64 = 256;
+
+/// Resource limits applied to a single call of `eval_with_limits`.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Maximum number of nested (non-tail) function calls.
+    pub max_depth: usize,
+    /// Maximum number of statements and expressions evaluated.
+    pub max_steps: Option<u64>,
+    /// Wall-clock time allowed, measured from the start of the evaluation.
+    pub timeout: Option<Duration>,
+    /// Stops the evaluation when cancelled, possibly from another thread.
+    pub cancel: Option<CancelHandle>,
+    /// Maximum number of bytes held by values and environments created
+    /// during the evaluation.
+    pub max_memory: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        return Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_steps: None,
+            timeout: None,
+            cancel: None,
+            max_memory: None,
+        };
+    }
+}
+
+/// A shareable flag that interrupts every evaluation it is attached to.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        return self.cancelled.load(Ordering::SeqCst);
+    }
+
+    /// Clears a previous cancellation so the handle can be reused.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Default)]
+struct State {
+    limits: Limits,
+    depth: usize,
+    steps: u64,
+    deadline: Option<Instant>,
+    interrupted: Option<String>,
+    memory: usize,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+/// The stack needed for `max_depth` nested calls, up to `MAX_STACK`.
+pub fn stack_size(max_depth: usize) -> usize {
+    return max_depth.checked_mul(STACK_PER_CALL)
+        .and_then(|calls| calls.checked_add(BASE_STACK))
+        .map_or(MAX_STACK, |size| size.min(MAX_STACK));
+}
+
+/// Runs `f` with `limits` in force on a stack large enough for
+/// `limits.max_depth` nested calls, so exceeding the limit is reported as an
+/// error rather than overflowing the caller's stack. A thread is started for
+/// it unless the caller's stack was set up by `with_stack` and has room.
+/// Fails if the thread can't be started.
+pub fn with_limits<T: Send>(mut limits: Limits, f: impl FnOnce() -> T + Send) -> Result<T, String> {
+    limits.max_depth = limits.max_depth.min((MAX_STACK - BASE_STACK) / STACK_PER_CALL);
+    let stack_size = stack_size(limits.max_depth);
+    let deadline = limits.timeout.map(|t| Instant::now() + t);
+    return with_stack("monkey-eval", stack_size, || {
+        let outer = STATE.with(|s| s.replace(State { limits, deadline, ..State::default() }));
+        let _restore = Restore(Some(outer));
+        return f();
+    })
+    .map_err(|e| format!("error starting the evaluator: {}", e));
+}
+
+/// Runs `f` on a stack with room for evaluations under the default limits,
+/// so those it makes don't each start a thread.
+pub fn with_eval_stack<T: Send>(f: impl FnOnce() -> T + Send) -> Result<T, String> {
+    return with_stack("monkey-eval", stack_size(DEFAULT_MAX_DEPTH), f)
+        .map_err(|e| format!("error starting the evaluator: {}", e));
+}
+
+/// Puts back the state of an enclosing evaluation on the same thread, even
+/// if the inner one panics.
+struct Restore(Option<State>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(outer) = self.0.take() {
+            STATE.with(|s| s.replace(outer));
+        }
+    }
+}
+
+/// Counts one evaluation step against the budget. Once any limit has been hit
+/// every later step fails too, so the evaluation unwinds all the way out.
+pub fn tick() -> Result<(), String> {
+    return STATE.with(|s| {
+        let mut s = s.borrow_mut();
+        if let Some(reason) = &s.interrupted {
+            return Err(reason.clone());
+        }
+        s.steps += 1;
+
+        let mut reason = None;
+        if let Some(cancel) = &s.limits.cancel {
+            if cancel.is_cancelled() {
+                reason = Some(String::from("evaluation cancelled"));
+            }
+        }
+        if let Some(max_steps) = s.limits.max_steps {
+            if s.steps > max_steps {
+                reason = Some(format!("step budget of {} exhausted", max_steps));
+            }
+        }
+        if let Some(deadline) = s.deadline {
+            if s.steps % DEADLINE_CHECK_INTERVAL == 0 && Instant::now() >= deadline {
+                let timeout = s.limits.timeout.unwrap_or_default();
+                reason = Some(format!("timed out after {}ms", timeout.as_millis()));
+            }
+        }
+
+        if let Some(reason) = reason {
+            s.interrupted = Some(reason.clone());
+            return Err(reason);
+        }
+        return Ok(());
+    });
+}
+
+/// Records entry into a function call. Returns the configured maximum depth
+/// as an error if the call would exceed it.
+pub fn enter_call() -> Result<(), usize> {
+    return STATE.with(|s| {
+        let mut s = s.borrow_mut();
+        if s.depth >= s.limits.max_depth {
+            return Err(s.limits.max_depth);
+        }
+        s.depth += 1;
+        return Ok(());
+    });
+}
+
+pub fn exit_call() {
+    STATE.with(|s| {
+        let mut s = s.borrow_mut();
+        s.depth = s.depth.saturating_sub(1);
+    });
+}
+
+/// Accounts for `bytes` newly held by the evaluation. The bytes are recorded
+/// even when they exceed the limit, so a later `release` balances out.
+pub fn charge(bytes: usize) -> Result<(), usize> {
+    return STATE.with(|s| {
+        let mut s = s.borrow_mut();
+        s.memory += bytes;
+        return match s.limits.max_memory {
+            Some(max) if s.memory > max => Err(max),
+            _ => Ok(()),
+        };
+    });
+}
+
+/// Checks that `bytes` more could be held without charging for them, for
+/// temporaries that are about to be allocated.
+pub fn reserve(bytes: usize) -> Result<(), usize> {
+    return STATE.with(|s| {
+        let s = s.borrow();
+        return match s.limits.max_memory {
+            Some(max) if s.memory.saturating_add(bytes) > max => Err(max),
+            _ => Ok(()),
+        };
+    });
+}
+
+pub fn release(bytes: usize) {
+    STATE.with(|s| {
+        let mut s = s.borrow_mut();
+        s.memory = s.memory.saturating_sub(bytes);
+    });
+}
@@ -1,21 +1,100 @@
+#[cfg(test)]
 mod tests;
+mod limits;
+mod hooks;
+pub mod snapshot;
+
+pub use limits::{with_eval_stack, CancelHandle, Limits};
+pub use hooks::{Hook, Shared};
+
+/// Namespaces that `import` gives meaning to.
+pub const NAMESPACES: &[&str] = &["std.string", "std.test"];
+
+/// A function that importing its namespace makes available.
+pub struct StdFunction {
+    pub namespace: &'static str,
+    pub name: &'static str,
+    /// How many arguments it takes.
+    pub arity: usize,
+    pub doc: &'static str,
+}
+
+pub const STD_FUNCTIONS: &[StdFunction] = &[
+    StdFunction { namespace: "std.string", name: "strlen", arity: 1, doc: "strlen(s) returns the length of the string s in bytes." },
+    StdFunction { namespace: "std.test", name: "assert", arity: 1, doc: "assert(cond) raises an AssertionError unless cond is truthy." },
+    StdFunction {
+        namespace: "std.test",
+        name: "assert_eq",
+        arity: 2,
+        doc: "assert_eq(actual, expected) raises an AssertionError showing how the two differ unless they are equal.",
+    },
+    StdFunction {
+        namespace: "std.test",
+        name: "assert_error",
+        arity: 1,
+        doc: "assert_error(f) calls f with no arguments and returns the error it raises, or raises an AssertionError if it doesn't.",
+    },
+];
 
 use std::sync::{Mutex, Arc};
 use crate::object::*;
 use crate::ast::*;
 
 macro_rules! new_error {
-    ($($arg:tt)*) => {{
+    ($kind:expr, $($arg:tt)*) => {{
         let str = format!($($arg)*);
-        return Object::Error(str.to_string());
+        return Object::Error(RuntimeError::new($kind, str.to_string()));
     }}
 }
 
+fn memory_error(max: usize) -> Object<'static> {
+    return Object::Error(RuntimeError::new(
+        ErrorKind::MemoryError,
+        format!("memory limit of {} bytes exceeded", max),
+    ));
+}
+
+/// Binds `name` in `env`, charging the evaluation for any growth.
+fn bind(env: &mut Environment<'static>, name: String, val: Object<'static>) -> Result<(), Object<'static>> {
+    let before = env.size();
+    env.set(name, val);
+    let after = env.size();
+    if after < before {
+        limits::release(before - after);
+        return Ok(());
+    }
+    if after > before {
+        hooks::allocate(after - before);
+    }
+    return limits::charge(after - before).map_err(memory_error);
+}
+
+/// Creates an environment for a call or catch block, charging for its
+/// overhead. It must be handed back to `release_env` when the block is done.
+fn new_charged_env(parent: Arc<Mutex<Environment<'static>>>) -> Result<Environment<'static>, Object<'static>> {
+    let env = new_enclosed_env(parent);
+    hooks::allocate(env.size());
+    limits::charge(env.size()).map_err(memory_error)?;
+    return Ok(env);
+}
+
+/// Credits an environment's bytes back unless a closure still holds it, in
+/// which case the cycle collector starts tracking it.
+fn release_env(env: Arc<Mutex<Environment<'static>>>) {
+    if Arc::strong_count(&env) == 1 {
+        if let Ok(env) = env.try_lock() {
+            limits::release(env.size());
+        }
+    } else {
+        gc::track(&env);
+    }
+}
+
 fn is_truthy(condition: Object) -> bool {
     return match condition {
         Object::Null => false,
-        Object::Boolean(b) => if b { true } else { false },
-        Object::Integer(i) => if i == 0 { false } else { true },
+        Object::Boolean(b) => b,
+        Object::Integer(i) => i != 0,
         Object::ReturnValue(r) => is_truthy(*r),
         _ => true,
     };
@@ -25,6 +104,9 @@ fn eval_program(p: Program, env: Arc<Mutex<Environment<'static>>>) -> Object<'st
     let mut result: Object = Object::Null;
     for i in 0..p.statements.len() {
         result = eval_statement(p.statements[i].clone(), env.clone());
+        if let Object::TailCall(..) = result {
+            result = resolve_tail_call(result, env.clone());
+        }
         if let Object::ReturnValue(r) = result{
             return *r;
         } else if let Object::Error(_) = result {
@@ -36,24 +118,99 @@ fn eval_program(p: Program, env: Arc<Mutex<Environment<'static>>>) -> Object<'st
 
 fn eval_block_statement(bs: BlockStatement, env: Arc<Mutex<Environment<'static>>>) -> Object<'static> {
     let mut result: Object = Object::Null;
-    for i in 0..bs.statements.len() {
-        result = eval_statement(bs.statements[i].clone(), env.clone());
-        if let Object::ReturnValue(_) = result {
+    for stmt in bs.statements {
+        result = eval_statement(stmt, env.clone());
+        if let Object::ReturnValue(_) | Object::TailCall(..) | Object::Error(_) = result {
             break;
         }
-        if let Object::Error(_) = result {
+    }
+    return result;
+}
+
+/// Evaluates a block whose value is the value of the enclosing function, so a
+/// call in its final expression can be deferred as a tail call.
+fn eval_tail_block_statement(bs: BlockStatement, env: Arc<Mutex<Environment<'static>>>) -> Object<'static> {
+    let mut result: Object = Object::Null;
+    let len = bs.statements.len();
+    for (i, stmt) in bs.statements.into_iter().enumerate() {
+        let tail = i == len - 1 && matches!(stmt, Statement::ExpressionStatement(_));
+        // the tail expression skips eval_statement, so it is hooked here
+        if tail {
+            if let Err(reason) = hooks::statement(&stmt, &env) {
+                let mut err = RuntimeError::new(ErrorKind::InterruptedError, reason);
+                err.position = Some(stmt.position());
+                result = Object::Error(err);
+                break;
+            }
+        }
+        result = match stmt {
+            Statement::ExpressionStatement(es) if tail => {
+                let position = es.position;
+                let val = eval_tail_expression(es.expression, env.clone());
+                hooks::end_statement();
+                if let Object::Error(mut err) = val {
+                    err.position.get_or_insert(position);
+                    Object::Error(err)
+                } else {
+                    val
+                }
+            },
+            _ => eval_statement(stmt, env.clone()),
+        };
+        if let Object::ReturnValue(_) | Object::TailCall(..) | Object::Error(_) = result {
             break;
         }
     }
     return result;
 }
 
+fn eval_tail_expression(e: Expression, env: Arc<Mutex<Environment<'static>>>) -> Object<'static> {
+    return match e {
+        Expression::CallExpression(ce) => eval_call_expression(ce, env, true),
+        Expression::IfExpression(ie) => {
+            let condition = eval_expression(*ie.condition, env.clone());
+            if let Object::Error(_) = condition {
+                condition
+            } else if is_truthy(condition) {
+                hooks::branch(&ie.consequence, true);
+                eval_tail_block_statement(ie.consequence, env)
+            } else {
+                hooks::branch(&ie.consequence, false);
+                match ie.alternative {
+                    Some(alt) => eval_tail_block_statement(alt, env),
+                    None => Object::Null,
+                }
+            }
+        },
+        _ => eval_expression(e, env),
+    };
+}
+
 fn eval_statement(s: Statement, env: Arc<Mutex<Environment<'static>>>) -> Object<'static> {
+    let position = s.position();
+    let result = match limits::tick().and_then(|_| hooks::statement(&s, &env)) {
+        Ok(()) => {
+            let result = eval_statement_inner(s, env);
+            hooks::end_statement();
+            result
+        },
+        Err(reason) => Object::Error(RuntimeError::new(ErrorKind::InterruptedError, reason)),
+    };
+    if let Object::Error(mut err) = result {
+        if err.position.is_none() {
+            err.position = Some(position);
+        }
+        return Object::Error(err);
+    }
+    return result;
+}
+
+fn eval_statement_inner(s: Statement, env: Arc<Mutex<Environment<'static>>>) -> Object<'static> {
     return match s {
         Statement::ExpressionStatement(es) => eval_expression(es.expression, env.clone()),
         Statement::ReturnStatement(rs) => {
-            let val = eval_expression(rs.return_val, env);
-            if let Object::Error(_) = val {
+            let val = eval_tail_expression(rs.return_val, env);
+            if let Object::Error(_) | Object::TailCall(..) = val {
                 val
             } else {
                 Object::ReturnValue(Box::new(val))
@@ -65,24 +222,35 @@ fn eval_statement(s: Statement, env: Arc<Mutex<Environment<'static>>>) -> Object
                 if let Object::Error(_) = val {
                     val
                 } else {
-                    let mut env = env.try_lock().expect("Error locking env");
-                    env.set(ls.name.value, val.clone());
-                    val
+                    let mut env = lock_env(&env);
+                    match bind(&mut env, ls.name.value, val.clone()) {
+                        Ok(()) => val,
+                        Err(err) => err,
+                    }
                 }
             } else {
-                new_error!("variable does not have initial value: {}", ls.name.value)
+                new_error!(ErrorKind::ValueError, "variable does not have initial value: {}", ls.name.value)
             }
         },
         Statement::ImportStatement(is) => {
-            env.try_lock().expect("Error locking env").imports.insert(0, is.namespace.value.clone());
+            lock_env(&env).imports.insert(0, is.namespace.value.clone());
             Object::Null
-        }
+        },
+        Statement::ThrowStatement(ts) => {
+            let val = eval_expression(ts.value, env);
+            match val {
+                Object::Error(_) => val,
+                Object::Exception(err) => Object::Error(err),
+                Object::String(message) => Object::Error(RuntimeError::new(ErrorKind::Error, message)),
+                _ => Object::Error(RuntimeError::new(ErrorKind::Error, val.inspect())),
+            }
+        },
     };
 }
 
 fn eval_bang(right: Object) -> Object {
     return match right {
-        Object::Boolean(b) => if b == true { Object::Boolean(false) } else { Object::Boolean(true) },
+        Object::Boolean(b) => Object::Boolean(!b),
         Object::Null => Object::Boolean(true),
         _ => Object::Boolean(false),
     };
@@ -90,8 +258,11 @@ fn eval_bang(right: Object) -> Object {
 
 fn eval_minus(right: Object) -> Object {
     return match right {
-        Object::Integer(i) => Object::Integer(-1*i),
-        _ => new_error!("unknown operator: -{}", get_type(&right).as_str()),
+        Object::Integer(i) => match i.checked_neg() {
+            Some(negated) => Object::Integer(negated),
+            None => new_error!(ErrorKind::OverflowError, "integer overflow: -({})", i),
+        },
+        _ => new_error!(ErrorKind::TypeError, "unknown operator: -{}", get_type(&right).as_str()),
     };
 }
 
@@ -99,22 +270,42 @@ fn eval_prefix_expression(op: String, right: Object) -> Object {
     return match op.as_str() {
         "!" => eval_bang(right),
         "-" => eval_minus(right),
-        _ => new_error!("unknown operator: {}{}", op.as_str(), get_type(&right).as_str()),
+        _ => new_error!(ErrorKind::TypeError, "unknown operator: {}{}", op.as_str(), get_type(&right).as_str()),
     };
 }
 
 fn eval_infix_int_expression(op: String, left: i32, right: i32) -> Object<'static> {
+    let result = match op.as_str() {
+        "+" => left.checked_add(right),
+        "-" => left.checked_sub(right),
+        "*" => left.checked_mul(right),
+        "/" => {
+            if right == 0 {
+                new_error!(ErrorKind::ZeroDivisionError, "division by zero: {} / {}", left, right)
+            }
+            left.checked_div(right)
+        },
+        "**" => {
+            let Ok(exponent) = u32::try_from(right) else {
+                new_error!(ErrorKind::ValueError, "negative exponent: {} ** {}", left, right)
+            };
+            left.checked_pow(exponent)
+        },
+        _ => return eval_int_comparison(op, left, right),
+    };
+    return match result {
+        Some(i) => Object::Integer(i),
+        None => new_error!(ErrorKind::OverflowError, "integer overflow: {} {} {}", left, op, right),
+    };
+}
+
+fn eval_int_comparison(op: String, left: i32, right: i32) -> Object<'static> {
     return match op.as_str() {
-        "+" => Object::Integer(left + right),
-        "-" => Object::Integer(left - right),
-        "*" => Object::Integer(left * right),
-        "/" => Object::Integer(left / right),
-        "**" => Object::Integer(i32::pow(left, right.try_into().unwrap())),
         "<" => Object::Boolean(left < right),
         ">" => Object::Boolean(left > right),
         "==" => Object::Boolean(left == right),
         "!=" => Object::Boolean(left != right),
-        _ => new_error!("unknown operator: {} {} {}", left, op, right),
+        _ => new_error!(ErrorKind::TypeError, "unknown operator: {} {} {}", left, op, right),
     };
 }
 
@@ -128,8 +319,14 @@ fn eval_infix_bool_expression(op: String, left: bool, right: bool) -> Object<'st
 
 fn eval_infix_string_expression(op: String, left: String, right: String) -> Object<'static> {
     return match op.as_str() {
-        "+" => Object::String(left + &right),
-        _ => new_error!("unknown operator: STRING {} STRING", op),
+        "+" => {
+            if let Err(max) = limits::reserve(left.len() + right.len()) {
+                return memory_error(max);
+            }
+            hooks::allocate(left.len() + right.len());
+            Object::String(left + &right)
+        },
+        _ => new_error!(ErrorKind::TypeError, "unknown operator: STRING {} STRING", op),
     }
 }
 
@@ -144,25 +341,24 @@ fn eval_infix_expression(op: String, left: &Object<'static>, right: &Object<'sta
                 if let Object::Integer(r) = right {
                     eval_infix_int_expression(op, *l, *r)
                 } else {
-                    new_error!("type mismatch: {} {} {}", get_type(left), op, get_type(right))
+                    new_error!(ErrorKind::TypeError, "type mismatch: {} {} {}", get_type(left), op, get_type(right))
                 }
             },
             Object::String(l) => {
                 if let Object::String(r) = right {
                     eval_infix_string_expression(op, l.clone(), r.clone())
                 } else {
-                    new_error!("type mismatch: {} {} {}", get_type(left), op, get_type(right))
+                    new_error!(ErrorKind::TypeError, "type mismatch: {} {} {}", get_type(left), op, get_type(right))
                 }
             },
-            _ => new_error!("unknown operator: {} {} {}", get_type(left), op, get_type(right))
+            _ => new_error!(ErrorKind::TypeError, "unknown operator: {} {} {}", get_type(left), op, get_type(right))
         }
     };
 }
 
-fn eval_expressions(exps: Vec<Box<Expression>>, env: Arc<Mutex<Environment<'static>>>) -> Vec<Object<'static>> {
+fn eval_expressions(exps: Vec<Expression>, env: Arc<Mutex<Environment<'static>>>) -> Vec<Object<'static>> {
     let mut objs: Vec<Object> = Vec::new();
-    for i in 0..exps.len() {
-        let exp = *exps[i].clone();
+    for exp in exps {
         let evaluated = eval_expression(exp, env.clone());
         if let Object::Error(_) = evaluated {
             return vec![evaluated];
@@ -172,20 +368,109 @@ fn eval_expressions(exps: Vec<Box<Expression>>, env: Arc<Mutex<Environment<'stat
     return objs;
 }
 
-fn get_std_string_func(f: &String) -> Option<fn(&Vec<Object<'static>>)->Object<'static>> {
-    return match f.as_str() {
+fn get_std_string_func(f: &str) -> Option<fn(&Vec<Object<'static>>)->Object<'static>> {
+    return match f {
         "strlen" => Some(move |args| {
-            if let Object::String(s) = args[0].clone() {
-                Object::Integer(s.len().try_into().unwrap())
+            if let Err(err) = check_args("strlen", args, 1) {
+                return err;
+            }
+            if let Object::String(s) = &args[0] {
+                match i32::try_from(s.len()) {
+                    Ok(len) => Object::Integer(len),
+                    Err(_) => new_error!(ErrorKind::OverflowError, "string of {} bytes is too long to measure", s.len()),
+                }
             } else {
-                new_error!("std.string.len must be passed a STRING, got {}", get_type(&args[0]))
+                new_error!(ErrorKind::TypeError, "std.string.len must be passed a STRING, got {}", get_type(&args[0]))
             }
         }),
         _ => None,
     };
 }
 
-fn get_std_func(f: &String, imports: Vec<String>) -> Option<Object<'static>> {
+/// Checks that a builtin was passed `expected` arguments.
+fn check_args(name: &str, args: &[Object<'static>], expected: usize) -> Result<(), Object<'static>> {
+    if args.len() != expected {
+        return Err(Object::Error(RuntimeError::new(
+            ErrorKind::TypeError,
+            format!("wrong number of arguments to {}: expected {}, got {}", name, expected, args.len()),
+        )));
+    }
+    return Ok(());
+}
+
+/// The lines of `expected` and `actual` marked as the same (` `), only in
+/// `expected` (`-`) or only in `actual` (`+`), from their longest common
+/// subsequence of lines.
+fn line_diff(expected: &str, actual: &str) -> String {
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+    // common[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] { common[i + 1][j + 1] + 1 } else { common[i + 1][j].max(common[i][j + 1]) };
+        }
+    }
+    let mut lines: Vec<String> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(format!("  {}", a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("- {}", a[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", b[j]));
+            j += 1;
+        }
+    }
+    return lines.join("\n");
+}
+
+fn get_std_test_func(f: &str) -> Option<fn(&Vec<Object<'static>>)->Object<'static>> {
+    return match f {
+        "assert" => Some(|args| {
+            if let Err(err) = check_args("assert", args, 1) {
+                return err;
+            }
+            if !is_truthy(args[0].clone()) {
+                new_error!(ErrorKind::AssertionError, "assertion failed: got {}", args[0].inspect())
+            }
+            Object::Null
+        }),
+        "assert_eq" => Some(|args| {
+            if let Err(err) = check_args("assert_eq", args, 2) {
+                return err;
+            }
+            if args[0] != args[1] || get_type(&args[0]) != get_type(&args[1]) {
+                let (actual, expected) = (args[0].inspect(), args[1].inspect());
+                let diff = if actual == expected {
+                    format!("  {} (expected {}, got {})", actual, get_type(&args[1]), get_type(&args[0]))
+                } else {
+                    line_diff(&expected, &actual)
+                };
+                new_error!(ErrorKind::AssertionError, "values differ (- expected, + actual):\n{}", diff)
+            }
+            Object::Null
+        }),
+        "assert_error" => Some(|args| {
+            if let Err(err) = check_args("assert_error", args, 1) {
+                return err;
+            }
+            let env = Arc::new(Mutex::new(Environment::new()));
+            return match apply_function(args[0].clone(), Vec::new(), env, String::new()) {
+                Object::Error(err) if err.kind.is_catchable() => Object::Exception(err),
+                Object::Error(err) => Object::Error(err),
+                other => new_error!(ErrorKind::AssertionError, "expected an error, got {}", other.inspect()),
+            };
+        }),
+        _ => None,
+    };
+}
+
+fn get_std_func(f: &str, imports: Vec<String>) -> Option<Object<'static>> {
     let mut ret: Option<Object<'static>> = None;
 
     imports.iter().for_each(|namespace| {
@@ -196,13 +481,178 @@ fn get_std_func(f: &String, imports: Vec<String>) -> Option<Object<'static>> {
                     return;
                 }
             }
+            "std.test" => {
+                if let Some(fun) = get_std_test_func(f) {
+                    ret = Some(Object::Builtin(fun));
+                    return;
+                }
+            }
             _ => (),
         };
     });
     return ret;
 }
 
+fn eval_try_expression(te: TryExpression, env: Arc<Mutex<Environment<'static>>>) -> Object<'static> {
+    // tail calls are run here rather than deferred so that errors they raise
+    // are still caught and the finally block runs after them
+    let mut result = eval_block_statement(te.body, env.clone());
+    result = resolve_tail_call(result, env.clone());
+    if let Object::Error(err) = result {
+        result = match te.handler {
+            _ if !err.kind.is_catchable() => return Object::Error(err),
+            Some(handler) => {
+                let mut catch_env = match new_charged_env(env.clone()) {
+                    Ok(catch_env) => catch_env,
+                    Err(err) => return err,
+                };
+                let mut bound = Ok(());
+                if let Some(param) = te.parameter {
+                    bound = bind(&mut catch_env, param.value, Object::Exception(err));
+                }
+                let catch_env = Arc::new(Mutex::new(catch_env));
+                let handled = match bound {
+                    Ok(()) => {
+                        let handled = eval_block_statement(handler, catch_env.clone());
+                        resolve_tail_call(handled, catch_env.clone())
+                    },
+                    Err(err) => err,
+                };
+                release_env(catch_env);
+                handled
+            },
+            None => Object::Error(err),
+        };
+    }
+
+    // a finally block only replaces the result if it errors or returns itself
+    if let Some(finalizer) = te.finalizer {
+        let finished = eval_block_statement(finalizer, env.clone());
+        let finished = resolve_tail_call(finished, env);
+        if let Object::Error(_) | Object::ReturnValue(_) = finished {
+            return finished;
+        }
+    }
+    return result;
+}
+
+fn eval_member_expression(object: Object<'static>, property: &String) -> Object<'static> {
+    return match object {
+        Object::Exception(err) => match property.as_str() {
+            "message" => Object::String(err.message),
+            "kind" => Object::String(err.kind.as_str().to_string()),
+            "line" => err.position.map_or(Object::Null, |pos| Object::Integer(pos.line as i32)),
+            "column" => err.position.map_or(Object::Null, |pos| Object::Integer(pos.column as i32)),
+            _ => new_error!(ErrorKind::NameError, "unknown property: ERROR.{}", property),
+        },
+        _ => new_error!(ErrorKind::TypeError, "unknown property: {}.{}", get_type(&object), property),
+    };
+}
+
+fn eval_call_expression(ce: CallExpression, env: Arc<Mutex<Environment<'static>>>, tail: bool) -> Object<'static> {
+    // the name is only for hooks, so it isn't worked out without one
+    let name = if hooks::active() { ce.function.to_string() } else { String::new() };
+    let function = eval_expression(*ce.function, env.clone());
+    if let Object::Error(_) = function {
+        return function;
+    }
+    let args = eval_expressions(ce.arguments, env.clone());
+    if let Some(Object::Error(_)) = args.first() {
+        return args[0].clone();
+    }
+    if tail {
+        if let Object::Function(f) = function {
+            return Object::TailCall(Box::new(f), args, name);
+        }
+    }
+    return apply_function(function, args, env, name);
+}
+
+/// Runs a deferred tail call that was returned from somewhere other than a
+/// function body, wrapping its value back up as the return it came from.
+fn resolve_tail_call(obj: Object<'static>, env: Arc<Mutex<Environment<'static>>>) -> Object<'static> {
+    if let Object::TailCall(f, args, name) = obj {
+        let val = apply_function(Object::Function(*f), args, env, name);
+        if let Object::Error(_) = val {
+            return val;
+        }
+        return Object::ReturnValue(Box::new(val));
+    }
+    return obj;
+}
+
+fn apply_function(function: Object<'static>, args: Vec<Object<'static>>, env: Arc<Mutex<Environment<'static>>>, name: String) -> Object<'static> {
+    let mut f = match function {
+        Object::Function(f) => f,
+        Object::Builtin(b) => return b(&args),
+        _ => new_error!(ErrorKind::TypeError, "not a function: {}", get_type(&function)),
+    };
+    if let Err(max_depth) = limits::enter_call() {
+        new_error!(ErrorKind::StackOverflowError, "stack overflow: maximum call depth of {} exceeded", max_depth)
+    }
+
+    let mut args = args;
+    let mut name = name;
+    // calls in tail position come back as TailCall and are run by this loop
+    // instead of recursing
+    let ret = loop {
+        if args.len() != f.parameters.len() {
+            break Object::Error(RuntimeError::new(
+                ErrorKind::TypeError,
+                format!("wrong number of arguments: expected {}, got {}", f.parameters.len(), args.len()),
+            ));
+        }
+        let parent = f.env.clone().unwrap_or(env.clone());
+        // the frame is the call's, but the hook only hears of the call once
+        // the frame exists
+        let (frame, allocations) = hooks::hold_allocations(|| {
+            let mut local_env = new_charged_env(parent)?;
+            let mut bound = Ok(());
+            for (param, arg) in f.parameters.iter().zip(args) {
+                bound = bound.and_then(|_| bind(&mut local_env, param.value.clone(), arg));
+            }
+            return Ok((local_env, bound));
+        });
+        let (local_env, bound) = match frame {
+            Ok(frame) => frame,
+            Err(err) => {
+                hooks::report_allocations(allocations);
+                break err;
+            },
+        };
+        let local_env = Arc::new(Mutex::new(local_env));
+        let ret = match bound {
+            Ok(()) => {
+                hooks::enter_call(&name, &f, &local_env);
+                hooks::report_allocations(allocations);
+                let ret = eval_tail_block_statement(f.body, local_env.clone());
+                hooks::exit_call();
+                ret
+            },
+            Err(err) => {
+                hooks::report_allocations(allocations);
+                err
+            },
+        };
+        release_env(local_env);
+        match ret {
+            Object::TailCall(next, next_args, next_name) => {
+                f = *next;
+                args = next_args;
+                name = next_name;
+            },
+            Object::ReturnValue(rv) => break *rv,
+            other => break other,
+        }
+    };
+    limits::exit_call();
+    return ret;
+}
+
 fn eval_expression(e: Expression, env: Arc<Mutex<Environment<'static>>>) -> Object<'static> {
+    if let Err(reason) = limits::tick() {
+        new_error!(ErrorKind::InterruptedError, "{}", reason)
+    }
     return match e {
         Expression::IntegerLiteral(i) => Object::Integer(i.value),
         Expression::Boolean(b) => Object::Boolean(b.value),
@@ -223,8 +673,7 @@ fn eval_expression(e: Expression, env: Arc<Mutex<Environment<'static>>>) -> Obje
             } else if let Object::Error(_) = right {
                 right
             } else {
-                let val = eval_infix_expression(ie.operator, &left, &right);
-                val
+                eval_infix_expression(ie.operator, &left, &right)
             }
         },
         Expression::IfExpression(ie) => {
@@ -232,25 +681,25 @@ fn eval_expression(e: Expression, env: Arc<Mutex<Environment<'static>>>) -> Obje
             if let Object::Error(_) = condition {
                 condition
             } else if is_truthy(condition) {
-                let val = eval(Node::BlockStatement(ie.consequence), env.clone());
-                val
+                hooks::branch(&ie.consequence, true);
+                eval_block_statement(ie.consequence, env.clone())
             } else {
+                hooks::branch(&ie.consequence, false);
                 if let Some(alt) = ie.alternative {
-                    let val = eval(Node::BlockStatement(alt), env.clone());
-                    val
+                    eval_block_statement(alt, env.clone())
                 } else {
                     Object::Null
                 }
             }
         }
         Expression::Identifier(i) => {
-            let e = env.try_lock().expect("Error locking env");
+            let e = lock_env(&env);
             if let Some(v) = e.get(&i.value) {
                 v
             } else if let Some(f) = get_std_func(&i.value, e.imports.clone()) {
                 f
             } else {
-                new_error!("unknown identifier: {}", i.value)
+                new_error!(ErrorKind::NameError, "unknown identifier: {}", i.value)
             }
         },
         Expression::FunctionLiteral(fl) => {
@@ -259,41 +708,66 @@ fn eval_expression(e: Expression, env: Arc<Mutex<Environment<'static>>>) -> Obje
             
             Object::Function(Function { parameters, body, env: Some(env) })
         }
-        Expression::CallExpression(ce) => {
-            let function = eval_expression(*ce.function, env.clone());
-            if let Object::Error(_) = function {
-                function
+        Expression::CallExpression(ce) => eval_call_expression(ce, env, false),
+        Expression::TryExpression(te) => eval_try_expression(te, env),
+        Expression::MemberExpression(me) => {
+            let object = eval_expression(*me.object, env);
+            if let Object::Error(_) = object {
+                object
             } else {
-                let args = eval_expressions(ce.arguments, env.clone());
-                if let Object::Error(_) = args[0] {
-                    args[0].clone()
-                } else if let Object::Function(f) = function {
-                    let mut local_env = new_enclosed_env(env);
-                    for i in 0..args.len() {
-                        local_env.set(f.parameters[i].value.clone(), args[i].clone());
-                    }
-                    let ret = eval_block_statement(f.body, Arc::new(Mutex::new(local_env)));
-                    if let Object::ReturnValue(rv) = ret {
-                        *rv
-                    } else {
-                        ret
-                    }
-                } else if let Object::Builtin(f) = function {
-                    f(&args)
-                } else {
-                    new_error!("not a function: {}", get_type(&function))
-                }
+                eval_member_expression(object, &me.property.value)
             }
         },
         Expression::Empty => Object::Null,
     };
 }
 
+/// Evaluates `node` in `env` with the default limits. Unless the caller is
+/// already on a stack set up by `parser::with_stack` with room for them,
+/// this starts a thread with a stack of `limits::stack_size`, about 190MiB
+/// of address space for the default depth, and waits for it.
 pub fn eval(node: Node, env: Arc<Mutex<Environment<'static>>>) -> Object<'static> {
-    return match node {
+    return eval_with_limits(node, env, Limits::default());
+}
+
+/// Like `eval`, but with `limits` in force instead of the defaults.
+pub fn eval_with_limits(node: Node, env: Arc<Mutex<Environment<'static>>>, limits: Limits) -> Object<'static> {
+    gc::track(&env);
+    return limits::with_limits(limits, || eval_node(node, env)).unwrap_or_else(not_started);
+}
+
+/// Like `eval_with_limits`, but calling `hook` as the evaluation runs. The
+/// hook is handed back with the result.
+pub fn eval_with_hook(
+    node: Node,
+    env: Arc<Mutex<Environment<'static>>>,
+    limits: Limits,
+    hook: Box<dyn Hook + Send>,
+) -> (Object<'static>, Box<dyn Hook + Send>) {
+    gc::track(&env);
+    // the hook is handed back even if the evaluation never starts
+    let slot = Mutex::new(Some(hook));
+    let result = limits::with_limits(limits, || {
+        let outer = hooks::install(slot.lock().unwrap_or_else(|e| e.into_inner()).take());
+        let result = eval_node(node, env);
+        *slot.lock().unwrap_or_else(|e| e.into_inner()) = hooks::install(outer);
+        result
+    });
+    let hook = slot.into_inner().unwrap_or_else(|e| e.into_inner()).expect("hook removed during evaluation");
+    return (result.unwrap_or_else(not_started), hook);
+}
+
+/// The error for an evaluation whose thread couldn't be started.
+fn not_started(message: String) -> Object<'static> {
+    return Object::Error(RuntimeError::new(ErrorKind::MemoryError, message));
+}
+
+fn eval_node(node: Node, env: Arc<Mutex<Environment<'static>>>) -> Object<'static> {
+    let result = match node {
         Node::Program(p) => eval_program(p, env.clone()),
         Node::Statement(s) => eval_statement(s, env.clone()),
         Node::Expression(e) => eval_expression(e, env.clone()),
         Node::BlockStatement(bs) => eval_block_statement(bs, env.clone()),
     };
+    return resolve_tail_call(result, env);
 }
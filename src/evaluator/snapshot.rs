@@ -0,0 +1,108 @@
+use std::sync::{Arc, Mutex};
+use crate::ast::Node;
+use crate::lexer::Lexer;
+use crate::object::{lock_env, Environment, ErrorKind, Object};
+use crate::parser::Parser;
+
+// A snapshot is Monkey source that rebuilds an environment when evaluated:
+// its imports, then a `let` for every binding. Closures are rebuilt by
+// wrapping their literal in calls that bind the variables they captured.
+
+/// The source for a saved environment, and the names of any bindings whose
+/// values could not be written out.
+pub struct Snapshot {
+    pub source: String,
+    pub skipped: Vec<String>,
+}
+
+type Env = Arc<Mutex<Environment<'static>>>;
+
+fn own_bindings(env: &Env) -> Vec<(String, Object<'static>)> {
+    let env = lock_env(env);
+    return env.bindings().into_iter().map(|(name, val)| (name.clone(), val.clone())).collect();
+}
+
+/// Source for a function literal evaluated in `env`, which is `global` or
+/// nested inside it. `open` holds the environments already being written
+/// out, so closures that capture each other don't recurse forever.
+fn closure(literal: String, env: &Env, global: &Env, open: &mut Vec<Env>) -> Option<String> {
+    if Arc::ptr_eq(env, global) || open.iter().any(|e| Arc::ptr_eq(e, env)) {
+        return Some(literal);
+    }
+    open.push(env.clone());
+    let mut lets: Vec<String> = Vec::new();
+    for (name, val) in own_bindings(env) {
+        let val = match &val {
+            // functions defined alongside this one are rebuilt in the same scope
+            Object::Function(f) if f.env.as_ref().is_some_and(|e| Arc::ptr_eq(e, env)) => val.inspect(),
+            _ => value(&val, global, open)?,
+        };
+        lets.push(format!("let {} = {};", name, val));
+    }
+    open.pop();
+    let wrapped = format!("fn() {{ {} {} }}()", lets.join(" "), literal);
+    let parent = lock_env(env).parent();
+    return match parent {
+        Some(parent) => closure(wrapped, &parent, global, open),
+        None => Some(wrapped),
+    };
+}
+
+/// Source that evaluates to `obj`, if there is any.
+fn value(obj: &Object<'static>, global: &Env, open: &mut Vec<Env>) -> Option<String> {
+    return match obj {
+        Object::Integer(i32::MIN) => Some(format!("({} - 1)", i32::MIN + 1)),
+        Object::Integer(i) => Some(i.to_string()),
+        Object::Boolean(b) => Some(b.to_string()),
+        Object::String(s) if !s.contains('"') => Some(format!("\"{}\"", s)),
+        Object::Null => Some(String::from("if (false) { 0 }")),
+        Object::Exception(e) if e.kind == ErrorKind::Error && !e.message.contains('"') => {
+            Some(format!("try {{ throw \"{}\" }} catch (e) {{ e }}", e.message))
+        },
+        Object::Function(f) => match &f.env {
+            Some(env) => closure(obj.inspect(), env, global, open),
+            None => Some(obj.inspect()),
+        },
+        // a builtin is written as its name, which the saved imports bring back
+        Object::Builtin(b) => super::STD_FUNCTIONS.iter()
+            .find(|sf| matches!(
+                super::get_std_func(sf.name, vec![sf.namespace.to_string()]),
+                Some(Object::Builtin(f)) if std::ptr::fn_addr_eq(f, *b)
+            ))
+            .map(|sf| sf.name.to_string()),
+        _ => None,
+    };
+}
+
+/// Writes out the bindings and imports of the global environment `env`.
+pub fn save(env: &Env) -> Snapshot {
+    let mut source = String::new();
+    let mut skipped: Vec<String> = Vec::new();
+    let imports = lock_env(env).imports.clone();
+    // imports are kept newest first, so replay them oldest first
+    for namespace in imports.iter().rev() {
+        source += &format!("import \"{}\";\n", namespace);
+    }
+    for (name, val) in own_bindings(env) {
+        match value(&val, env, &mut Vec::new()) {
+            Some(val) => source += &format!("let {} = {};\n", name, val),
+            None => skipped.push(name),
+        }
+    }
+    return Snapshot { source, skipped };
+}
+
+/// Rebuilds an environment from a snapshot's source.
+pub fn restore(source: &str) -> Result<Env, String> {
+    let mut p = Parser::new(Lexer::new(source));
+    let program = p.parse_program();
+    if let Some(e) = p.errors.first() {
+        return Err(format!("invalid snapshot: {} (line {}, column {})", e.message, e.position.line, e.position.column));
+    }
+    let program = program.ok_or_else(|| String::from("invalid snapshot"))?;
+    let env = Arc::new(Mutex::new(Environment::new()));
+    if let Object::Error(e) = super::eval(Node::Program(program), env.clone()) {
+        return Err(format!("invalid snapshot: {}", e.inspect()));
+    }
+    return Ok(env);
+}
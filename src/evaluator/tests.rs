@@ -1,260 +1,301 @@
 #[cfg(test)]
 
 #[test]
-fn eval_integer_expression() {
+fn recursion_depth_limit() {
     use std::sync::{Arc, Mutex};
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use crate::object::*;
-    use crate::evaluator::eval;
+    use crate::evaluator::{eval_with_limits, Limits};
 
-    let input = "5";
-    let l = Lexer::new(input);
-    let mut p = Parser::new(l);
+    let input = "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; try { f(1000) } catch (e) { e.kind }";
+    let mut p = Parser::new(Lexer::new(input));
     let program = p.parse_program().unwrap();
     let env = Arc::new(Mutex::new(Environment::new()));
-    let obj = eval(crate::ast::Node::Program(program), env);
-    if let Object::Integer(i) = obj {
-        assert_eq!(5, i);
-    } else {
-        panic!("obj is not an Integer");
-    }
+    let obj = eval_with_limits(crate::ast::Node::Program(program), env, Limits { max_depth: 50, ..Limits::default() });
+    assert_eq!(Object::String(String::from("StackOverflowError")), obj);
 }
 
 #[test]
-fn eval_boolean_expression() {
+fn huge_depth_limits() {
     use std::sync::{Arc, Mutex};
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use crate::object::*;
-    use crate::evaluator::eval;
+    use crate::evaluator::{eval_with_limits, Limits};
+    use crate::evaluator::limits::stack_size;
 
-    let input = "true";
-    let l = Lexer::new(input);
-    let mut p = Parser::new(l);
+    assert_eq!(stack_size(usize::MAX), stack_size(usize::MAX / 2));
+    assert!(stack_size(1000) < stack_size(usize::MAX));
+
+    let input = "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(100)";
+    let mut p = Parser::new(Lexer::new(input));
     let program = p.parse_program().unwrap();
     let env = Arc::new(Mutex::new(Environment::new()));
-    let obj = eval(crate::ast::Node::Program(program), env);
-    if let Object::Boolean(b) = obj {
-        assert_eq!(true, b);
-    } else {
-        panic!("obj is not an Boolean");
-    }
+    let obj = eval_with_limits(crate::ast::Node::Program(program), env, Limits { max_depth: usize::MAX, ..Limits::default() });
+    assert_eq!(Object::Integer(100), obj);
+}
+
+#[test]
+fn eval_reuses_a_large_stack() {
+    use std::thread;
+    use crate::evaluator::{with_eval_stack, Limits};
+    use crate::evaluator::limits::{tick, with_limits};
+
+    let outside = thread::current().id();
+    assert_ne!(outside, with_limits(Limits::default(), || thread::current().id()).unwrap());
+    with_eval_stack(|| {
+        let caller = thread::current().id();
+        // repeated and nested evaluations stay on the caller's thread
+        for _ in 0..3 {
+            assert_eq!(caller, with_limits(Limits::default(), || thread::current().id()).unwrap());
+        }
+        // an inner evaluation leaves the outer one's budget alone
+        let outer = with_limits(Limits { max_steps: Some(1), ..Limits::default() }, || {
+            assert_eq!(Ok(()), tick());
+            assert_eq!(Ok(Ok(())), with_limits(Limits::default(), tick));
+            tick()
+        });
+        assert_eq!(Ok(Err(String::from("step budget of 1 exhausted"))), outer);
+        // a deeper limit than the stack has room for still gets its own thread
+        assert_ne!(caller, with_limits(Limits { max_depth: 100_000, ..Limits::default() }, || thread::current().id()).unwrap());
+    }).unwrap();
 }
 
 #[test]
-fn eval_bang_expression() {
+fn step_budget() {
     use std::sync::{Arc, Mutex};
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use crate::object::*;
-    use crate::evaluator::eval;
+    use crate::evaluator::{eval_with_limits, Limits};
 
-    let input = "!true";
-    let l = Lexer::new(input);
-    let mut p = Parser::new(l);
+    // the budget can't be caught by the script itself
+    let input = "let spin = fn(n) { spin(n + 1) }; try { spin(0) } catch (e) { \"caught\" }";
+    let mut p = Parser::new(Lexer::new(input));
     let program = p.parse_program().unwrap();
     let env = Arc::new(Mutex::new(Environment::new()));
-    let obj = eval(crate::ast::Node::Program(program), env);
-    if let Object::Boolean(b) = obj {
-        assert_eq!(false, b);
+    let limits = Limits { max_steps: Some(10000), ..Limits::default() };
+    let obj = eval_with_limits(crate::ast::Node::Program(program), env, limits);
+    if let Object::Error(e) = obj {
+        assert_eq!(ErrorKind::InterruptedError, e.kind);
+        assert_eq!("step budget of 10000 exhausted", e.message);
     } else {
-        panic!("obj is not an Boolean");
+        panic!("obj is not an Error: {:?}", obj);
     }
 }
 
 #[test]
-fn eval_minus_expression() {
+fn timeout() {
     use std::sync::{Arc, Mutex};
+    use std::time::Duration;
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use crate::object::*;
-    use crate::evaluator::eval;
+    use crate::evaluator::{eval_with_limits, Limits};
 
-    let input = "-10";
-    let l = Lexer::new(input);
-    let mut p = Parser::new(l);
+    let input = "let spin = fn(n) { spin(n + 1) }; spin(0)";
+    let mut p = Parser::new(Lexer::new(input));
     let program = p.parse_program().unwrap();
     let env = Arc::new(Mutex::new(Environment::new()));
-    let obj = eval(crate::ast::Node::Program(program), env);
-    if let Object::Integer(i) = obj {
-        assert_eq!(-10, i);
+    let limits = Limits { timeout: Some(Duration::from_millis(50)), ..Limits::default() };
+    let obj = eval_with_limits(crate::ast::Node::Program(program), env, limits);
+    if let Object::Error(e) = obj {
+        assert_eq!(ErrorKind::InterruptedError, e.kind);
+        assert_eq!("timed out after 50ms", e.message);
     } else {
-        panic!("obj is not an Integer");
+        panic!("obj is not an Error: {:?}", obj);
     }
 }
 
 #[test]
-fn eval_infix_int_expression() {
+fn cancellation() {
     use std::sync::{Arc, Mutex};
+    use std::time::Duration;
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use crate::object::*;
-    use crate::evaluator::eval;
+    use crate::evaluator::{eval_with_limits, CancelHandle, Limits};
 
-    let tests = vec![
-        (String::from("3 * 6"), 18),
-        (String::from("6 / 3"), 2),
-        (String::from("9 + 9"), 18),
-        (String::from("9 - 9"), 0),
-    ];
-
-    tests.iter().for_each(|(i, o)| {
-        let mut p = Parser::new(Lexer::new(i.as_str()));
-        let program = p.parse_program().unwrap();
-        let env = Arc::new(Mutex::new(Environment::new()));
-        let obj = eval(crate::ast::Node::Program(program), env);
-        println!("{:?}", obj);
-        if let Object::Integer(i) = obj {
-            assert_eq!(*o, i);
-        } else {
-            panic!("obj is not an Integer");
-        }
+    let input = "let spin = fn(n) { spin(n + 1) }; spin(0)";
+    let mut p = Parser::new(Lexer::new(input));
+    let program = p.parse_program().unwrap();
+    let env = Arc::new(Mutex::new(Environment::new()));
+    let cancel = CancelHandle::new();
+    let canceller = cancel.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        canceller.cancel();
     });
+    let limits = Limits { cancel: Some(cancel), ..Limits::default() };
+    let obj = eval_with_limits(crate::ast::Node::Program(program), env, limits);
+    if let Object::Error(e) = obj {
+        assert_eq!(ErrorKind::InterruptedError, e.kind);
+        assert_eq!("evaluation cancelled", e.message);
+    } else {
+        panic!("obj is not an Error: {:?}", obj);
+    }
 }
 
 #[test]
-fn eval_infix_bool_expression() {
+fn memory_limit() {
     use std::sync::{Arc, Mutex};
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use crate::object::*;
-    use crate::evaluator::eval;
+    use crate::evaluator::{eval_with_limits, Limits};
 
-    let tests = vec![
-        (String::from("1 == 1"), true),
-        (String::from("6 < 3"), false),
-        (String::from("4 != 9"), true),
-        (String::from("9 > 9"), false),
-        (String::from("true == true"), true),
-        (String::from("true != true"), false),
-        (String::from("false == true"), false),
-        (String::from("false != true"), true),
-        (String::from("false < 17"), false),
-    ];
+    // memory held by the failed calls is released once they unwind, so the
+    // script can keep going after catching the error
+    let input = "import \"std.string\";
+    let grow = fn(s) { grow(s + s) };
+    let kind = try { grow(\"a\") } catch (e) { e.kind };
+    let big = fn(s, n) { if (n == 0) { s } else { big(s + s, n - 1) } };
+    if (kind == \"MemoryError\") { strlen(big(\"a\", 10)) } else { 0 }";
+    let mut p = Parser::new(Lexer::new(input));
+    let program = p.parse_program().unwrap();
+    let env = Arc::new(Mutex::new(Environment::new()));
+    let limits = Limits { max_memory: Some(64 * 1024), ..Limits::default() };
+    let obj = eval_with_limits(crate::ast::Node::Program(program), env, limits);
+    assert_eq!(Object::Integer(1024), obj);
 
-    tests.iter().for_each(|(i, o)| {
-        let mut p = Parser::new(Lexer::new(i.as_str()));
-        let program = p.parse_program().unwrap();
-        let env = Arc::new(Mutex::new(Environment::new()));
-        let obj = eval(crate::ast::Node::Program(program), env);
-        println!("{} {:?}", i, obj);
-        if let Object::Boolean(i) = obj {
-            assert_eq!(*o, i);
-        } else {
-            panic!("obj is not an Boolean");
-        }
-    });
+    let input = "let grow = fn(s) { grow(s + s) }; try { grow(\"a\") } catch (e) { e.message }";
+    let mut p = Parser::new(Lexer::new(input));
+    let program = p.parse_program().unwrap();
+    let env = Arc::new(Mutex::new(Environment::new()));
+    let limits = Limits { max_memory: Some(64 * 1024), ..Limits::default() };
+    let obj = eval_with_limits(crate::ast::Node::Program(program), env, limits);
+    assert_eq!(Object::String(String::from("memory limit of 65536 bytes exceeded")), obj);
 }
 
 #[test]
-fn eval_if_expression() {
+fn collect_cycles() {
     use std::sync::{Arc, Mutex};
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use crate::object::*;
     use crate::evaluator::eval;
 
-    let tests = vec![
-        (String::from("if (0) { true } else { false }"), false),
-        (String::from("if (1) { true } else { false }"), true),
-    ];
+    // the global environment holds `f`, which holds the global environment
+    let input = "let f = fn() { 1 }; f()";
+    let mut p = Parser::new(Lexer::new(input));
+    let program = p.parse_program().unwrap();
+    let env = Arc::new(Mutex::new(Environment::new()));
+    assert_eq!(Object::Integer(1), eval(crate::ast::Node::Program(program), env.clone()));
+    let global = Arc::downgrade(&env);
 
-    tests.iter().for_each(|(i, o)| {
-        let mut p = Parser::new(Lexer::new(i.as_str()));
-        let program = p.parse_program().unwrap();
-        let env = Arc::new(Mutex::new(Environment::new()));
-        let obj = eval(crate::ast::Node::Program(program), env);
-        println!("{} {:?}", i, obj);
-        if let Object::Boolean(i) = obj {
-            assert_eq!(*o, i);
-        } else {
-            panic!("obj is not an Boolean");
-        }
-    });
+    // `self` is bound in the call's own environment and captures it
+    let input = "let make = fn() { let self = fn() { self }; self }; make()";
+    let mut p = Parser::new(Lexer::new(input));
+    let program = p.parse_program().unwrap();
+    let other = Arc::new(Mutex::new(Environment::new()));
+    let closure = eval(crate::ast::Node::Program(program), other.clone());
+    let local = match &closure {
+        Object::Function(f) => Arc::downgrade(f.env.as_ref().unwrap()),
+        _ => panic!("obj is not a Function: {:?}", closure),
+    };
+
+    // everything is still referenced from outside, so nothing is freed
+    gc::collect();
+    assert!(global.upgrade().is_some());
+    assert!(local.upgrade().is_some());
+
+    drop(env);
+    drop(closure);
+    assert!(global.upgrade().is_some());
+    assert!(local.upgrade().is_some());
+
+    let stats = gc::collect();
+    assert!(global.upgrade().is_none());
+    assert!(local.upgrade().is_none());
+    assert!(stats.freed >= 2);
+
+    // `other` is still in use, along with `make` bound inside it
+    let make = other.lock().unwrap().get(&String::from("make"));
+    assert!(make.is_some());
 }
 
 #[test]
-fn eval_return_statement() {
+fn snapshot_round_trip() {
     use std::sync::{Arc, Mutex};
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use crate::object::*;
-    use crate::evaluator::eval;
+    use crate::evaluator::{eval, snapshot};
 
-    let tests = vec![
-        (String::from("return 5"), 5),
-        (String::from("return 10"), 10),
-        (String::from("if (10 > 1) {
-            if (10 > 1) {
-                return 10;
-            }
-            return 1;
-        }"), 10)
-    ];
+    let input = "import \"std.string\"; let n = 5; let s = \"hi\"; let adder = fn(x) { fn(y) { x + y } }; \
+        let add5 = adder(5); let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; let len = strlen;";
+    let mut p = Parser::new(Lexer::new(input));
+    let program = p.parse_program().unwrap();
+    let env = Arc::new(Mutex::new(Environment::new()));
+    eval(crate::ast::Node::Program(program), env.clone());
+
+    let snap = snapshot::save(&env);
+    assert!(snap.skipped.is_empty());
+    let restored = snapshot::restore(&snap.source).unwrap();
+    assert_eq!(vec![String::from("std.string")], restored.lock().unwrap().imports);
 
-    tests.iter().for_each(|(i, o)| {
-        let mut p = Parser::new(Lexer::new(i.as_str()));
+    let tests = [
+        ("n", Object::Integer(5)),
+        ("s", Object::String(String::from("hi"))),
+        ("add5(1)", Object::Integer(6)),
+        ("fact(5)", Object::Integer(120)),
+        ("len(s)", Object::Integer(2)),
+    ];
+    for (input, expected) in tests {
+        let mut p = Parser::new(Lexer::new(input));
         let program = p.parse_program().unwrap();
-        let env = Arc::new(Mutex::new(Environment::new()));
-        let obj = eval(crate::ast::Node::Program(program), env);
-        println!("{} {:?}", i, obj);
-        if let Object::Integer(i) = obj {
-            assert_eq!(*o, i);
-        } else {
-            println!("{:?}", obj);
-            panic!("obj is no an Integer");
-        }
-    });
+        assert_eq!(expected, eval(crate::ast::Node::Program(program), restored.clone()));
+    }
 }
 
 #[test]
-fn error_handling() {
+fn snapshot_round_trip_test_builtins() {
     use std::sync::{Arc, Mutex};
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use crate::object::*;
-    use crate::evaluator::eval;
+    use crate::evaluator::{eval, snapshot};
 
-    let tests = vec![
-        ("5 + true;", "type mismatch: INTEGER + BOOLEAN"),
-        ("5 + true; 5;", "type mismatch: INTEGER + BOOLEAN"),
-        ("-true", "unknown operator: -BOOLEAN"),
-        ("true + false", "unknown operator: BOOLEAN + BOOLEAN"),
-        ("5; true + false; 5", "unknown operator: BOOLEAN + BOOLEAN"),
-        ("if (10 > 1) { return true + false; }", "unknown operator: BOOLEAN + BOOLEAN"),
-    ];
+    let input = "import \"std.test\"; let check = assert_eq; let fails = assert_error;";
+    let mut p = Parser::new(Lexer::new(input));
+    let program = p.parse_program().unwrap();
+    let env = Arc::new(Mutex::new(Environment::new()));
+    eval(crate::ast::Node::Program(program), env.clone());
+
+    let snap = snapshot::save(&env);
+    assert!(snap.skipped.is_empty());
+    let restored = snapshot::restore(&snap.source).unwrap();
+    assert_eq!(vec![String::from("std.test")], restored.lock().unwrap().imports);
 
-    tests.iter().for_each(|(i, o)| {
-        let mut p = Parser::new(Lexer::new(i));
+    let tests = [
+        ("check(1 + 1, 2)", Object::Null),
+        ("try { check(1, 2) } catch (e) { e.kind }", Object::String(String::from("AssertionError"))),
+        ("fails(fn() { 1 / 0 }).kind", Object::String(String::from("ZeroDivisionError"))),
+    ];
+    for (input, expected) in tests {
+        let mut p = Parser::new(Lexer::new(input));
         let program = p.parse_program().unwrap();
-        let env = Arc::new(Mutex::new(Environment::new()));
-        let obj = eval(crate::ast::Node::Program(program), env);
-        if let Object::Error(e) = obj {
-            assert_eq!(*o, e);
-        } else {
-            panic!("obj is not an Error");
-        }
-    });
+        assert_eq!(expected, eval(crate::ast::Node::Program(program), restored.clone()));
+    }
 }
 
 #[test]
-fn string_concat() {
+fn snapshot_skips_unsaveable_values() {
     use std::sync::{Arc, Mutex};
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use crate::object::*;
-    use crate::evaluator::eval;
-
-    let input = "\"hello \" + \"world!\";";
+    use crate::evaluator::{eval, snapshot};
 
+    let input = "let ok = 1; let bad = try { 1 / 0 } catch (e) { e };";
     let mut p = Parser::new(Lexer::new(input));
     let program = p.parse_program().unwrap();
     let env = Arc::new(Mutex::new(Environment::new()));
-    let obj = eval(crate::ast::Node::Program(program), env);
-    if let Object::String(s) = obj {
-        assert_eq!(s, "hello world!");
-    } else {
-        panic!("obj is not an Error");
-    }
+    eval(crate::ast::Node::Program(program), env.clone());
+
+    let snap = snapshot::save(&env);
+    assert_eq!(vec![String::from("bad")], snap.skipped);
+    assert_eq!("let ok = 1;\n", snap.source);
+    assert!(snapshot::restore("let x = ;").is_err());
 }
@@ -0,0 +1,371 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use crate::ast::{BlockStatement, Expression, Inspect, Program, Statement};
+use crate::lexer::Lexer;
+use crate::parser::{get_priority, with_nesting_stack, Parser, Priority};
+use crate::token::{Comment, Position, Token};
+
+#[cfg(test)]
+mod tests;
+
+const INDENT: &str = "    ";
+/// Calls whose arguments would run past this column are wrapped, one
+/// argument per line.
+const MAX_WIDTH: usize = 80;
+
+/// How tightly an expression binds, for deciding where parentheses go.
+fn priority(e: &Expression) -> Priority {
+    return match e {
+        Expression::InfixExpression(ie) => get_priority(&ie.token),
+        Expression::PrefixExpression(_) => Priority::PREFIX,
+        _ => Priority::CALL,
+    };
+}
+
+/// Whether an expression statement ends in a block, and so needs no `;`
+/// when nothing follows it.
+fn ends_in_block(e: &Expression) -> bool {
+    return matches!(e, Expression::IfExpression(_) | Expression::TryExpression(_));
+}
+
+/// Writes a program back out as canonical source, keeping its comments.
+struct Formatter {
+    out: String,
+    depth: usize,
+    comments: VecDeque<Comment>,
+    /// Where each token in the source starts, in order.
+    tokens: Vec<Position>,
+    /// The source line the last statement or comment written ended on.
+    last_line: usize,
+    /// Whether nothing has been written in the current block yet.
+    block_start: bool,
+    /// Whether long calls may be wrapped over several lines.
+    wrap: bool,
+}
+
+impl Formatter {
+    fn new(comments: Vec<Comment>, tokens: Vec<Position>) -> Self {
+        return Self {
+            out: String::new(),
+            depth: 0,
+            comments: VecDeque::from(comments),
+            tokens,
+            last_line: 0,
+            block_start: true,
+            wrap: true,
+        };
+    }
+
+    fn column(&self) -> usize {
+        return match self.out.rfind('\n') {
+            Some(i) => self.out[i + 1..].chars().count(),
+            None => self.out.chars().count(),
+        };
+    }
+
+    fn indent(&mut self) {
+        self.out += &INDENT.repeat(self.depth);
+    }
+
+    /// Keeps a single blank line where the source had one or more.
+    fn separate(&mut self, line: usize) {
+        if !self.block_start && line > self.last_line + 1 {
+            self.out += "\n";
+        }
+        self.block_start = false;
+    }
+
+    /// Writes each comment that comes before `position` on its own line.
+    fn comments_before(&mut self, position: Position) {
+        while self.comments.front().is_some_and(|c| c.position < position) {
+            let comment = self.comments.pop_front().unwrap();
+            self.separate(comment.position.line);
+            self.indent();
+            self.out += &comment.text;
+            self.out += "\n";
+            self.last_line = comment.position.line;
+        }
+    }
+
+    /// Where the last token before `bound` starts.
+    fn last_token_before(&self, bound: Position) -> Position {
+        let i = self.tokens.partition_point(|p| *p < bound);
+        return if i > 0 { self.tokens[i - 1] } else { Position::default() };
+    }
+
+    /// Writes a list of statements, which end before `limit`: the closing
+    /// brace of their block, or the end of the program.
+    fn statements(&mut self, statements: &[Statement], limit: Position, in_block: bool) {
+        for (i, s) in statements.iter().enumerate() {
+            self.comments_before(s.position());
+            self.separate(s.position().line);
+            self.indent();
+            let last = i == statements.len() - 1;
+            self.statement(s, last, in_block);
+
+            let bound = if last { limit } else { statements[i + 1].position() };
+            let end = self.last_token_before(bound);
+            if self.comments.front().is_some_and(|c| c.position.line == end.line && c.position > end && c.position < limit) {
+                let comment = self.comments.pop_front().unwrap();
+                self.out += " ";
+                self.out += &comment.text;
+            }
+            self.out += "\n";
+            self.last_line = end.line;
+        }
+    }
+
+    /// Writes one statement. An expression statement that isn't last keeps
+    /// its `;`, since the next statement could otherwise continue it:
+    /// `if (a) { 1 }; -b` isn't `if (a) { 1 } - b`.
+    fn statement(&mut self, s: &Statement, last: bool, in_block: bool) {
+        match s {
+            Statement::LetStatement(ls) => {
+                self.out += &format!("let {} = ", ls.name.value);
+                if let Some(value) = &ls.value {
+                    self.expression(value);
+                }
+                self.out += ";";
+            },
+            Statement::ReturnStatement(rs) => {
+                self.out += "return ";
+                self.expression(&rs.return_val);
+                self.out += ";";
+            },
+            Statement::ThrowStatement(ts) => {
+                self.out += "throw ";
+                self.expression(&ts.value);
+                self.out += ";";
+            },
+            Statement::ImportStatement(is) => self.out += &format!("{};", is.to_string()),
+            Statement::ExpressionStatement(es) => {
+                self.expression(&es.expression);
+                if !(last && (in_block || ends_in_block(&es.expression))) {
+                    self.out += ";";
+                }
+            },
+        }
+    }
+
+    fn block(&mut self, b: &BlockStatement) {
+        let has_comments = self.comments.front().is_some_and(|c| c.position < b.end);
+        if b.statements.is_empty() && !has_comments {
+            self.out += "{}";
+            return;
+        }
+        self.out += "{\n";
+        self.depth += 1;
+        self.block_start = true;
+        self.statements(&b.statements, b.end, true);
+        self.comments_before(b.end);
+        self.depth -= 1;
+        self.indent();
+        self.out += "}";
+        self.last_line = b.end.line;
+    }
+
+    /// Writes `e`, in parentheses if `parens` is set.
+    fn operand(&mut self, e: &Expression, parens: bool) {
+        if parens {
+            self.out += "(";
+            self.expression(e);
+            self.out += ")";
+        } else {
+            self.expression(e);
+        }
+    }
+
+    /// `e` on a single line, if it fits on one.
+    fn flat(e: &Expression) -> Option<String> {
+        let mut f = Formatter::new(Vec::new(), Vec::new());
+        f.wrap = false;
+        f.expression(e);
+        if f.out.contains('\n') {
+            return None;
+        }
+        return Some(f.out);
+    }
+
+    fn expression(&mut self, e: &Expression) {
+        match e {
+            Expression::Empty => (),
+            Expression::Identifier(i) => self.out += &i.value,
+            Expression::IntegerLiteral(il) => self.out += &il.value.to_string(),
+            Expression::StringLiteral(sl) => self.out += &sl.to_string(),
+            Expression::Boolean(b) => self.out += &b.value.to_string(),
+            Expression::PrefixExpression(pe) => {
+                self.out += &pe.operator;
+                self.operand(&pe.right, priority(&pe.right) < Priority::PREFIX);
+            },
+            Expression::InfixExpression(ie) => {
+                let p = get_priority(&ie.token);
+                // operators are left associative, so only the right operand
+                // needs parentheses at the same priority
+                self.operand(&ie.left, priority(&ie.left) < p);
+                self.out += &format!(" {} ", ie.operator);
+                self.operand(&ie.right, priority(&ie.right) <= p);
+            },
+            Expression::IfExpression(ie) => {
+                self.out += "if (";
+                self.expression(&ie.condition);
+                self.out += ") ";
+                self.block(&ie.consequence);
+                if let Some(alt) = &ie.alternative {
+                    self.out += " else ";
+                    self.block(alt);
+                }
+            },
+            Expression::FunctionLiteral(fl) => {
+                let params: Vec<String> = fl.parameters.iter().map(|p| p.value.clone()).collect();
+                self.out += &format!("fn({}) ", params.join(", "));
+                self.block(&fl.body);
+            },
+            Expression::CallExpression(ce) => {
+                self.operand(&ce.function, priority(&ce.function) < Priority::CALL);
+                let flat: Vec<Option<String>> = ce.arguments.iter().map(Formatter::flat).collect();
+                let width: usize = flat.iter().map(|a| a.as_ref().map_or(0, |a| a.len() + 2)).sum();
+                let fits = self.column() + width + 2 <= MAX_WIDTH;
+                if !self.wrap || fits || flat.iter().any(Option::is_none) {
+                    self.out += "(";
+                    for (i, arg) in ce.arguments.iter().enumerate() {
+                        if i > 0 {
+                            self.out += ", ";
+                        }
+                        self.expression(arg);
+                    }
+                    self.out += ")";
+                } else {
+                    self.out += "(\n";
+                    self.depth += 1;
+                    for (i, arg) in ce.arguments.iter().enumerate() {
+                        self.indent();
+                        self.expression(arg);
+                        self.out += if i + 1 < ce.arguments.len() { ",\n" } else { "\n" };
+                    }
+                    self.depth -= 1;
+                    self.indent();
+                    self.out += ")";
+                }
+            },
+            Expression::TryExpression(te) => {
+                self.out += "try ";
+                self.block(&te.body);
+                if let Some(handler) = &te.handler {
+                    match &te.parameter {
+                        Some(param) => self.out += &format!(" catch ({}) ", param.value),
+                        None => self.out += " catch ",
+                    }
+                    self.block(handler);
+                }
+                if let Some(finalizer) = &te.finalizer {
+                    self.out += " finally ";
+                    self.block(finalizer);
+                }
+            },
+            Expression::MemberExpression(me) => {
+                self.operand(&me.object, priority(&me.object) < Priority::CALL);
+                self.out += &format!(".{}", me.property.value);
+            },
+        }
+    }
+}
+
+/// Formats a parsed program, placing `comments` back among its statements.
+/// `tokens` are the positions of the program's tokens, which tell where
+/// each statement ends.
+pub fn format_program(program: &Program, comments: Vec<Comment>, tokens: Vec<Position>) -> String {
+    let end = Position { line: usize::MAX, column: usize::MAX };
+    let mut f = Formatter::new(comments, tokens);
+    f.statements(&program.statements, end, false);
+    f.comments_before(end);
+    return f.out;
+}
+
+/// Formats Monkey source, or returns its parse errors rendered under the
+/// lines they're on.
+pub fn format_source(source: &str) -> Result<String, String> {
+    return with_nesting_stack(|| format_text(source))?;
+}
+
+fn format_text(source: &str) -> Result<String, String> {
+    let mut p = Parser::new(Lexer::new(source));
+    let program = p.parse_program();
+    if !p.errors.is_empty() {
+        let errors: Vec<String> = p.errors.iter().map(|e| e.render(source)).collect();
+        return Err(errors.join("\n"));
+    }
+    let program = program.ok_or_else(|| String::from("error parsing program"))?;
+
+    let mut tokens: Vec<Position> = Vec::new();
+    let mut l = Lexer::new(source);
+    while l.next_token() != Token::EOF {
+        tokens.push(l.token_position);
+    }
+    let formatted = format_program(&program, p.comments().clone(), tokens);
+    // a formatter that garbles text is worse than none, so check before
+    // anything gets rewritten
+    if texts(&formatted) != texts(source) {
+        return Err(String::from("formatting would change the text of a string or comment"));
+    }
+    let mut reparsed = Parser::new(Lexer::new(&formatted));
+    let same = reparsed.parse_program().is_some_and(|r| reparsed.errors.is_empty() && r.to_string() == program.to_string());
+    if !same {
+        return Err(String::from("formatting would change what the program means"));
+    }
+    return Ok(formatted);
+}
+
+/// The strings and comments in `source`, in order.
+fn texts(source: &str) -> Vec<String> {
+    let mut texts: Vec<String> = Vec::new();
+    let mut l = Lexer::new(source);
+    loop {
+        match l.next_token() {
+            Token::EOF => break,
+            Token::STRING(s) => texts.push(s),
+            _ => (),
+        }
+    }
+    texts.extend(l.comments.into_iter().map(|c| c.text));
+    return texts;
+}
+
+/// Formats each file in place, or with `check` only lists the files that
+/// aren't formatted. With no files, formats stdin to stdout. Returns whether
+/// every file parsed and, with `check`, was already formatted.
+pub fn format_files(paths: &[&str], check: bool, out: &mut dyn Write) -> std::io::Result<bool> {
+    if paths.is_empty() {
+        let source = std::io::read_to_string(std::io::stdin())?;
+        return match format_source(&source) {
+            Ok(formatted) => {
+                if check {
+                    return Ok(formatted == source);
+                }
+                write!(out, "{}", formatted)?;
+                Ok(true)
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                Ok(false)
+            },
+        };
+    }
+
+    let mut ok = true;
+    for path in paths {
+        let source = std::fs::read_to_string(path)?;
+        match format_source(&source) {
+            Ok(formatted) if formatted == source => (),
+            Ok(_) if check => {
+                writeln!(out, "{}", path)?;
+                ok = false;
+            },
+            Ok(formatted) => std::fs::write(path, formatted)?,
+            Err(e) => {
+                eprintln!("{}:\n{}", path, e);
+                ok = false;
+            },
+        }
+    }
+    return Ok(ok);
+}
@@ -0,0 +1,129 @@
+#[cfg(test)]
+use crate::format::format_source;
+
+#[test]
+fn format_statements() {
+    let input = "let add=fn(x,y){x+y};let r=add(1,2)\nif(r>2){puts(r);r}else{0}\nreturn r";
+    let expected = "\
+let add = fn(x, y) {
+    x + y
+};
+let r = add(1, 2);
+if (r > 2) {
+    puts(r);
+    r
+} else {
+    0
+};
+return r;
+";
+    assert_eq!(expected, format_source(input).unwrap());
+}
+
+#[test]
+fn format_keeps_semicolon_after_block() {
+    let tests = [
+        ("let f = fn(x) { x };\nif (true) { 1 };\n(2 + 3) * 4", "let f = fn(x) {\n    x\n};\nif (true) {\n    1\n};\n(2 + 3) * 4;\n"),
+        ("if (a) { 1 }; -b", "if (a) {\n    1\n};\n-b;\n"),
+        ("if (a) { 1 }", "if (a) {\n    1\n}\n"),
+    ];
+    for (input, expected) in tests {
+        let formatted = format_source(input).unwrap();
+        assert_eq!(expected, formatted);
+        assert_eq!(formatted, format_source(&formatted).unwrap());
+    }
+}
+
+#[test]
+fn format_parentheses() {
+    let tests = [
+        ("(1 + 2) * 3", "(1 + 2) * 3;\n"),
+        ("1 + (2 * 3)", "1 + 2 * 3;\n"),
+        ("1 - (2 - 3)", "1 - (2 - 3);\n"),
+        ("(1 - 2) - 3", "1 - 2 - 3;\n"),
+        ("-(a + b)", "-(a + b);\n"),
+        ("-(a ** 2)", "-(a ** 2);\n"),
+        ("(-a).b", "(-a).b;\n"),
+        ("(f)(1)", "f(1);\n"),
+        ("fn(x) { x }(1)", "fn(x) {\n    x\n}(1);\n"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(expected, format_source(input).unwrap());
+    }
+}
+
+#[test]
+fn format_comments() {
+    let input = "\
+// add two numbers
+let add = fn(x, y) {
+  // the sum
+  x + y // returned
+}; // end of add
+
+
+// call it
+add(1, 2)
+// the end
+";
+    let expected = "\
+// add two numbers
+let add = fn(x, y) {
+    // the sum
+    x + y // returned
+}; // end of add
+
+// call it
+add(1, 2);
+// the end
+";
+    assert_eq!(expected, format_source(input).unwrap());
+}
+
+#[test]
+fn format_wraps_long_calls() {
+    let input = "let total = sum(first_argument, second_argument, third_argument, fourth_argument);";
+    let expected = "\
+let total = sum(
+    first_argument,
+    second_argument,
+    third_argument,
+    fourth_argument
+);
+";
+    assert_eq!(expected, format_source(input).unwrap());
+}
+
+#[test]
+fn format_is_idempotent() {
+    let input = "\
+let f = fn() { try { throw \"x\" } catch (e) { e.message } finally { 1 } };
+let g = fn() { // nothing yet
+};
+if (true) { 1 } else { }
+let total = sum(first_argument, second_argument, third_argument, fourth_argument) + 1;
+";
+    let once = format_source(input).unwrap();
+    assert_eq!(once, format_source(&once).unwrap());
+}
+
+#[test]
+fn format_keeps_non_ascii_text() {
+    use crate::format::format_files;
+
+    let input = "let s = \"café\"; // naïve\nlet t = \"λ😀\"";
+    let expected = "let s = \"café\"; // naïve\nlet t = \"λ😀\";\n";
+    assert_eq!(expected, format_source(input).unwrap());
+
+    let path = std::env::temp_dir().join(format!("waiig_fmt_{}.mk", std::process::id()));
+    std::fs::write(&path, input).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    assert!(format_files(&[path.to_str().unwrap()], false, &mut out).unwrap());
+    assert_eq!(expected, std::fs::read_to_string(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn format_reports_parse_errors() {
+    assert_eq!("let x = ;\n        ^ no prefix parse function for ; found", format_source("let x = ;").unwrap_err());
+}
@@ -0,0 +1,168 @@
+use crate::ast::*;
+use crate::fuzz::Rng;
+use crate::token::{Position, Token};
+
+const NAMES: &[&str] = &["a", "b", "x", "foo", "bar_2", "_tmp"];
+const PREFIXES: &[(Token, &str)] = &[(Token::MINUS, "-"), (Token::BANG, "!")];
+const INFIXES: &[(Token, &str)] = &[
+    (Token::PLUS, "+"), (Token::MINUS, "-"), (Token::ASTERISK, "*"), (Token::SLASH, "/"),
+    (Token::POWER, "**"), (Token::LT, "<"), (Token::GT, ">"), (Token::EQ, "=="), (Token::NEQ, "!="),
+];
+
+fn gen_identifier(rng: &mut Rng) -> Identifier {
+    let name = NAMES[rng.below(NAMES.len())].to_string();
+    return Identifier { token: Token::IDENT(name.clone()), value: name, position: Position::default(), end: Position::default() };
+}
+
+fn gen_string(rng: &mut Rng) -> StringLiteral {
+    let value: String = (0..rng.below(6)).map(|_| ['a', 'z', ' ', '7', '{', '_'][rng.below(6)]).collect();
+    return StringLiteral { token: Token::STRING(value.clone()), value, position: Position::default(), end: Position::default() };
+}
+
+fn gen_block(rng: &mut Rng, depth: usize) -> BlockStatement {
+    return BlockStatement {
+        token: Token::LSQUIRLY,
+        statements: (0..rng.below(3)).map(|_| gen_statement(rng, depth)).collect(),
+        position: Position::default(),
+        end: Position::default(),
+    };
+}
+
+fn gen_expression(rng: &mut Rng, depth: usize) -> Expression {
+    let choice = if depth == 0 { rng.below(4) } else { rng.below(11) };
+    let depth = depth.saturating_sub(1);
+    return match choice {
+        0 => Expression::Identifier(gen_identifier(rng)),
+        1 => {
+            let value = rng.below(100000) as i32;
+            Expression::IntegerLiteral(IntegerLiteral {
+                token: Token::INT(value.to_string()),
+                value,
+                position: Position::default(),
+                end: Position::default(),
+            })
+        },
+        2 => Expression::StringLiteral(gen_string(rng)),
+        3 => {
+            let value = rng.below(2) == 0;
+            Expression::Boolean(BooleanLiteral {
+                token: if value { Token::TRUE } else { Token::FALSE },
+                value,
+                position: Position::default(),
+                end: Position::default(),
+            })
+        },
+        4 => {
+            let (token, operator) = PREFIXES[rng.below(PREFIXES.len())].clone();
+            Expression::PrefixExpression(PrefixExpression {
+                token,
+                operator: operator.to_string(),
+                right: Box::new(gen_expression(rng, depth)),
+                position: Position::default(),
+                end: Position::default(),
+            })
+        },
+        5 | 6 => {
+            let (token, operator) = INFIXES[rng.below(INFIXES.len())].clone();
+            Expression::InfixExpression(InfixExpression {
+                token,
+                operator: operator.to_string(),
+                left: Box::new(gen_expression(rng, depth)),
+                right: Box::new(gen_expression(rng, depth)),
+                position: Position::default(),
+                end: Position::default(),
+            })
+        },
+        7 => Expression::IfExpression(IfExpression {
+            token: Token::IF,
+            condition: Box::new(gen_expression(rng, depth)),
+            consequence: gen_block(rng, depth),
+            alternative: if rng.below(2) == 0 { Some(gen_block(rng, depth)) } else { None },
+            position: Position::default(),
+            end: Position::default(),
+        }),
+        8 => Expression::FunctionLiteral(FunctionLiteral {
+            token: Token::FUNCTION,
+            parameters: (0..rng.below(3)).map(|_| gen_identifier(rng)).collect(),
+            body: gen_block(rng, depth),
+            position: Position::default(),
+            end: Position::default(),
+        }),
+        9 => Expression::CallExpression(CallExpression {
+            token: Token::LPAREN,
+            function: Box::new(gen_expression(rng, depth)),
+            arguments: (0..rng.below(3)).map(|_| gen_expression(rng, depth)).collect(),
+            position: Position::default(),
+            end: Position::default(),
+        }),
+        _ => {
+            if rng.below(2) == 0 {
+                Expression::MemberExpression(MemberExpression {
+                    token: Token::DOT,
+                    object: Box::new(gen_expression(rng, depth)),
+                    property: gen_identifier(rng),
+                    position: Position::default(),
+                    end: Position::default(),
+                })
+            } else {
+                // a try needs at least one of catch and finally
+                let kind = rng.below(3);
+                let handler = if kind != 1 { Some(gen_block(rng, depth)) } else { None };
+                Expression::TryExpression(TryExpression {
+                    token: Token::TRY,
+                    body: gen_block(rng, depth),
+                    parameter: if handler.is_some() && rng.below(2) == 0 { Some(gen_identifier(rng)) } else { None },
+                    handler,
+                    finalizer: if kind != 0 { Some(gen_block(rng, depth)) } else { None },
+                    position: Position::default(),
+                    end: Position::default(),
+                })
+            }
+        },
+    };
+}
+
+fn gen_statement(rng: &mut Rng, depth: usize) -> Statement {
+    return match rng.below(6) {
+        0 => Statement::LetStatement(LetStatement {
+            token: Token::LET,
+            name: gen_identifier(rng),
+            value: Some(gen_expression(rng, depth)),
+            position: Position::default(),
+            end: Position::default(),
+        }),
+        1 => Statement::ReturnStatement(ReturnStatement {
+            token: Token::RETURN,
+            return_val: gen_expression(rng, depth),
+            position: Position::default(),
+            end: Position::default(),
+        }),
+        2 => Statement::ThrowStatement(ThrowStatement {
+            token: Token::THROW,
+            value: gen_expression(rng, depth),
+            position: Position::default(),
+            end: Position::default(),
+        }),
+        3 => Statement::ImportStatement(ImportStatement {
+            token: Token::IMPORT,
+            namespace: gen_string(rng),
+            position: Position::default(),
+            end: Position::default(),
+        }),
+        _ => {
+            let expression = gen_expression(rng, depth);
+            Statement::ExpressionStatement(ExpressionStatement {
+                token: expression.first_token(),
+                expression,
+                position: Position::default(),
+                end: Position::default(),
+            })
+        },
+    };
+}
+
+/// A random program of one to four statements, with expressions nested at
+/// most `depth` deep. Printing it and parsing the result gives it back.
+pub fn program(rng: &mut Rng, depth: usize) -> Program {
+    return Program { statements: (0..1 + rng.below(4)).map(|_| gen_statement(rng, depth)).collect() };
+}
@@ -0,0 +1,223 @@
+use std::io::Write;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::ast::{Inspect, Node};
+use crate::evaluator::{eval_with_limits, Limits};
+use crate::lexer::Lexer;
+use crate::object::Environment;
+use crate::parser::{with_nesting_stack, Parser, MAX_NESTING, MAX_TREE_DEPTH};
+use crate::token::Token;
+
+pub mod generate;
+
+#[cfg(test)]
+mod tests;
+
+/// A xorshift generator, so fuzzing needs no extra crates.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift never leaves zero, and nearby seeds should diverge quickly
+        return Self(seed.wrapping_mul(0x9e3779b97f4a7c15).max(1));
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        return (self.0 % n as u64) as usize;
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        return items[self.below(items.len())];
+    }
+}
+
+/// What a fuzz input is fed to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// `Lexer::next_token`, until it reaches the end of the input.
+    Lexer,
+    /// `Parser::parse_program`.
+    Parser,
+    /// `evaluator::eval`, of inputs that parse, with limits on its steps,
+    /// memory and time.
+    Evaluator,
+}
+
+impl Target {
+    pub fn from_name(name: &str) -> Option<Target> {
+        return match name {
+            "lexer" => Some(Target::Lexer),
+            "parser" => Some(Target::Parser),
+            "evaluator" => Some(Target::Evaluator),
+            _ => None,
+        };
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Target::Lexer => "lexer",
+            Target::Parser => "parser",
+            Target::Evaluator => "evaluator",
+        };
+    }
+
+    /// Feeds `source` to this target, panicking if it misbehaves.
+    pub fn run(&self, source: &str) {
+        match self {
+            Target::Lexer => {
+                // every token but the last consumes at least one character
+                let mut l = Lexer::new(source);
+                let mut tokens = 0;
+                while l.next_token() != Token::EOF {
+                    tokens += 1;
+                    assert!(tokens <= source.len(), "the lexer didn't reach the end of the input");
+                }
+            },
+            Target::Parser => {
+                Parser::new(Lexer::new(source)).parse_program();
+            },
+            Target::Evaluator => {
+                let mut p = Parser::new(Lexer::new(source));
+                let Some(program) = p.parse_program() else { return };
+                if !p.errors.is_empty() {
+                    return;
+                }
+                let limits = Limits {
+                    max_steps: Some(100_000),
+                    max_memory: Some(1024 * 1024),
+                    timeout: Some(Duration::from_secs(1)),
+                    ..Limits::default()
+                };
+                let env = Arc::new(Mutex::new(Environment::new()));
+                eval_with_limits(Node::Program(program), env, limits);
+            },
+        }
+    }
+}
+
+const CHARACTERS: &[&str] = &[
+    "a", "z", "_", "0", "7", " ", "\n", "\t", "\"", "(", ")", "{", "}", "[", "]", ",", ";", ".",
+    "=", "!", "+", "-", "*", "/", "<", ">", "#", "\\", "\0", "é", "λ", "€", "😀",
+];
+
+const FRAGMENTS: &[&str] = &[
+    "let", "fn", "if", "else", "return", "true", "false", "import", "try", "catch", "finally", "throw",
+    "x", "f", "e", "e.kind", "e.message", "strlen", "assert", "assert_eq", "assert_error",
+    "\"std.string\"", "\"std.test\"", "\"\"", "\"a\"", "\"", "0", "1", "2", "31", "-1",
+    "2147483647", "2147483648", "99999999999999999999",
+    "(", ")", "{", "}", "[", "]", ",", ";", ".", "=", "==", "!=", "!", "+", "-", "*", "/", "**", "<", ">",
+    "// note\n", "\n", "λ", "😀",
+];
+
+/// Random characters, some of them outside ASCII.
+fn characters(rng: &mut Rng) -> String {
+    return (0..rng.below(64)).map(|_| rng.pick(CHARACTERS)).collect();
+}
+
+/// Random keywords, operators and literals, with and without spaces
+/// between them.
+fn fragments(rng: &mut Rng) -> String {
+    let separator = rng.pick(&["", " "]);
+    let fragments: Vec<&str> = (0..rng.below(40)).map(|_| rng.pick(FRAGMENTS)).collect();
+    return fragments.join(separator);
+}
+
+/// A valid program with a few fragments cut out, copied or inserted.
+fn mutated(rng: &mut Rng) -> String {
+    let mut chars: Vec<char> = generate::program(rng, 4).to_string().chars().collect();
+    for _ in 0..rng.below(4) {
+        let start = rng.below(chars.len() + 1);
+        let end = start + rng.below(chars.len() - start + 1).min(8);
+        match rng.below(3) {
+            0 => {
+                chars.drain(start..end);
+            },
+            1 => {
+                let copy: Vec<char> = chars[start..end].to_vec();
+                let at = rng.below(chars.len() + 1);
+                chars.splice(at..at, copy);
+            },
+            _ => {
+                let fragment = rng.pick(FRAGMENTS);
+                chars.splice(start..start, fragment.chars());
+            },
+        }
+    }
+    return chars.into_iter().collect();
+}
+
+/// Expressions nested, or operators chained, up to twice as deep as the
+/// parser allows.
+fn nested(rng: &mut Rng) -> String {
+    let (open, close, limit) = match rng.below(7) {
+        0 => ("(", ")", MAX_NESTING),
+        1 => ("-", "", MAX_NESTING),
+        2 => ("fn() { ", " }()", MAX_NESTING),
+        3 => ("if (true) { ", " }", MAX_NESTING),
+        4 => ("try { ", " } finally { 0 }", MAX_NESTING),
+        5 => ("", " * 2", MAX_TREE_DEPTH),
+        _ => ("", "(1)", MAX_TREE_DEPTH),
+    };
+    let depth = rng.below(2 * limit);
+    return format!("{}1{}", open.repeat(depth), close.repeat(depth));
+}
+
+/// The input the fuzzer generates from `seed`.
+pub fn input(seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    return match rng.below(8) {
+        0 | 1 => characters(&mut rng),
+        2 | 3 => fragments(&mut rng),
+        4..=6 => mutated(&mut rng),
+        _ => nested(&mut rng),
+    };
+}
+
+/// An input that made a target panic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub seed: u64,
+    pub input: String,
+    pub message: String,
+}
+
+/// Feeds `target` the inputs generated from the `iterations` seeds starting
+/// at `seed`, returning the first one it panics on.
+pub fn fuzz(target: Target, seed: u64, iterations: u64) -> Result<(), Failure> {
+    return with_nesting_stack(|| fuzz_seeds(target, seed, iterations))
+        .unwrap_or_else(|message| Err(Failure { seed, input: String::new(), message }));
+}
+
+fn fuzz_seeds(target: Target, seed: u64, iterations: u64) -> Result<(), Failure> {
+    for seed in seed..seed.saturating_add(iterations) {
+        let source = input(seed);
+        if let Err(panic) = catch_unwind(AssertUnwindSafe(|| target.run(&source))) {
+            let message = panic.downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| String::from("panicked"));
+            return Err(Failure { seed, input: source, message });
+        }
+    }
+    return Ok(());
+}
+
+/// Fuzzes `target`, printing what it tried and the input it failed on, if
+/// any. Returns whether it never panicked.
+pub fn fuzz_target(target: Target, seed: u64, iterations: u64, out: &mut dyn Write) -> std::io::Result<bool> {
+    writeln!(out, "fuzzing the {} with {} inputs from seed {}", target.name(), iterations, seed)?;
+    return match fuzz(target, seed, iterations) {
+        Ok(()) => {
+            writeln!(out, "no panics")?;
+            Ok(true)
+        },
+        Err(failure) => {
+            writeln!(out, "seed {} panicked: {}\ninput: {:?}", failure.seed, failure.message, failure.input)?;
+            Ok(false)
+        },
+    };
+}
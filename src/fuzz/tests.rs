@@ -0,0 +1,80 @@
+#[test]
+fn targets_survive_fuzzing() {
+    use crate::fuzz::{fuzz, Target};
+
+    for (target, iterations) in [(Target::Lexer, 3000), (Target::Parser, 3000), (Target::Evaluator, 1000)] {
+        assert_eq!(Ok(()), fuzz(target, 1, iterations), "{}", target.name());
+    }
+}
+
+#[test]
+fn inputs_depend_only_on_the_seed() {
+    use crate::fuzz::input;
+
+    assert_eq!(input(42), input(42));
+    let inputs: std::collections::HashSet<String> = (0..100).map(input).collect();
+    assert!(inputs.len() > 90);
+    // some inputs are outside ASCII
+    assert!(inputs.iter().any(|i| !i.is_ascii()));
+}
+
+#[test]
+fn fuzz_target_output() {
+    use crate::fuzz::{fuzz_target, Target};
+
+    let mut out: Vec<u8> = Vec::new();
+    assert!(fuzz_target(Target::Parser, 5, 10, &mut out).unwrap());
+    assert_eq!("fuzzing the parser with 10 inputs from seed 5\nno panics\n", String::from_utf8(out).unwrap());
+}
+
+#[test]
+fn nesting_limit() {
+    use crate::lexer::Lexer;
+    use crate::parser::{with_nesting_stack, ParseError, Parser, MAX_NESTING, MAX_TREE_DEPTH};
+    use crate::fuzz::Target;
+
+    let errors = |source: &str| -> Vec<String> {
+        let mut p = Parser::new(Lexer::new(source));
+        p.parse_program();
+        return p.errors.into_iter().map(|e: ParseError| e.message).collect();
+    };
+    // the deepest input that parses runs, and the tools that walk its tree
+    // work, from a test thread
+    let walk = |source: &str| {
+        let program = crate::parser::parse_source(source).unwrap();
+        with_nesting_stack(|| Target::Evaluator.run(source)).unwrap();
+        crate::format::format_source(source).unwrap();
+        assert_eq!(0, crate::check::check_source(source).iter().filter(|d| d.code == "syntax").count());
+        with_nesting_stack(move || {
+            crate::dump::program_json(&program).to_string();
+            crate::dump::program_sexp(&program);
+        }).unwrap();
+    };
+
+    // the statement's expression is the first level, so each of these can
+    // wrap it one time fewer than the limit
+    let nesting = [("(", ")"), ("-", ""), ("fn() { ", " }()"), ("if (true) { ", " }"), ("try { ", " } finally { 0 }")];
+    for (open, close) in nesting {
+        let nested = |depth: usize| format!("{}1{}", open.repeat(depth), close.repeat(depth));
+        let source = nested(MAX_NESTING - 1);
+        assert_eq!(Vec::<String>::new(), with_nesting_stack(|| errors(&source)).unwrap(), "{}{}", open, close);
+        walk(&source);
+        let source = nested(MAX_NESTING);
+        let messages = with_nesting_stack(|| errors(&source)).unwrap();
+        assert_eq!(vec![String::from("expression nested more than 1000 deep")], messages, "{}{}", open, close);
+    }
+
+    // operators applied one after another don't nest, so chains far longer
+    // than the nesting limit parse on a test thread's stack; only the depth
+    // of the tree they build is limited
+    let chains = [" + 1", ".kind", "(1)"];
+    for link in chains {
+        let source = format!("1{}", link.repeat(MAX_TREE_DEPTH - 1));
+        assert_eq!(Vec::<String>::new(), errors(&source), "{}", link);
+        walk(&source);
+        let source = format!("1{}", link.repeat(MAX_TREE_DEPTH));
+        assert_eq!(vec![String::from("expression tree more than 5000 deep")], errors(&source), "{}", link);
+    }
+    let source = format!("-({})", "1 + ".repeat(MAX_TREE_DEPTH - 2) + "1");
+    assert_eq!(vec![String::from("expression tree more than 5000 deep")], errors(&source));
+}
@@ -0,0 +1,37 @@
+#[cfg(test)]
+use crate::json::{parse, Json};
+
+#[test]
+fn parse_values() {
+    let input = r#"{"a": [1, -2.5, true, null], "b": {"c": "d\n\"e\" é 😀"}, "f": []}"#;
+    let expected = Json::object(vec![
+        ("a", Json::Array(vec![Json::Number(1.0), Json::Number(-2.5), Json::Bool(true), Json::Null])),
+        ("b", Json::object(vec![("c", Json::str("d\n\"e\" é 😀"))])),
+        ("f", Json::Array(vec![])),
+    ]);
+    assert_eq!(Ok(expected), parse(input));
+}
+
+#[test]
+fn parse_errors() {
+    assert!(parse("").is_err());
+    assert!(parse("{\"a\" 1}").is_err());
+    assert!(parse("[1, 2").is_err());
+    assert!(parse("\"abc").is_err());
+    assert!(parse("1 2").is_err());
+    assert!(parse(&"[".repeat(100000)).is_err());
+}
+
+#[test]
+fn print_values() {
+    let value = Json::object(vec![
+        ("n", Json::Number(3.0)),
+        ("s", Json::str("tab\there")),
+        ("a", Json::Array(vec![Json::Bool(false), Json::Number(0.5)])),
+        ("o", Json::Object(vec![])),
+    ]);
+    assert_eq!(r#"{"n":3,"s":"tab\there","a":[false,0.5],"o":{}}"#, value.to_string());
+    assert_eq!("{\n  \"n\": 3,\n  \"s\": \"tab\\there\",\n  \"a\": [\n    false,\n    0.5\n  ],\n  \"o\": {}\n}", value.pretty());
+    assert_eq!(Ok(value.clone()), parse(&value.to_string()));
+    assert_eq!(Ok(value.clone()), parse(&value.pretty()));
+}
@@ -1,36 +1,63 @@
-use crate::token::{self, Token};
+use crate::token::{self, Comment, Position, Token};
 
+#[cfg(test)]
 mod tests;
 
 #[derive(Clone)]
 pub struct Lexer<'a> {
-    input: &'a [u8],
+    input: &'a str,
     position: usize,
     read_position: usize,
-    ch: char
+    ch: char,
+    line: usize,
+    column: usize,
+    pub token_position: Position,
+    /// Where the last token read ends, just past its last character.
+    pub token_end: Position,
+    pub unterminated_string: bool,
+    /// The comments skipped so far, in source order.
+    pub comments: Vec<Comment>,
 }
 
 #[allow(dead_code)]
 impl<'a> Lexer<'a> {
     pub fn new(inpt: &'a str) -> Self {
         let mut l = Lexer {
-            input: inpt.as_bytes(),
+            input: inpt,
             position: 0,
             read_position: 0,
             ch: '\0',
+            line: 1,
+            column: 0,
+            token_position: Position::default(),
+            token_end: Position::default(),
+            unterminated_string: false,
+            comments: Vec::new(),
         };
         l.read_char();
         return l;
     }
 
     pub fn read_char(&mut self) {
-        if self.read_position >= self.input.len().into() {
-            self.ch = '\0';
+        if self.ch == '\n' {
+            self.line += 1;
+            self.column = 1;
         } else {
-            self.ch = self.input[self.read_position].into();
+            self.column += 1;
         }
         self.position = self.read_position;
-        self.read_position += 1;
+        // positions are byte offsets; past the end, read_position keeps
+        // counting up by one so the end of the input can be told apart
+        match self.input.get(self.read_position..).and_then(|rest| rest.chars().next()) {
+            Some(ch) => {
+                self.ch = ch;
+                self.read_position += ch.len_utf8();
+            },
+            None => {
+                self.ch = '\0';
+                self.read_position += 1;
+            },
+        }
     }
 
     fn read_identifier(&mut self) -> String {
@@ -38,53 +65,71 @@ impl<'a> Lexer<'a> {
         while self.ch.is_alphanumeric() || self.ch == '_' {
             self.read_char();
         }
-        let mut s = String::new();
-        for i in position..self.position {
-            s.push(self.input[i].into());
-        }
-        return s;
+        return self.input[position..self.position].to_string();
     }
 
     fn read_number(&mut self) -> String {
         let position = self.position;
-        while self.ch.is_digit(10) {
+        while self.ch.is_ascii_digit() {
             self.read_char();
         }
-        let mut s = String::new();
-        for i in position..self.position {
-            s.push(self.input[i].into());
-        }
-        return s;
+        return self.input[position..self.position].to_string();
     }
 
-    fn read_string(&mut self) -> String {
+    fn read_string(&mut self) -> Option<String> {
         self.read_char();
         let mut s = String::new();
         loop {
             if self.ch == '"' {
                 break;
             }
+            if self.read_position > self.input.len() {
+                self.unterminated_string = true;
+                return None;
+            }
             s.push(self.ch);
             self.read_char();
         }
-        return s;
+        return Some(s);
     }
 
-    fn peek_char(&mut self) -> char {
-        if self.read_position >= self.input.len() {
-            return '\0';
+    fn read_comment(&mut self) {
+        let position = Position { line: self.line, column: self.column };
+        let start = self.position;
+        while self.ch != '\n' && self.read_position <= self.input.len() {
+            self.read_char();
         }
-        return self.input[self.read_position].into();
+        let text = &self.input[start..self.position.min(self.input.len())];
+        self.comments.push(Comment { position, text: text.trim_end().to_string() });
+    }
+
+    fn peek_char(&mut self) -> char {
+        return self.input.get(self.read_position..).and_then(|rest| rest.chars().next()).unwrap_or('\0');
     }
 
     pub fn next_token(&mut self) -> Token {
-        while self.ch.is_whitespace() {
-            self.read_char();
+        let tok = self.read_token();
+        self.token_end = Position { line: self.line, column: self.column };
+        return tok;
+    }
+
+    fn read_token(&mut self) -> Token {
+        loop {
+            while self.ch.is_whitespace() {
+                self.read_char();
+            }
+            if self.ch == '/' && self.peek_char() == '/' {
+                self.read_comment();
+            } else {
+                break;
+            }
         }
+        self.token_position = Position { line: self.line, column: self.column };
 
         let tok: Token = match self.ch {
             '\0' => Token::EOF,
             ',' => Token::COMMA,
+            '.' => Token::DOT,
             ';' => Token::SEMICOLON,
             '(' => Token::LPAREN,
             ')' => Token::RPAREN,
@@ -106,7 +151,10 @@ impl<'a> Lexer<'a> {
             '/' => Token::SLASH,
             '<' => Token::LT,
             '>' => Token::GT,
-            '"' => Token::STRING(self.read_string()),
+            '"' => match self.read_string() {
+                Some(s) => Token::STRING(s),
+                None => Token::ILLEGAL,
+            },
             '=' => {
                 if self.peek_char() == '=' {
                     self.read_char();
@@ -128,7 +176,7 @@ impl<'a> Lexer<'a> {
                 if self.ch.is_alphabetic() || self.ch == '_' {
                     let literal= self.read_identifier();
                     return token::lookup_ident(literal);
-                } else if self.ch.is_digit(10) {
+                } else if self.ch.is_ascii_digit() {
                     // intentionally omit self.read_char() as it is taken care
                     // of in read_number
                     return Token::INT(self.read_number());
@@ -64,3 +64,72 @@ fn read_identifier() {
     t = l.next_token();
     assert_eq!(t, Token::INT(String::from("5")));
 }
+
+#[test]
+fn unterminated_string() {
+    let input = "let s = \"abc";
+    let mut l = Lexer::new(input);
+    assert_eq!(l.next_token(), Token::LET);
+    assert_eq!(l.next_token(), Token::IDENT(String::from("s")));
+    assert_eq!(l.next_token(), Token::ASSIGN);
+    assert!(!l.unterminated_string);
+    assert_eq!(l.next_token(), Token::ILLEGAL);
+    assert!(l.unterminated_string);
+    assert_eq!(l.next_token(), Token::EOF);
+}
+
+#[test]
+fn comments() {
+    use crate::token::{Comment, Position};
+
+    let input = "// leading\nlet x = 5; // trailing\n//last";
+    let mut l = Lexer::new(input);
+    assert_eq!(l.next_token(), Token::LET);
+    assert_eq!(l.token_position, Position { line: 2, column: 1 });
+    assert_eq!(l.next_token(), Token::IDENT(String::from("x")));
+    assert_eq!(l.next_token(), Token::ASSIGN);
+    assert_eq!(l.next_token(), Token::INT(String::from("5")));
+    assert_eq!(l.next_token(), Token::SEMICOLON);
+    assert_eq!(l.next_token(), Token::EOF);
+    assert_eq!(vec![
+        Comment { position: Position { line: 1, column: 1 }, text: String::from("// leading") },
+        Comment { position: Position { line: 2, column: 12 }, text: String::from("// trailing") },
+        Comment { position: Position { line: 3, column: 1 }, text: String::from("//last") },
+    ], l.comments);
+}
+
+#[test]
+fn token_end() {
+    use crate::token::Position;
+
+    let input = "let name = \"ab\nc\";";
+    let mut l = Lexer::new(input);
+    l.next_token();
+    assert_eq!(l.token_end, Position { line: 1, column: 4 });
+    l.next_token();
+    assert_eq!(l.token_end, Position { line: 1, column: 9 });
+    l.next_token();
+    l.next_token();
+    assert_eq!(l.token_position, Position { line: 1, column: 12 });
+    assert_eq!(l.token_end, Position { line: 2, column: 3 });
+    l.next_token();
+    assert_eq!(l.token_end, Position { line: 2, column: 4 });
+}
+
+#[test]
+fn non_ascii_input() {
+    use crate::token::{Comment, Position};
+
+    let input = "let café = \"naïve λ😀\"; // ünïcode\nx";
+    let mut l = Lexer::new(input);
+    assert_eq!(l.next_token(), Token::LET);
+    assert_eq!(l.next_token(), Token::IDENT(String::from("café")));
+    assert_eq!(l.next_token(), Token::ASSIGN);
+    assert_eq!(l.token_position, Position { line: 1, column: 10 });
+    assert_eq!(l.next_token(), Token::STRING(String::from("naïve λ😀")));
+    assert_eq!(l.token_end, Position { line: 1, column: 22 });
+    assert_eq!(l.next_token(), Token::SEMICOLON);
+    assert_eq!(l.next_token(), Token::IDENT(String::from("x")));
+    assert_eq!(l.next_token(), Token::EOF);
+    assert_eq!(vec![Comment { position: Position { line: 1, column: 24 }, text: String::from("// ünïcode") }], l.comments);
+}
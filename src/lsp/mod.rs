@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::ast::visit::{self, Visitor};
+use crate::ast::{Expression, Inspect, LetStatement, Node, Program, Statement};
+use crate::check::{self, Severity};
+use crate::evaluator::{eval_with_limits, Limits, STD_FUNCTIONS};
+use crate::json::{self, Json};
+use crate::lexer::Lexer;
+use crate::object::{get_type, Environment};
+use crate::parser::Parser;
+use crate::token::{self, Position, Token};
+
+#[cfg(test)]
+mod tests;
+
+// A language server speaking JSON-RPC over stdin and stdout. Documents are
+// synced in full on every change. LSP positions count lines and characters
+// from 0 where ours count from 1; columns are taken to be characters, which
+// is exact for ASCII source.
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const PARSE_ERROR: i64 = -32700;
+
+/// Hovering evaluates the document to show values, within these limits.
+fn hover_limits() -> Limits {
+    return Limits { max_steps: Some(100_000), timeout: Some(Duration::from_millis(200)), ..Limits::default() };
+}
+
+/// Reads one `Content-Length` framed message, or None at the end of input.
+pub fn read_message(input: &mut dyn BufRead) -> std::io::Result<Option<String>> {
+    let mut length: Option<usize> = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    return Ok(Some(String::from_utf8_lossy(&body).to_string()));
+}
+
+pub fn write_message(out: &mut dyn Write, message: &Json) -> std::io::Result<()> {
+    let body = message.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    return out.flush();
+}
+
+fn response(id: Json, result: Json) -> Json {
+    return Json::object(vec![("jsonrpc", Json::str("2.0")), ("id", id), ("result", result)]);
+}
+
+fn error_response(id: Json, code: i64, message: &str) -> Json {
+    let error = Json::object(vec![("code", Json::Number(code as f64)), ("message", Json::str(message))]);
+    return Json::object(vec![("jsonrpc", Json::str("2.0")), ("id", id), ("error", error)]);
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    return Json::object(vec![("jsonrpc", Json::str("2.0")), ("method", Json::str(method)), ("params", params)]);
+}
+
+fn lsp_position(p: Position) -> Json {
+    return Json::object(vec![
+        ("line", Json::Number(p.line.saturating_sub(1) as f64)),
+        ("character", Json::Number(p.column.saturating_sub(1) as f64)),
+    ]);
+}
+
+fn range(start: Position, end: Position) -> Json {
+    return Json::object(vec![("start", lsp_position(start)), ("end", lsp_position(end))]);
+}
+
+/// The range of a name that starts at `p`.
+fn name_range(p: Position, name: &str) -> Json {
+    return range(p, Position { line: p.line, column: p.column + name.chars().count() });
+}
+
+/// The range of the word starting at `p`, or of one character if there is
+/// no word there.
+fn word_range(text: &str, p: Position) -> Json {
+    let line = text.lines().nth(p.line.saturating_sub(1)).unwrap_or("");
+    let rest = line.chars().skip(p.column.saturating_sub(1));
+    let len = rest.take_while(|c| c.is_alphanumeric() || *c == '_').count().max(1);
+    return range(p, Position { line: p.line, column: p.column + len });
+}
+
+fn parse(text: &str) -> Option<Program> {
+    let mut p = Parser::new(Lexer::new(text));
+    let program = p.parse_program();
+    if !p.errors.is_empty() {
+        return None;
+    }
+    return program;
+}
+
+/// Finds the `let` that binds the name at a position.
+struct FindLet {
+    position: Position,
+    value: Option<Option<Expression>>,
+}
+
+impl Visitor for FindLet {
+    fn visit_let_statement(&mut self, ls: &LetStatement) {
+        if ls.name.position == self.position {
+            self.value = Some(ls.value.clone());
+        }
+        visit::walk_let_statement(self, ls);
+    }
+}
+
+/// The value of the `let` that binds the name at `position`, if a `let`
+/// does, and whether it's at the top level of the program.
+fn let_value(program: &Program, position: Position) -> Option<(Option<Expression>, bool)> {
+    let top_level = program.statements.iter().any(|s| matches!(s, Statement::LetStatement(ls) if ls.name.position == position));
+    let mut f = FindLet { position, value: None };
+    f.visit_program(program);
+    return f.value.map(|value| (value, top_level));
+}
+
+/// The type an expression evaluates to, where that's plain from the syntax.
+fn static_type(e: &Expression) -> Option<&'static str> {
+    return match e {
+        Expression::IntegerLiteral(_) => Some("INTEGER"),
+        Expression::StringLiteral(_) => Some("STRING"),
+        Expression::Boolean(_) => Some("BOOLEAN"),
+        Expression::FunctionLiteral(_) => Some("FUNCTION"),
+        _ => None,
+    };
+}
+
+pub struct Server {
+    documents: HashMap<String, String>,
+    shutdown: bool,
+    exited: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        return Self { documents: HashMap::new(), shutdown: false, exited: false };
+    }
+
+    /// Whether the client has sent `exit`.
+    pub fn exited(&self) -> bool {
+        return self.exited;
+    }
+
+    /// Handles one message from the client, returning the messages to send
+    /// back: a response for a request, plus any notifications.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => return self.notify(method, &params),
+        };
+        let result = match method {
+            "initialize" => Some(self.initialize()),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Json::Null)
+            },
+            "textDocument/hover" => self.at_position(&params, Server::hover),
+            "textDocument/definition" => self.at_position(&params, Server::definition),
+            "textDocument/documentSymbol" => Some(self.document(&params).map_or(Json::Null, |(_, text)| Server::symbols(text))),
+            "textDocument/completion" => Some(self.document(&params).map_or(Json::Null, |(_, text)| Server::completion(text))),
+            "textDocument/formatting" => Some(self.document(&params).map_or(Json::Null, |(_, text)| Server::formatting(text))),
+            _ => None,
+        };
+        return match result {
+            Some(result) => vec![response(id, result)],
+            None => vec![error_response(id, METHOD_NOT_FOUND, &format!("unknown method {}", method))],
+        };
+    }
+
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params.get("textDocument").and_then(|d| d.get("uri")).and_then(Json::as_str).unwrap_or("").to_string();
+        return match method {
+            "exit" => {
+                self.exited = true;
+                vec![]
+            },
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").and_then(|d| d.get("text")).and_then(Json::as_str).unwrap_or("");
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![self.diagnostics(&uri)]
+            },
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").and_then(Json::as_array);
+                if let Some(text) = changes.and_then(|c| c.last()).and_then(|c| c.get("text")).and_then(Json::as_str) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                vec![self.diagnostics(&uri)]
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                let params = Json::object(vec![("uri", Json::str(&uri)), ("diagnostics", Json::Array(vec![]))]);
+                vec![notification("textDocument/publishDiagnostics", params)]
+            },
+            _ => vec![],
+        };
+    }
+
+    fn initialize(&self) -> Json {
+        let capabilities = Json::object(vec![
+            ("textDocumentSync", Json::Number(1.0)),
+            ("hoverProvider", Json::Bool(true)),
+            ("definitionProvider", Json::Bool(true)),
+            ("documentSymbolProvider", Json::Bool(true)),
+            ("completionProvider", Json::object(vec![])),
+            ("documentFormattingProvider", Json::Bool(true)),
+        ]);
+        let info = Json::object(vec![("name", Json::str("waiig_rs")), ("version", Json::str(env!("CARGO_PKG_VERSION")))]);
+        return Json::object(vec![("capabilities", capabilities), ("serverInfo", info)]);
+    }
+
+    fn document<'a>(&'a self, params: &'a Json) -> Option<(&'a str, &'a str)> {
+        let uri = params.get("textDocument").and_then(|d| d.get("uri")).and_then(Json::as_str)?;
+        let text = self.documents.get(uri)?;
+        return Some((uri, text));
+    }
+
+    /// Runs `f` on the document and position a request is about.
+    fn at_position(&self, params: &Json, f: fn(&str, &str, Position) -> Json) -> Option<Json> {
+        let (uri, text) = match self.document(params) {
+            Some(document) => document,
+            None => return Some(Json::Null),
+        };
+        let p = params.get("position");
+        let line = p.and_then(|p| p.get("line")).and_then(Json::as_i64).unwrap_or(0) as usize;
+        let character = p.and_then(|p| p.get("character")).and_then(Json::as_i64).unwrap_or(0) as usize;
+        return Some(f(uri, text, Position { line: line + 1, column: character + 1 }));
+    }
+
+    fn diagnostics(&self, uri: &str) -> Json {
+        let text = self.documents.get(uri).map_or("", String::as_str);
+        let diagnostics = check::check_source(text).iter().map(|d| Json::object(vec![
+            ("range", word_range(text, d.position)),
+            ("severity", Json::Number(if d.severity == Severity::Error { 1.0 } else { 2.0 })),
+            ("code", Json::str(d.code)),
+            ("source", Json::str("waiig_rs")),
+            ("message", Json::str(&d.message)),
+        ])).collect();
+        let params = Json::object(vec![("uri", Json::str(uri)), ("diagnostics", Json::Array(diagnostics))]);
+        return notification("textDocument/publishDiagnostics", params);
+    }
+
+    /// The identifier under the cursor and what it refers to.
+    fn reference_at(text: &str, p: Position) -> Option<(Program, check::Reference)> {
+        let program = parse(text)?;
+        let reference = check::references(&program).into_iter().find(|r| {
+            r.position.line == p.line && r.position.column <= p.column && p.column < r.position.column + r.name.chars().count()
+        })?;
+        return Some((program, reference));
+    }
+
+    fn hover(_uri: &str, text: &str, p: Position) -> Json {
+        let (program, reference) = match Server::reference_at(text, p) {
+            Some(found) => found,
+            None => return Json::Null,
+        };
+        let mut lines: Vec<String> = Vec::new();
+        match reference.definition.and_then(|d| let_value(&program, d)) {
+            Some((value, top_level)) => {
+                let source = value.as_ref().map_or(String::from("null"), |v| v.to_string());
+                lines.push(format!("```monkey\nlet {} = {}\n```", reference.name, source));
+                let mut kind = value.as_ref().and_then(static_type).map(String::from);
+                if top_level {
+                    // top level bindings show the value they have once the
+                    // document has run
+                    let env = Arc::new(Mutex::new(Environment::new()));
+                    let run = AssertUnwindSafe(|| eval_with_limits(Node::Program(program.clone()), env.clone(), hover_limits()));
+                    let _ = std::panic::catch_unwind(run);
+                    if let Some(val) = env.lock().ok().and_then(|e| e.get(&reference.name)) {
+                        kind = Some(get_type(&val));
+                        lines.push(format!("{}: {} = {}", reference.name, get_type(&val), val.inspect()));
+                    }
+                }
+                if lines.len() == 1 {
+                    if let Some(kind) = kind {
+                        lines.push(format!("{}: {}", reference.name, kind));
+                    }
+                }
+            },
+            None if reference.definition.is_some() => lines.push(format!("```monkey\n{}\n```\nparameter", reference.name)),
+            None => match STD_FUNCTIONS.iter().find(|f| f.name == reference.name) {
+                Some(f) => lines.push(format!("```monkey\n{}\n```\nfrom {}\n\n{}", f.name, f.namespace, f.doc)),
+                None => return Json::Null,
+            },
+        }
+        let contents = Json::object(vec![("kind", Json::str("markdown")), ("value", Json::String(lines.join("\n\n")))]);
+        return Json::object(vec![("contents", contents), ("range", name_range(reference.position, &reference.name))]);
+    }
+
+    fn definition(uri: &str, text: &str, p: Position) -> Json {
+        return match Server::reference_at(text, p).and_then(|(_, r)| r.definition.map(|d| (d, r.name))) {
+            Some((d, name)) => Json::object(vec![("uri", Json::str(uri)), ("range", name_range(d, &name))]),
+            None => Json::Null,
+        };
+    }
+
+    /// The top level `let`s.
+    fn symbols(text: &str) -> Json {
+        let program = match parse(text) {
+            Some(program) => program,
+            None => return Json::Array(vec![]),
+        };
+        let symbols = program.statements.iter().filter_map(|s| match s {
+            Statement::LetStatement(ls) => {
+                let function = matches!(ls.value, Some(Expression::FunctionLiteral(_)));
+                Some(Json::object(vec![
+                    ("name", Json::str(&ls.name.value)),
+                    // the protocol's numbers for functions and variables
+                    ("kind", Json::Number(if function { 12.0 } else { 13.0 })),
+                    ("range", range(ls.position, ls.end)),
+                    ("selectionRange", name_range(ls.name.position, &ls.name.value)),
+                ]))
+            },
+            _ => None,
+        }).collect();
+        return Json::Array(symbols);
+    }
+
+    /// Identifiers in the document, `std` functions and keywords. This reads
+    /// tokens rather than parsing, so it works on code being typed.
+    fn completion(text: &str) -> Json {
+        let mut items: Vec<Json> = Vec::new();
+        let mut seen: Vec<String> = Vec::new();
+        let mut l = Lexer::new(text);
+        loop {
+            match l.next_token() {
+                Token::EOF => break,
+                Token::IDENT(name) if !seen.contains(&name) && STD_FUNCTIONS.iter().all(|f| f.name != name) => {
+                    items.push(Json::object(vec![("label", Json::str(&name)), ("kind", Json::Number(6.0))]));
+                    seen.push(name);
+                },
+                _ => (),
+            }
+        }
+        for f in STD_FUNCTIONS {
+            items.push(Json::object(vec![
+                ("label", Json::str(f.name)),
+                ("kind", Json::Number(3.0)),
+                ("detail", Json::str(f.namespace)),
+                ("documentation", Json::str(f.doc)),
+            ]));
+        }
+        for keyword in token::keywords() {
+            items.push(Json::object(vec![("label", Json::str(keyword)), ("kind", Json::Number(14.0))]));
+        }
+        return Json::Array(items);
+    }
+
+    /// One edit replacing the whole document, or none if it doesn't parse.
+    fn formatting(text: &str) -> Json {
+        return match crate::format::format_source(text) {
+            Ok(formatted) if formatted == text => Json::Array(vec![]),
+            Ok(formatted) => {
+                let end = Position { line: text.split('\n').count() + 1, column: 1 };
+                Json::Array(vec![Json::object(vec![
+                    ("range", range(Position { line: 1, column: 1 }, end)),
+                    ("newText", Json::String(formatted)),
+                ])])
+            },
+            Err(_) => Json::Null,
+        };
+    }
+}
+
+/// Serves the client on `input` and `output` until it sends `exit`. Returns
+/// whether it asked for a shutdown first, as the protocol expects.
+pub fn run(input: &mut dyn BufRead, output: &mut dyn Write) -> std::io::Result<bool> {
+    let mut server = Server::new();
+    while let Some(body) = read_message(input)? {
+        let replies = match json::parse(&body) {
+            Ok(message) => server.handle(&message),
+            Err(e) => vec![error_response(Json::Null, PARSE_ERROR, &e)],
+        };
+        for reply in replies.iter() {
+            write_message(output, reply)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+    return Ok(server.shutdown);
+}
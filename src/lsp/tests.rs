@@ -0,0 +1,204 @@
+#[cfg(test)]
+use crate::json::Json;
+#[cfg(test)]
+use crate::lsp::Server;
+
+/// A scripted client talking to a server in the same process.
+#[cfg(test)]
+struct Client {
+    server: Server,
+    next_id: usize,
+}
+
+#[cfg(test)]
+impl Client {
+    fn new() -> Self {
+        return Self { server: Server::new(), next_id: 1 };
+    }
+
+    /// Sends a request and returns its result.
+    fn request(&mut self, method: &str, params: Json) -> Json {
+        let id = self.next_id;
+        self.next_id += 1;
+        let message = Json::object(vec![
+            ("jsonrpc", Json::str("2.0")),
+            ("id", Json::Number(id as f64)),
+            ("method", Json::str(method)),
+            ("params", params),
+        ]);
+        let replies = self.server.handle(&message);
+        assert_eq!(1, replies.len());
+        assert_eq!(Some(id as i64), replies[0].get("id").and_then(Json::as_i64));
+        return replies[0].get("result").cloned().unwrap_or_else(|| panic!("error response: {}", replies[0]));
+    }
+
+    /// Sends a notification and returns the server's notifications.
+    fn notify(&mut self, method: &str, params: Json) -> Vec<Json> {
+        let message = Json::object(vec![("jsonrpc", Json::str("2.0")), ("method", Json::str(method)), ("params", params)]);
+        return self.server.handle(&message);
+    }
+
+    fn open(&mut self, text: &str) -> Vec<Json> {
+        let document = Json::object(vec![
+            ("uri", Json::str("file:///test.mk")),
+            ("languageId", Json::str("monkey")),
+            ("version", Json::Number(1.0)),
+            ("text", Json::str(text)),
+        ]);
+        return self.notify("textDocument/didOpen", Json::object(vec![("textDocument", document)]));
+    }
+
+    /// Sends a request about a line and character in the open document.
+    fn at(&mut self, method: &str, line: usize, character: usize) -> Json {
+        let params = Json::object(vec![
+            ("textDocument", Json::object(vec![("uri", Json::str("file:///test.mk"))])),
+            ("position", Json::object(vec![("line", Json::Number(line as f64)), ("character", Json::Number(character as f64))])),
+        ]);
+        return self.request(method, params);
+    }
+
+    fn document(&mut self, method: &str) -> Json {
+        let params = Json::object(vec![("textDocument", Json::object(vec![("uri", Json::str("file:///test.mk"))]))]);
+        return self.request(method, params);
+    }
+}
+
+/// The line and character a range starts at.
+#[cfg(test)]
+fn start(range: &Json) -> (i64, i64) {
+    let start = range.get("start").unwrap();
+    return (start.get("line").and_then(Json::as_i64).unwrap(), start.get("character").and_then(Json::as_i64).unwrap());
+}
+
+#[test]
+fn lsp_lifecycle() {
+    let mut client = Client::new();
+    let result = client.request("initialize", Json::object(vec![("capabilities", Json::object(vec![]))]));
+    let capabilities = result.get("capabilities").unwrap();
+    assert_eq!(Some(true), capabilities.get("hoverProvider").and_then(Json::as_bool));
+    assert_eq!(Some(1), capabilities.get("textDocumentSync").and_then(Json::as_i64));
+    assert!(client.notify("initialized", Json::object(vec![])).is_empty());
+
+    let reply = client.server.handle(&crate::json::parse(r#"{"jsonrpc": "2.0", "id": "x", "method": "nope"}"#).unwrap());
+    assert_eq!(Some(-32601), reply[0].get("error").and_then(|e| e.get("code")).and_then(Json::as_i64));
+
+    assert_eq!(Json::Null, client.request("shutdown", Json::Null));
+    assert!(!client.server.exited());
+    client.notify("exit", Json::Null);
+    assert!(client.server.exited());
+}
+
+#[test]
+fn lsp_diagnostics() {
+    let mut client = Client::new();
+    let published = client.open("let x = 1;\nfoo(x)");
+    assert_eq!(1, published.len());
+    assert_eq!(Some("textDocument/publishDiagnostics"), published[0].get("method").and_then(Json::as_str));
+    let diagnostics = published[0].get("params").and_then(|p| p.get("diagnostics")).and_then(Json::as_array).unwrap();
+    assert_eq!(1, diagnostics.len());
+    assert_eq!(Some("unknown identifier foo"), diagnostics[0].get("message").and_then(Json::as_str));
+    assert_eq!(Some(1), diagnostics[0].get("severity").and_then(Json::as_i64));
+    let range = diagnostics[0].get("range").unwrap();
+    assert_eq!((1, 0), start(range));
+    assert_eq!(Some(3), range.get("end").and_then(|e| e.get("character")).and_then(Json::as_i64));
+
+    // a change replaces the whole document
+    let change = Json::object(vec![
+        ("textDocument", Json::object(vec![("uri", Json::str("file:///test.mk")), ("version", Json::Number(2.0))])),
+        ("contentChanges", Json::Array(vec![Json::object(vec![("text", Json::str("let = 1"))])])),
+    ]);
+    let published = client.notify("textDocument/didChange", change);
+    let diagnostics = published[0].get("params").and_then(|p| p.get("diagnostics")).and_then(Json::as_array).unwrap();
+    assert_eq!(Some("syntax"), diagnostics[0].get("code").and_then(Json::as_str));
+}
+
+#[test]
+fn lsp_hover_and_definition() {
+    let mut client = Client::new();
+    client.open("import \"std.string\";\nlet n = 2 + 3;\nlet f = fn(s) { s };\nf(n) + strlen(\"ab\")");
+
+    let hover = client.at("textDocument/hover", 3, 2);
+    let value = hover.get("contents").and_then(|c| c.get("value")).and_then(Json::as_str).unwrap();
+    assert!(value.contains("let n = (2 + 3)"), "{}", value);
+    assert!(value.contains("n: INTEGER = 5"), "{}", value);
+    assert_eq!((3, 2), start(hover.get("range").unwrap()));
+
+    let hover = client.at("textDocument/hover", 2, 11);
+    let value = hover.get("contents").and_then(|c| c.get("value")).and_then(Json::as_str).unwrap();
+    assert!(value.contains("parameter"), "{}", value);
+
+    let hover = client.at("textDocument/hover", 3, 10);
+    let value = hover.get("contents").and_then(|c| c.get("value")).and_then(Json::as_str).unwrap();
+    assert!(value.contains("std.string") && value.contains("length"), "{}", value);
+
+    assert_eq!(Json::Null, client.at("textDocument/hover", 1, 8));
+
+    let definition = client.at("textDocument/definition", 3, 0);
+    assert_eq!(Some("file:///test.mk"), definition.get("uri").and_then(Json::as_str));
+    assert_eq!((2, 4), start(definition.get("range").unwrap()));
+    let definition = client.at("textDocument/definition", 2, 16);
+    assert_eq!((2, 11), start(definition.get("range").unwrap()));
+    assert_eq!(Json::Null, client.at("textDocument/definition", 3, 8));
+}
+
+#[test]
+fn lsp_symbols_completion_and_formatting() {
+    let mut client = Client::new();
+    client.open("let total = 1;\nlet add = fn(a, b) { a+b };\nadd(total,2)");
+
+    let symbols = client.document("textDocument/documentSymbol");
+    let symbols = symbols.as_array().unwrap();
+    assert_eq!(2, symbols.len());
+    assert_eq!(Some("total"), symbols[0].get("name").and_then(Json::as_str));
+    assert_eq!(Some(13), symbols[0].get("kind").and_then(Json::as_i64));
+    assert_eq!(Some(12), symbols[1].get("kind").and_then(Json::as_i64));
+    assert_eq!((1, 4), start(symbols[1].get("selectionRange").unwrap()));
+
+    let items = client.document("textDocument/completion");
+    let labels: Vec<&str> = items.as_array().unwrap().iter().filter_map(|i| i.get("label").and_then(Json::as_str)).collect();
+    for label in ["total", "add", "a", "b", "strlen", "let", "fn"] {
+        assert!(labels.contains(&label), "{} missing from {:?}", label, labels);
+    }
+    assert_eq!(1, labels.iter().filter(|l| **l == "add").count());
+
+    let edits = client.document("textDocument/formatting");
+    let edits = edits.as_array().unwrap();
+    assert_eq!(1, edits.len());
+    assert_eq!(Some("let total = 1;\nlet add = fn(a, b) {\n    a + b\n};\nadd(total, 2);\n"), edits[0].get("newText").and_then(Json::as_str));
+}
+
+#[test]
+fn lsp_over_stdio() {
+    use std::io::Write;
+    use crate::lsp::{read_message, run, write_message};
+
+    let mut input: Vec<u8> = Vec::new();
+    let messages = [
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}"#,
+        r#"{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {"textDocument": {"uri": "file:///a.mk", "text": "x"}}}"#,
+        "not json",
+        r#"{"jsonrpc": "2.0", "id": 2, "method": "shutdown"}"#,
+        r#"{"jsonrpc": "2.0", "method": "exit"}"#,
+    ];
+    for message in messages {
+        write!(input, "Content-Length: {}\r\n\r\n{}", message.len(), message).unwrap();
+    }
+
+    let mut output: Vec<u8> = Vec::new();
+    assert!(run(&mut input.as_slice(), &mut output).unwrap());
+
+    let mut replies: Vec<Json> = Vec::new();
+    let mut reader = output.as_slice();
+    while let Some(body) = read_message(&mut reader).unwrap() {
+        replies.push(crate::json::parse(&body).unwrap());
+    }
+    assert_eq!(4, replies.len());
+    assert!(replies[0].get("result").and_then(|r| r.get("capabilities")).is_some());
+    assert_eq!(Some("textDocument/publishDiagnostics"), replies[1].get("method").and_then(Json::as_str));
+    assert_eq!(Some(-32700), replies[2].get("error").and_then(|e| e.get("code")).and_then(Json::as_i64));
+    assert_eq!(Some(2), replies[3].get("id").and_then(Json::as_i64));
+
+    let mut echoed: Vec<u8> = Vec::new();
+    write_message(&mut echoed, &replies[3]).unwrap();
+    assert_eq!(Some(replies[3].to_string()), read_message(&mut echoed.as_slice()).unwrap());
+}
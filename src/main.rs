@@ -1,4 +1,5 @@
 #![allow(dead_code)]
+#![allow(clippy::needless_return)]
 
 mod token;
 mod lexer;
@@ -7,8 +8,161 @@ mod ast;
 mod object;
 mod evaluator;
 mod repl;
+mod format;
+mod json;
+mod dump;
+mod check;
+mod lsp;
+mod debugger;
+mod dap;
+mod profiler;
+mod coverage;
+mod run;
+mod testing;
+mod fuzz;
+#[cfg(test)]
+mod conformance;
+
+const USAGE: &str = "\
+usage: waiig_rs                  start the REPL
+       waiig_rs run [--profile] [--coverage <lcov file>] <file>
+                                 run a file and print its value; with --profile, also
+                                 report time and allocations per function and line, and
+                                 write collapsed stacks to <file>.folded; with --coverage,
+                                 write the lines and branches that ran as LCOV
+       waiig_rs test <files or directories...>
+                                 run the test_ functions in .mk files, failing if any fail
+       waiig_rs replay <file>    replay a REPL transcript, failing if any output differs
+       waiig_rs fmt [--check] [files...]
+                                 format files in place, or stdin to stdout; with --check,
+                                 list the files that aren't formatted instead
+       waiig_rs tokens <file> [--format json|sexp]
+                                 print the file's tokens with their spans
+       waiig_rs ast <file> [--format json|sexp]
+                                 print the file's syntax tree with spans
+       waiig_rs check [--format text|json] <files...>
+                                 report likely mistakes without running the files, failing
+                                 if any are errors
+       waiig_rs lsp              serve the Language Server Protocol on stdin and stdout
+       waiig_rs debug <file>     run a file under the step debugger
+       waiig_rs dap              serve the Debug Adapter Protocol on stdin and stdout
+       waiig_rs fuzz <lexer|parser|evaluator> [--seed <n>] [--iterations <n>]
+                                 feed generated input to the lexer, parser or evaluator,
+                                 failing with the input if it panics";
 
 fn main() -> std::io::Result<()>{
-    repl::run()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // one large stack for the whole command, rather than a thread per
+    // evaluation
+    return evaluator::with_eval_stack(|| command(&args)).map_err(std::io::Error::other)?;
+}
+
+fn command(args: &[String]) -> std::io::Result<()> {
+    match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        [] => repl::run()?,
+        ["run", rest @ ..] => {
+            let mut options = run::Options::default();
+            let mut path = None;
+            let mut args = rest.iter();
+            while let Some(arg) = args.next() {
+                match *arg {
+                    "--profile" => options.profile = true,
+                    "--coverage" => options.coverage = Some(args.next().unwrap_or_else(|| usage()).to_string()),
+                    file if path.is_none() => path = Some(file),
+                    _ => usage(),
+                }
+            }
+            let Some(path) = path else { usage() };
+            if !run::run_file(path, &options, &mut std::io::stdout())? {
+                std::process::exit(1);
+            }
+        },
+        ["test", paths @ ..] if !paths.is_empty() => {
+            if !testing::test_paths(paths, &mut std::io::stdout())? {
+                std::process::exit(1);
+            }
+        },
+        ["replay", path] => {
+            if !repl::replay(path)? {
+                std::process::exit(1);
+            }
+        },
+        ["fmt", rest @ ..] => {
+            let check = rest.contains(&"--check");
+            let files: Vec<&str> = rest.iter().copied().filter(|a| *a != "--check").collect();
+            if !format::format_files(&files, check, &mut std::io::stdout())? {
+                std::process::exit(1);
+            }
+        },
+        [command @ ("tokens" | "ast"), rest @ ..] => {
+            let (path, format) = match rest {
+                [path] => (*path, Some(dump::Format::Json)),
+                [path, "--format", name] | ["--format", name, path] => (*path, dump::Format::from_name(name)),
+                _ => usage(),
+            };
+            let Some(format) = format else { usage() };
+            let dumped = if *command == "tokens" {
+                dump::dump_tokens(path, format, &mut std::io::stdout())?
+            } else {
+                dump::dump_ast(path, format, &mut std::io::stdout())?
+            };
+            if !dumped {
+                std::process::exit(1);
+            }
+        },
+        ["lsp"] => {
+            let shutdown = lsp::run(&mut std::io::stdin().lock(), &mut std::io::stdout())?;
+            if !shutdown {
+                std::process::exit(1);
+            }
+        },
+        ["debug", path] => {
+            let input = Box::new(std::io::BufReader::new(std::io::stdin()));
+            let output = std::sync::Arc::new(std::sync::Mutex::new(std::io::stdout()));
+            if !debugger::debug_file(path, input, output) {
+                std::process::exit(1);
+            }
+        },
+        ["dap"] => {
+            let output = std::sync::Arc::new(std::sync::Mutex::new(std::io::stdout()));
+            dap::run(&mut std::io::stdin().lock(), output)?;
+        },
+        ["check", rest @ ..] => {
+            let (json, files) = match rest {
+                ["--format", "json", files @ ..] => (true, files),
+                ["--format", "text", files @ ..] => (false, files),
+                ["--format", ..] => usage(),
+                files => (false, files),
+            };
+            if files.is_empty() {
+                usage();
+            }
+            if !check::check_files(files, json, &mut std::io::stdout())? {
+                std::process::exit(1);
+            }
+        },
+        ["fuzz", name, rest @ ..] => {
+            let Some(target) = fuzz::Target::from_name(name) else { usage() };
+            let (mut seed, mut iterations) = (1, 10_000);
+            let mut args = rest.iter();
+            while let Some(arg) = args.next() {
+                let value = args.next().and_then(|v| v.parse().ok()).unwrap_or_else(|| usage());
+                match *arg {
+                    "--seed" => seed = value,
+                    "--iterations" => iterations = value,
+                    _ => usage(),
+                }
+            }
+            if !fuzz::fuzz_target(target, seed, iterations, &mut std::io::stdout())? {
+                std::process::exit(1);
+            }
+        },
+        _ => usage(),
+    }
     return Ok(());
 }
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+
+use super::{Environment, Object};
+
+type SharedEnv = Arc<Mutex<Environment<'static>>>;
+
+// collect automatically once this many environments are tracked, and then
+// again each time the number of survivors doubles
+const INITIAL_THRESHOLD: usize = 1024;
+
+/// Counters describing the cycle collector, for embedders to monitor.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GcStats {
+    /// Environments currently tracked, including any that are already dead.
+    pub tracked: usize,
+    /// Number of collections run so far.
+    pub collections: usize,
+    /// Environments freed by breaking reference cycles, in total.
+    pub freed: usize,
+}
+
+struct Registry {
+    /// Tracked environments by address. A `Weak` keeps its allocation, so
+    /// an address isn't reused while it's in here.
+    envs: HashMap<usize, Weak<Mutex<Environment<'static>>>>,
+    threshold: usize,
+    stats: GcStats,
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(|| Mutex::new(Registry {
+    envs: HashMap::new(),
+    threshold: INITIAL_THRESHOLD,
+    stats: GcStats::default(),
+}));
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    return REGISTRY.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+}
+
+/// Registers an environment that may end up in a reference cycle, e.g. one
+/// captured by a closure. Collects automatically when enough have piled up.
+pub fn track(env: &SharedEnv) {
+    let should_collect = {
+        let mut r = registry();
+        let key = Arc::as_ptr(env) as usize;
+        if r.envs.contains_key(&key) {
+            return;
+        }
+        r.envs.insert(key, Arc::downgrade(env));
+        r.stats.tracked = r.envs.len();
+        r.envs.len() >= r.threshold
+    };
+    if should_collect {
+        collect();
+    }
+}
+
+pub fn stats() -> GcStats {
+    return registry().stats;
+}
+
+/// Pushes the environments directly referenced by `obj` onto `out`.
+fn object_references(obj: &Object<'static>, out: &mut Vec<SharedEnv>) {
+    match obj {
+        Object::Function(f) => {
+            if let Some(env) = &f.env {
+                out.push(env.clone());
+            }
+        },
+        Object::ReturnValue(r) => object_references(r, out),
+        Object::TailCall(f, args, _) => {
+            if let Some(env) = &f.env {
+                out.push(env.clone());
+            }
+            args.iter().for_each(|a| object_references(a, out));
+        },
+        _ => (),
+    }
+}
+
+/// The environments referenced by `env`, or `None` if it is locked by a
+/// running evaluation and can't be inspected.
+fn env_references(env: &SharedEnv) -> Option<Vec<SharedEnv>> {
+    let env = env.try_lock().ok()?;
+    let mut out = Vec::new();
+    if let Some(parent) = &env.parent {
+        out.push(parent.clone());
+    }
+    env.store.values().for_each(|v| object_references(v, &mut out));
+    return Some(out);
+}
+
+/// Frees tracked environments that are only kept alive by reference cycles
+/// among themselves.
+///
+/// An environment is a root if something other than tracked environments
+/// (a running evaluation, an embedder, a value held on the Rust stack) holds
+/// a reference to it. Everything reachable from a root survives; the rest is
+/// garbage, and clearing its bindings breaks the cycles so it can be dropped.
+pub fn collect() -> GcStats {
+    let mut r = registry();
+    let envs: Vec<SharedEnv> = r.envs.values().filter_map(|e| e.upgrade()).collect();
+    let index: HashMap<*const Mutex<Environment<'static>>, usize> = envs
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (Arc::as_ptr(e), i))
+        .collect();
+
+    let edges: Vec<Option<Vec<usize>>> = envs
+        .iter()
+        .map(|e| env_references(e).map(|refs| refs
+            .iter()
+            .filter_map(|target| index.get(&Arc::as_ptr(target)).copied())
+            .collect()))
+        .collect();
+
+    let mut internal = vec![0; envs.len()];
+    edges.iter().flatten().flatten().for_each(|&target| internal[target] += 1);
+
+    // the upgrade above, the temporaries in `edges` and `env_references` are
+    // gone by now, so one strong reference is ours
+    let mut reachable = vec![false; envs.len()];
+    let mut stack: Vec<usize> = (0..envs.len())
+        .filter(|&i| edges[i].is_none() || Arc::strong_count(&envs[i]) - 1 > internal[i])
+        .collect();
+    while let Some(i) = stack.pop() {
+        if reachable[i] {
+            continue;
+        }
+        reachable[i] = true;
+        if let Some(targets) = &edges[i] {
+            stack.extend(targets.iter().filter(|&&t| !reachable[t]));
+        }
+    }
+
+    // values are dropped after every lock is released, since dropping them
+    // may free other environments in the same cycle
+    let mut garbage = Vec::new();
+    for (i, env) in envs.iter().enumerate() {
+        if reachable[i] {
+            continue;
+        }
+        if let Ok(mut env) = env.try_lock() {
+            garbage.push((std::mem::take(&mut env.store), env.parent.take()));
+        }
+    }
+
+    r.stats.collections += 1;
+    r.stats.freed += garbage.len();
+    drop(envs);
+    drop(garbage);
+    r.envs.retain(|_, e| e.strong_count() > 0);
+    r.stats.tracked = r.envs.len();
+    r.threshold = usize::max(INITIAL_THRESHOLD, r.envs.len() * 2);
+    return r.stats;
+}
@@ -1,8 +1,69 @@
-use std::sync::{Mutex, Arc};
+use std::sync::{Mutex, MutexGuard, Arc};
+
+pub mod gc;
 
 use crate::ast::{self, Inspect};
+use crate::token::Position;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum ErrorKind {
+    TypeError,
+    NameError,
+    StackOverflowError,
+    MemoryError,
+    InterruptedError,
+    ValueError,
+    ZeroDivisionError,
+    OverflowError,
+    AssertionError,
+    Error,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            ErrorKind::TypeError => "TypeError",
+            ErrorKind::NameError => "NameError",
+            ErrorKind::StackOverflowError => "StackOverflowError",
+            ErrorKind::MemoryError => "MemoryError",
+            ErrorKind::InterruptedError => "InterruptedError",
+            ErrorKind::ValueError => "ValueError",
+            ErrorKind::ZeroDivisionError => "ZeroDivisionError",
+            ErrorKind::OverflowError => "OverflowError",
+            ErrorKind::AssertionError => "AssertionError",
+            ErrorKind::Error => "Error",
+        };
+    }
+
+    /// Interruptions come from the host (budgets, timeouts, cancellation) and
+    /// must not be swallowed by a script's `try`.
+    pub fn is_catchable(&self) -> bool {
+        return *self != ErrorKind::InterruptedError;
+    }
+}
+
+/// A runtime error raised by the evaluator or by a `throw` statement.
+/// `position` is filled in by the statement that produced the error.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub position: Option<Position>,
+}
+
+impl RuntimeError {
+    pub fn new(kind: ErrorKind, message: String) -> Self {
+        return Self { kind, message, position: None };
+    }
+
+    pub fn inspect(&self) -> String {
+        return match self.position {
+            Some(pos) => format!("{}: {} (line {}, column {})", self.kind.as_str(), self.message, pos.line, pos.column),
+            None => format!("{}: {}", self.kind.as_str(), self.message),
+        };
+    }
+}
 
-#[derive(Debug)]
 pub struct Function<'a> {
     pub parameters: Vec<ast::Identifier>,
     pub body: ast::BlockStatement,
@@ -26,23 +87,79 @@ impl Clone for Function<'_> {
         Self {
             parameters: self.parameters.clone(),
             body: self.body.clone(),
-            env: None,
+            env: self.env.clone(),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+// the captured environment usually contains the function itself, so it is
+// left out to keep the output finite
+impl std::fmt::Debug for Function<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.debug_struct("Function")
+            .field("parameters", &self.parameters)
+            .field("body", &self.body)
+            .finish_non_exhaustive();
+    }
+}
+
+/// `Error` is an error in flight that unwinds evaluation; `Exception` is the
+/// same error once it has been caught and bound to a `catch` parameter.
+/// `TailCall` is a call in return position that the evaluator has deferred so
+/// it can run it without growing the Rust stack, with the callee as written
+/// at the call site; it never reaches user code.
+#[derive(Debug, Clone)]
 pub enum Object<'a> {
     Integer(i32),
     Boolean(bool),
     String(String),
     ReturnValue(Box<Object<'a>>),
+    TailCall(Box<Function<'a>>, Vec<Object<'a>>, String),
     Null,
-    Error(String),
+    Error(RuntimeError),
+    Exception(RuntimeError),
     Function(Function<'a>),
     Builtin(fn(&Vec<Object<'static>>)->Object<'static>),
 }
 
+impl PartialEq for Object<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        return match (self, other) {
+            (Object::Integer(a), Object::Integer(b)) => a == b,
+            (Object::Boolean(a), Object::Boolean(b)) => a == b,
+            (Object::String(a), Object::String(b)) => a == b,
+            (Object::ReturnValue(a), Object::ReturnValue(b)) => a == b,
+            (Object::TailCall(f, args, name), Object::TailCall(g, other_args, other_name)) => {
+                f == g && args == other_args && name == other_name
+            },
+            (Object::Null, Object::Null) => true,
+            (Object::Error(a), Object::Error(b)) => a == b,
+            (Object::Exception(a), Object::Exception(b)) => a == b,
+            (Object::Function(a), Object::Function(b)) => a == b,
+            // the same builtin can have more than one address, so this is a
+            // best effort
+            (Object::Builtin(a), Object::Builtin(b)) => std::ptr::fn_addr_eq(*a, *b),
+            _ => false,
+        };
+    }
+}
+
+// values of different types, functions and builtins are unordered
+impl PartialOrd for Object<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        return match (self, other) {
+            (Object::Integer(a), Object::Integer(b)) => a.partial_cmp(b),
+            (Object::Boolean(a), Object::Boolean(b)) => a.partial_cmp(b),
+            (Object::String(a), Object::String(b)) => a.partial_cmp(b),
+            (Object::ReturnValue(a), Object::ReturnValue(b)) => a.partial_cmp(b),
+            (Object::Null, Object::Null) => Some(std::cmp::Ordering::Equal),
+            (Object::Error(a), Object::Error(b)) => a.partial_cmp(b),
+            (Object::Exception(a), Object::Exception(b)) => a.partial_cmp(b),
+            _ => None,
+        };
+    }
+}
+
 impl Object<'_> {
     pub fn inspect(&self) -> String {
         return match self {
@@ -50,48 +167,69 @@ impl Object<'_> {
             Object::Boolean(b) => b.to_string(),
             Object::String(s) => s.to_string(),
             Object::ReturnValue(r) => r.inspect(),
-            Object::Error(e) => format!("Error: {}", e),
+            Object::TailCall(..) => String::from("tail call"),
+            Object::Error(e) => e.inspect(),
+            Object::Exception(e) => e.inspect(),
             Object::Null => "null".to_string(),
             Object::Function(f) => {
-                let mut params = String::new();
-                f.parameters.iter().for_each(|p| {
-                    params += p.to_string().as_str();
-                    params += ",";
-                });
-
-                format!("fn({}) {{{}}}", params, f.body.to_string())
+                let params: Vec<String> = f.parameters.iter().map(|p| p.to_string()).collect();
+                format!("fn({}) {{ {} }}", params.join(", "), f.body.to_string())
             },
             Object::Builtin(_) => String::from("builtin fn"),
         };
     }
 }
 
+/// An estimate of the bytes held by `obj`, used for memory accounting.
+pub fn size_of(obj: &Object) -> usize {
+    let own = std::mem::size_of::<Object>();
+    return match obj {
+        Object::String(s) => own + s.capacity(),
+        Object::Error(e) | Object::Exception(e) => own + e.message.capacity(),
+        Object::ReturnValue(r) => own + size_of(r),
+        Object::TailCall(_, args, _) => own + args.iter().map(size_of).sum::<usize>(),
+        Object::Function(f) => own + std::mem::size_of::<Function>() + f.parameters.len() * std::mem::size_of::<ast::Identifier>(),
+        _ => own,
+    };
+}
+
 pub fn get_type(obj: &Object) -> String {
     return match obj {
         Object::Integer(_) => String::from("INTEGER"),
         Object::Boolean(_) => String::from("BOOLEAN"),
         Object::String(_) => String::from("STRING"),
         Object::ReturnValue(_) => String::from("RETURN_VALUE"),
+        Object::TailCall(..) => String::from("TAIL_CALL"),
         Object::Error(_) => String::from("ERROR"),
+        Object::Exception(_) => String::from("ERROR"),
         Object::Null => String::from("NULL"),
         Object::Function(_) => String::from("FUNCTION"),
         Object::Builtin(_) => String::from("BUILTIN"),
     };
 }
 
+/// Locks `env`, waiting for any other thread using it. A panic on another
+/// thread while it held the lock leaves the environment intact, as every
+/// change to it is a single insert, so the lock is taken anyway.
+pub fn lock_env<'e, 'a>(env: &'e Mutex<Environment<'a>>) -> MutexGuard<'e, Environment<'a>> {
+    return env.lock().unwrap_or_else(|e| e.into_inner());
+}
+
 #[derive(Debug)]
 pub struct Environment<'a> {
-    store: Box<std::collections::HashMap<String, Object<'a>>>,
+    store: std::collections::HashMap<String, Object<'a>>,
     parent: Option<Arc<Mutex<Environment<'a>>>>,
     pub imports: Vec<String>,
+    size: usize,
 }
 
 impl <'a>Environment<'a> {
     pub fn new() -> Self {
         return Self {
-            store: Box::new(std::collections::HashMap::new()),
+            store: std::collections::HashMap::new(),
             parent: None,
             imports: Vec::new(),
+            size: std::mem::size_of::<Self>(),
         };
     }
 
@@ -99,21 +237,44 @@ impl <'a>Environment<'a> {
         if let Some(obj) = self.store.get(name) {
             return Some(obj.clone());
         } else if let Some(parent) = &self.parent {
-            let p = parent.try_lock().expect("error locking parent");
-            return p.get(name);
+            return lock_env(parent).get(name);
         }
         return None;
     }
 
     pub fn set<'b>(&'b mut self, name: String, val: Object<'a>) {
-        self.store.insert(name, val);
+        let name_size = name.capacity();
+        self.size += name_size + size_of(&val);
+        if let Some(old) = self.store.insert(name, val) {
+            // the existing key is kept, so the new name isn't held either
+            self.size -= name_size + size_of(&old);
+        }
+    }
+
+    /// This environment's own bindings, sorted by name.
+    pub fn bindings(&self) -> Vec<(&String, &Object<'a>)> {
+        let mut bindings: Vec<(&String, &Object<'a>)> = self.store.iter().collect();
+        bindings.sort_by(|a, b| a.0.cmp(b.0));
+        return bindings;
+    }
+
+    /// The enclosing environment, if this isn't the global one.
+    pub fn parent(&self) -> Option<Arc<Mutex<Environment<'a>>>> {
+        return self.parent.clone();
+    }
+
+    /// An estimate of the bytes held by this environment's own bindings,
+    /// not counting its parents.
+    pub fn size(&self) -> usize {
+        return self.size;
     }
 }
 
 pub fn new_enclosed_env(parent: Arc<Mutex<Environment>>) -> Environment {
     return Environment {
-        store: Box::new(std::collections::HashMap::new()),
+        store: std::collections::HashMap::new(),
         parent: Some(parent),
         imports: Vec::new(),
+        size: std::mem::size_of::<Environment>(),
     }
 }
@@ -1,14 +1,34 @@
+use std::cell::Cell;
 use crate::ast::{self, BlockStatement};
 use crate::lexer::Lexer;
-use crate::token::{get_literal, Token};
-
-mod tests;
+use crate::token::{get_literal, Comment, Position, Token};
+
+/// How deeply expressions can nest inside one another. The parser recurses
+/// once per level, so without a limit deep enough input overflows the stack.
+pub const MAX_NESTING: usize = 1000;
+/// How deep an expression's tree can be. Operators chained like `1 + 2 + 3`
+/// don't nest, but each puts what precedes it a level down the tree, and the
+/// evaluator and the tools that walk the tree recurse once per level.
+pub const MAX_TREE_DEPTH: usize = 5000;
+/// A stack that's enough, in a debug build, to parse input at those limits or
+/// to walk its tree.
+pub const NESTING_STACK: usize = 128 * 1024 * 1024;
+/// Extra stack given to threads started by `with_stack`, for the frames
+/// between the thread starting and it asking for a stack of its own.
+const STACK_HEADROOM: usize = 1024 * 1024;
+
+thread_local! {
+    /// Where the stack of a thread started by `with_stack` begins, and how
+    /// big it is.
+    static STACK: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
 
 type PrefixParseFn = fn(&mut Parser) -> Option<ast::Expression>;
 type InfixParseFn = fn(&mut Parser, ast::Expression) -> Option<ast::Expression>;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, PartialOrd)]
-enum Priority {
+pub enum Priority {
     LOWEST,
     EQUALS,
     LESSGREATER,
@@ -19,30 +39,30 @@ enum Priority {
     CALL,
 }
 
-fn get_priority(t: &Token) -> Priority {
+pub fn get_priority(t: &Token) -> Priority {
     return match t {
         Token::EQ | Token::NEQ => Priority::EQUALS,
         Token::LT | Token::GT => Priority::LESSGREATER,
         Token::PLUS | Token::MINUS => Priority::SUM,
         Token::SLASH | Token::ASTERISK => Priority::PRODUCT,
         Token::POWER => Priority::POWER,
-        Token::LPAREN => Priority::CALL,
+        Token::LPAREN | Token::DOT => Priority::CALL,
         _ => Priority::LOWEST,
     };
 }
 
-fn parse_call_arguments(p: &mut Parser) -> Option<Vec<Box<ast::Expression>>> {
-    let mut args: Vec<Box<ast::Expression>> = Vec::new();
+fn parse_call_arguments(p: &mut Parser) -> Option<Vec<ast::Expression>> {
+    let mut args: Vec<ast::Expression> = Vec::new();
     p.next_token();
     if p.curr_token == Token::RPAREN {
         return None;
     }
 
-    args.push(Box::new(p.parse_expression(Priority::LOWEST).unwrap()));
+    args.push(p.parse_expression(Priority::LOWEST)?);
     while p.peek_token == Token::COMMA {
         p.next_token();
         p.next_token();
-        args.push(Box::new(p.parse_expression(Priority::LOWEST).unwrap()));
+        args.push(p.parse_expression(Priority::LOWEST)?);
     }
 
     if !p.expect_peek(Token::RPAREN) {
@@ -59,6 +79,8 @@ fn parse_call_expression(p: &mut Parser, function: ast::Expression) -> Option<as
         token: p.curr_token.clone(),
         function: Box::new(function),
         arguments: Vec::new(),
+        position: Position::default(),
+        end: Position::default(),
     };
     if let Some(args) = parse_call_arguments(p) {
         exp.arguments = args;
@@ -70,6 +92,8 @@ fn parse_block_statement(p: &mut Parser) -> Option<ast::BlockStatement> {
     let mut block = ast::BlockStatement {
         token: p.curr_token.clone(),
         statements: Vec::new(),
+        position: p.curr_position,
+        end: Position::default(),
     };
 
     p.next_token();
@@ -79,6 +103,7 @@ fn parse_block_statement(p: &mut Parser) -> Option<ast::BlockStatement> {
         }
         p.next_token();
     }
+    block.end = p.curr_position;
 
     return Some(block);
 }
@@ -92,14 +117,18 @@ fn parse_function_parameters(p: &mut Parser) -> Option<Vec<ast::Identifier>> {
     let first_ident = ast::Identifier {
         token: p.curr_token.clone(),
         value: get_literal(&p.curr_token),
+        position: p.curr_position,
+        end: p.curr_end,
     };
     idents.push(first_ident);
-    while p.peek_token == Token::COMMA || idents.len() == 0 {
+    while p.peek_token == Token::COMMA || idents.is_empty() {
         p.next_token();
         p.next_token();
         let ident = ast::Identifier {
             token: p.curr_token.clone(),
             value: get_literal(&p.curr_token),
+            position: p.curr_position,
+            end: p.curr_end,
         };
         idents.push(ident);
     }
@@ -118,8 +147,12 @@ fn parse_function_literal(p: &mut Parser) -> Option<ast::Expression> {
         parameters: Vec::new(),
         body: BlockStatement {
             token: Token::EOF,
-            statements: Vec::new()
-        }
+            statements: Vec::new(),
+            position: Position::default(),
+            end: Position::default(),
+        },
+        position: Position::default(),
+        end: Position::default(),
     };
 
     if !p.expect_peek(Token::LPAREN) {
@@ -139,7 +172,8 @@ fn parse_function_literal(p: &mut Parser) -> Option<ast::Expression> {
     if let Some(bs) = parse_block_statement(p) {
         lit.body = bs;
     } else {
-        p.errors.push("Error parsing function body".to_string());
+        let position = p.curr_position;
+        p.error("Error parsing function body".to_string(), position);
         return None;
     }
 
@@ -153,8 +187,12 @@ fn parse_if_statement(p: &mut Parser) -> Option<ast::Expression> {
         consequence: BlockStatement {
             token: Token::EOF,
             statements: Vec::new(),
+            position: Position::default(),
+            end: Position::default(),
         },
         alternative: None,
+        position: Position::default(),
+        end: Position::default(),
     };
 
     if !p.expect_peek(Token::LPAREN) {
@@ -164,7 +202,7 @@ fn parse_if_statement(p: &mut Parser) -> Option<ast::Expression> {
     p.next_token();
     p.next_token();
     let cond = p.parse_expression(Priority::LOWEST);
-    exp.condition = Box::new(cond.unwrap());
+    exp.condition = Box::new(cond?);
 
     // TODO for some reason the token is getting advanced somewhere it shouldn't
     // be ... or something. the book's peek_token advances the token and mine
@@ -199,6 +237,72 @@ fn parse_if_statement(p: &mut Parser) -> Option<ast::Expression> {
     return Some(ast::Expression::IfExpression(exp));
 }
 
+fn parse_try_expression(p: &mut Parser) -> Option<ast::Expression> {
+    let mut exp = ast::TryExpression {
+        token: p.curr_token.clone(),
+        body: BlockStatement {
+            token: Token::EOF,
+            statements: Vec::new(),
+            position: Position::default(),
+            end: Position::default(),
+        },
+        parameter: None,
+        handler: None,
+        finalizer: None,
+        position: Position::default(),
+        end: Position::default(),
+    };
+
+    if !p.expect_peek(Token::LSQUIRLY) {
+        return None;
+    }
+    p.next_token();
+    exp.body = parse_block_statement(p)?;
+
+    if p.peek_token == Token::CATCH {
+        p.next_token();
+        if p.peek_token == Token::LPAREN {
+            p.next_token();
+            if !p.expect_peek(Token::IDENT(String::new())) {
+                return None;
+            }
+            p.next_token();
+            exp.parameter = Some(ast::Identifier {
+                token: p.curr_token.clone(),
+                value: get_literal(&p.curr_token),
+                position: p.curr_position,
+                end: p.curr_end,
+            });
+            if !p.expect_peek(Token::RPAREN) {
+                return None;
+            }
+            p.next_token();
+        }
+        if !p.expect_peek(Token::LSQUIRLY) {
+            return None;
+        }
+        p.next_token();
+        exp.handler = parse_block_statement(p);
+    }
+
+    if p.peek_token == Token::FINALLY {
+        p.next_token();
+        if !p.expect_peek(Token::LSQUIRLY) {
+            return None;
+        }
+        p.next_token();
+        exp.finalizer = parse_block_statement(p);
+    }
+
+    if exp.handler.is_none() && exp.finalizer.is_none() {
+        let position = p.curr_position;
+        p.error("try expression requires a catch or finally block".to_string(), position);
+        return None;
+    }
+
+    return Some(ast::Expression::TryExpression(exp));
+}
+
 fn parse_grouped_expression(p: &mut Parser) -> Option<ast::Expression> {
     p.next_token();
     let exp = p.parse_expression(Priority::LOWEST);
@@ -214,18 +318,25 @@ fn parse_identifier(p: &mut Parser) -> Option<ast::Expression> {
     return Some(ast::Expression::Identifier(ast::Identifier {
         token: p.curr_token.clone(),
         value: get_literal(&p.curr_token),
+        position: p.curr_position,
+        end: p.curr_end,
     }));
 }
 
 fn parse_integer_literal(p: &mut Parser) -> Option<ast::Expression> {
-    if let Ok(val) = get_literal(&p.curr_token).parse() {
+    let literal = get_literal(&p.curr_token);
+    if let Ok(val) = literal.parse() {
         let lit = ast::IntegerLiteral {
             token: p.curr_token.clone(),
             value: val,
+            position: Position::default(),
+            end: Position::default(),
         };
 
         return Some(ast::Expression::IntegerLiteral(lit));
     }
+    // literals are only ever digits, so they fail to parse by being too big
+    p.error(format!("integer literal {} is too large", literal), p.curr_position);
     return None;
 }
 
@@ -234,6 +345,8 @@ fn parse_string_literal(p: &mut Parser) -> Option<ast::Expression> {
         return Some(ast::Expression::StringLiteral(ast::StringLiteral{
             token: p.curr_token.clone(),
             value: s.to_string(),
+            position: Position::default(),
+            end: Position::default(),
         }));
     }
     return None;
@@ -245,7 +358,9 @@ fn parse_prefix_expression(p: &mut Parser) -> Option<ast::Expression> {
     let expression = ast::PrefixExpression {
         token: tok.clone(),
         operator: get_literal(&tok),
-        right: Box::new(p.parse_expression(Priority::PREFIX).unwrap()),
+        right: Box::new(p.parse_expression(Priority::PREFIX)?),
+        position: Position::default(),
+        end: Position::default(),
     };
     return Some(ast::Expression::PrefixExpression(expression));
 }
@@ -256,18 +371,42 @@ fn parse_infix_expression(p: &mut Parser, exp: ast::Expression) -> Option<ast::E
         operator: get_literal(&p.curr_token),
         left: Box::new(exp),
         right: Box::new(ast::Expression::Empty),
+        position: Position::default(),
+        end: Position::default(),
     };
 
     let priority = p.curr_priority();
     p.next_token();
-    expression.right = Box::new(p.parse_expression(priority).unwrap());
+    expression.right = Box::new(p.parse_expression(priority)?);
     return Some(ast::Expression::InfixExpression(expression));
 }
 
+fn parse_member_expression(p: &mut Parser, object: ast::Expression) -> Option<ast::Expression> {
+    let token = p.curr_token.clone();
+    if !p.expect_peek(Token::IDENT(String::new())) {
+        return None;
+    }
+    p.next_token();
+    return Some(ast::Expression::MemberExpression(ast::MemberExpression {
+        token,
+        object: Box::new(object),
+        property: ast::Identifier {
+            token: p.curr_token.clone(),
+            value: get_literal(&p.curr_token),
+            position: p.curr_position,
+            end: p.curr_end,
+        },
+        position: Position::default(),
+        end: Position::default(),
+    }));
+}
+
 fn parse_boolean(p: &mut Parser) -> Option<ast::Expression> {
     return Some(ast::Expression::Boolean(ast::BooleanLiteral {
         token: p.curr_token.clone(),
-        value: if p.curr_token == Token::TRUE { true } else { false },
+        value: p.curr_token == Token::TRUE,
+        position: Position::default(),
+        end: Position::default(),
     }));
 }
 
@@ -281,6 +420,7 @@ fn get_prefix_fn(token: &Token) -> Option<PrefixParseFn> {
         Token::LPAREN => Some(parse_grouped_expression),
         Token::IF => Some(parse_if_statement),
         Token::FUNCTION => Some(parse_function_literal),
+        Token::TRY => Some(parse_try_expression),
         _ => None,
     };
 }
@@ -297,15 +437,58 @@ fn get_infix_fn(token: Token) -> Option<InfixParseFn> {
         | Token::GT
         | Token::POWER => Some(parse_infix_expression),
         Token::LPAREN => Some(parse_call_expression),
+        Token::DOT => Some(parse_member_expression),
         _ => None,
     };
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: Position,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{}", self.message);
+    }
+}
+
+impl ParseError {
+    /// The offending source line with a caret under the column the error was
+    /// found at, followed by the message.
+    pub fn render(&self, source: &str) -> String {
+        let lines: Vec<&str> = source.lines().collect();
+        let (line, column) = match lines.get(self.position.line.saturating_sub(1)) {
+            Some(line) => (*line, self.position.column),
+            // errors at the end of input point just past the last line
+            None => {
+                let line = lines.last().copied().unwrap_or("");
+                (line, line.chars().count() + 1)
+            },
+        };
+        let indent = " ".repeat(column.saturating_sub(1));
+        return format!("{}\n{}^ {}", line, indent, self.message);
+    }
+}
+
 pub struct Parser<'a> {
     l: Lexer<'a>,
     pub curr_token: Token,
     pub peek_token: Token,
-    pub errors: Vec<String>,
+    pub curr_position: Position,
+    pub peek_position: Position,
+    /// Where the current and peek tokens end.
+    pub curr_end: Position,
+    pub peek_end: Position,
+    pub errors: Vec<ParseError>,
+    // how deeply the expression being parsed nests inside others
+    depth: usize,
+    // how deep the tree of the expression just parsed is
+    height: usize,
+    // set once the input nests too deeply, after which nothing more is
+    // parsed and the errors from unwinding aren't reported
+    abandoned: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -314,7 +497,14 @@ impl<'a> Parser<'a> {
             l,
             curr_token: Token::EOF,
             peek_token: Token::EOF,
+            curr_position: Position::default(),
+            peek_position: Position::default(),
+            curr_end: Position::default(),
+            peek_end: Position::default(),
             errors: Vec::new(),
+            depth: 0,
+            height: 0,
+            abandoned: false,
         };
         p.next_token();
         p.next_token();
@@ -323,7 +513,11 @@ impl<'a> Parser<'a> {
 
     fn next_token(&mut self) {
         self.curr_token = self.peek_token.clone();
+        self.curr_position = self.peek_position;
+        self.curr_end = self.peek_end;
         self.peek_token = self.l.next_token();
+        self.peek_position = self.l.token_position;
+        self.peek_end = self.l.token_end;
     }
 
     fn expect_peek(&mut self, t: Token) -> bool {
@@ -384,12 +578,18 @@ impl<'a> Parser<'a> {
         return false;
     }
 
+    fn error(&mut self, message: String, position: Position) {
+        if !self.abandoned {
+            self.errors.push(ParseError { message, position });
+        }
+    }
+
     fn peek_error(&mut self, t: Token) {
         let msg = format!(
             "expected next token to be {:?}, got {:?} instead",
             t, self.peek_token
         );
-        self.errors.push(msg);
+        self.error(msg, self.peek_position);
     }
 
     fn peek_priority(&self) -> Priority {
@@ -401,24 +601,29 @@ impl<'a> Parser<'a> {
     }
 
     fn no_prefix_parse_fn_error(&mut self, t: Token) {
-        let msg = format!("no prefix parse function for {} found", get_literal(&t));
-        self.errors.push(msg);
+        let msg = match t {
+            Token::EOF => String::from("unexpected end of input"),
+            _ => format!("no prefix parse function for {} found", get_literal(&t)),
+        };
+        self.error(msg, self.curr_position);
     }
 
     fn parse_import_statement(&mut self) -> Option<ast::Statement> {
+        let position = self.curr_position;
         if !self.expect_peek(Token::STRING(String::from(""))) {
             return None;
         }
         self.next_token();
         let namespace = parse_string_literal(self);
         if let Some(ast::Expression::StringLiteral(sl)) = namespace {
-            if !self.expect_peek(Token::SEMICOLON) {
-                return None;
+            if self.peek_token == Token::SEMICOLON {
+                self.next_token();
             }
-            self.next_token();
             return Some(ast::Statement::ImportStatement(ast::ImportStatement {
                 token: Token::IMPORT,
-                namespace: sl
+                namespace: sl,
+                position,
+                end: self.curr_end,
             }));
         }
         return None;
@@ -430,8 +635,12 @@ impl<'a> Parser<'a> {
             name: ast::Identifier {
                 token: Token::ILLEGAL,
                 value: String::new(),
+                position: Position::default(),
+                end: Position::default(),
             },
             value: None,
+            position: self.curr_position,
+            end: Position::default(),
         };
 
         if !self.expect_peek(Token::IDENT(String::new())) {
@@ -440,6 +649,8 @@ impl<'a> Parser<'a> {
         self.next_token();
         stmt.name.token = self.curr_token.clone();
         stmt.name.value = get_literal(&self.curr_token);
+        stmt.name.position = self.curr_position;
+        stmt.name.end = self.curr_end;
         if !self.expect_peek(Token::ASSIGN) {
             return None;
         }
@@ -450,6 +661,7 @@ impl<'a> Parser<'a> {
             self.next_token();
         }
 
+        stmt.end = self.curr_end;
         return Some(ast::Statement::LetStatement(stmt));
     }
 
@@ -457,6 +669,8 @@ impl<'a> Parser<'a> {
         let mut stmt = ast::ReturnStatement {
             token: self.curr_token.clone(),
             return_val: ast::Expression::Empty,
+            position: self.curr_position,
+            end: Position::default(),
         };
         self.next_token();
         if let Some(exp) = self.parse_expression(Priority::LOWEST) {
@@ -465,16 +679,91 @@ impl<'a> Parser<'a> {
         if self.peek_token == Token::SEMICOLON {
             self.next_token();
         }
+        stmt.end = self.curr_end;
         return Some(ast::Statement::ReturnStatement(stmt));
     }
 
+    fn parse_throw_statement(&mut self) -> Option<ast::Statement> {
+        let mut stmt = ast::ThrowStatement {
+            token: self.curr_token.clone(),
+            value: ast::Expression::Empty,
+            position: self.curr_position,
+            end: Position::default(),
+        };
+        self.next_token();
+        if let Some(exp) = self.parse_expression(Priority::LOWEST) {
+            stmt.value = exp;
+        }
+        if self.peek_token == Token::SEMICOLON {
+            self.next_token();
+        }
+        stmt.end = self.curr_end;
+        return Some(ast::Statement::ThrowStatement(stmt));
+    }
+
+    /// Counts one more level of nesting, reporting an error and abandoning
+    /// the parse if that's too deep.
+    fn nest(&mut self) -> bool {
+        if self.depth == MAX_NESTING {
+            self.error(format!("expression nested more than {} deep", MAX_NESTING), self.curr_position);
+            self.abandoned = true;
+            return false;
+        }
+        self.depth += 1;
+        return true;
+    }
+
+    /// Records that the expression just parsed sits a level above the
+    /// deepest of those it contains, reporting an error and abandoning the
+    /// parse if that makes its tree too deep.
+    fn grow(&mut self, below: usize) -> bool {
+        if below == MAX_TREE_DEPTH {
+            self.error(format!("expression tree more than {} deep", MAX_TREE_DEPTH), self.curr_position);
+            self.abandoned = true;
+            return false;
+        }
+        self.height = below + 1;
+        return true;
+    }
+
     fn parse_expression(&mut self, p: Priority) -> Option<ast::Expression> {
+        let depth = self.depth;
+        // the expressions parsed before this one are its siblings, so the
+        // tallest of them all is what the enclosing expression sits above
+        let height = self.height;
+        self.height = 0;
+        let exp = self.parse_nested_expression(p);
+        self.depth = depth;
+        self.height = self.height.max(height);
+        return exp;
+    }
+
+    fn parse_nested_expression(&mut self, p: Priority) -> Option<ast::Expression> {
+        if !self.nest() {
+            return None;
+        }
         if let Some(prefix) = get_prefix_fn(&self.curr_token) {
+            // every expression here starts where its leftmost operand does
+            let position = self.curr_position;
             let mut exp = prefix(self);
+            if let Some(exp) = exp.as_mut() {
+                exp.set_span(position, self.curr_end);
+            }
+            if !self.grow(self.height) {
+                return None;
+            }
             while self.peek_token != Token::SEMICOLON && p < self.peek_priority() {
                 if let Some(infix) = get_infix_fn(self.peek_token.clone()) {
+                    let left = self.height;
+                    self.height = 0;
                     self.next_token();
-                    exp = infix(self, exp.unwrap());
+                    exp = infix(self, exp?);
+                    if !self.grow(left.max(self.height)) {
+                        return None;
+                    }
+                    if let Some(exp) = exp.as_mut() {
+                        exp.set_span(position, self.curr_end);
+                    }
                 } else {
                     return exp;
                 }
@@ -486,17 +775,20 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_expression_statement(&mut self) -> Option<ast::Statement> {
-        let stmt = ast::ExpressionStatement {
-            token: self.curr_token.clone(),
-            expression: match self.parse_expression(Priority::LOWEST) {
-                Some(exp) => exp,
-                None => ast::Expression::Empty,
-            },
+        let position = self.curr_position;
+        let expression = match self.parse_expression(Priority::LOWEST) {
+            Some(exp) => exp,
+            None => ast::Expression::Empty,
         };
-
         if self.peek_token == Token::SEMICOLON {
             self.next_token();
         }
+        let stmt = ast::ExpressionStatement {
+            token: expression.first_token(),
+            position,
+            end: self.curr_end,
+            expression,
+        };
 
         return Some(ast::Statement::ExpressionStatement(stmt));
     }
@@ -506,15 +798,21 @@ impl<'a> Parser<'a> {
             Token::LET => self.parse_let_statement(),
             Token::RETURN => self.parse_return_statement(),
             Token::IMPORT => self.parse_import_statement(),
+            Token::THROW => self.parse_throw_statement(),
             _ => self.parse_expression_statement(),
         };
     }
 
+    /// The comments the lexer has skipped so far.
+    pub fn comments(&self) -> &Vec<Comment> {
+        return &self.l.comments;
+    }
+
     pub fn parse_program(&mut self) -> Option<ast::Program> {
         let mut prog = ast::Program {
             statements: Vec::new(),
         };
-        while self.curr_token != Token::EOF {
+        while self.curr_token != Token::EOF && !self.abandoned {
             if let Some(statement) = self.parse_statement() {
                 prog.statements.push(statement);
             }
@@ -523,3 +821,55 @@ impl<'a> Parser<'a> {
         return Some(prog);
     }
 }
+
+/// Roughly how much of this thread's stack is still free, or 0 if the
+/// thread wasn't started by `with_stack`.
+fn stack_left() -> usize {
+    let here = 0u8;
+    let address = std::ptr::addr_of!(here) as usize;
+    return STACK.with(|s| s.get().map_or(0, |(start, size)| size.saturating_sub(start.abs_diff(address))));
+}
+
+/// Runs `f` with at least `size` bytes of stack free. That's on the current
+/// thread when it was started here with enough still left, and otherwise on
+/// a new thread called `name`, so nested and repeated calls from one such
+/// thread don't each pay for starting a thread.
+pub fn with_stack<T: Send>(name: &str, size: usize, f: impl FnOnce() -> T + Send) -> std::io::Result<T> {
+    if stack_left() >= size {
+        return Ok(f());
+    }
+    return std::thread::scope(|scope| {
+        let handle = std::thread::Builder::new()
+            .name(String::from(name))
+            .stack_size(size.saturating_add(STACK_HEADROOM))
+            .spawn_scoped(scope, || {
+                let here = 0u8;
+                STACK.with(|s| s.set(Some((std::ptr::addr_of!(here) as usize, size.saturating_add(STACK_HEADROOM)))));
+                return f();
+            })?;
+        return match handle.join() {
+            Ok(ret) => Ok(ret),
+            Err(panic) => std::panic::resume_unwind(panic),
+        };
+    });
+}
+
+/// Runs `f` with a `NESTING_STACK` sized stack, so input nested up to the
+/// limits can be parsed and walked from any thread. Fails if a thread is
+/// needed and can't be started.
+pub fn with_nesting_stack<T: Send>(f: impl FnOnce() -> T + Send) -> Result<T, String> {
+    return with_stack("monkey-parse", NESTING_STACK, f).map_err(|e| format!("error starting the parser: {}", e));
+}
+
+/// Parses `source`, rendering any parse errors under the lines they're on.
+pub fn parse_source(source: &str) -> Result<ast::Program, String> {
+    return with_nesting_stack(|| {
+        let mut p = Parser::new(Lexer::new(source));
+        let program = p.parse_program();
+        if !p.errors.is_empty() {
+            let errors: Vec<String> = p.errors.iter().map(|e| e.render(source)).collect();
+            return Err(errors.join("\n"));
+        }
+        return program.ok_or_else(|| String::from("error parsing program"));
+    })?;
+}
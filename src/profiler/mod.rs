@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::ast::{Node, Program, Statement};
+use crate::evaluator::{eval_with_hook, Hook, Limits, Shared};
+use crate::object::{Environment, Function, Object};
+
+#[cfg(test)]
+mod tests;
+
+const MAIN: &str = "<main>";
+
+/// What was recorded for a function or a source line. Inclusive time counts
+/// everything that ran until it finished, but only for the outermost of any
+/// recursive calls. Exclusive time leaves out the functions it called, or for
+/// a line, the statements nested in it. Allocations are those made directly;
+/// a call's environment and arguments count against its caller.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub calls: u64,
+    pub inclusive: Duration,
+    pub exclusive: Duration,
+    pub allocations: u64,
+}
+
+/// A function call or statement that hasn't finished.
+struct Open<K> {
+    key: K,
+    start: Instant,
+    children: Duration,
+    outermost: bool,
+}
+
+impl<K: PartialEq> Open<K> {
+    fn new(key: K, stack: &[Open<K>]) -> Self {
+        let outermost = !stack.iter().any(|o| o.key == key);
+        return Self { key, start: Instant::now(), children: Duration::ZERO, outermost };
+    }
+}
+
+/// Records statistics as it's called by the evaluator.
+pub struct Profiler {
+    functions: HashMap<String, Stats>,
+    lines: HashMap<usize, Stats>,
+    // exclusive microseconds for each stack of functions, joined with `;`
+    stacks: HashMap<String, u64>,
+    calls: Vec<Open<String>>,
+    statements: Vec<Open<usize>>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let mut profiler = Self {
+            functions: HashMap::new(),
+            lines: HashMap::new(),
+            stacks: HashMap::new(),
+            calls: Vec::new(),
+            statements: Vec::new(),
+        };
+        profiler.enter(String::from(MAIN));
+        return profiler;
+    }
+
+    fn enter(&mut self, key: String) {
+        self.functions.entry(key.clone()).or_default().calls += 1;
+        let open = Open::new(key, &self.calls);
+        self.calls.push(open);
+    }
+
+    fn exit(&mut self) {
+        let path: Vec<&str> = self.calls.iter().map(|o| o.key.as_str()).collect();
+        let path = path.join(";");
+        let Some(open) = self.calls.pop() else { return };
+        let elapsed = open.start.elapsed();
+        let exclusive = elapsed.saturating_sub(open.children);
+        let stats = self.functions.entry(open.key).or_default();
+        if open.outermost {
+            stats.inclusive += elapsed;
+        }
+        stats.exclusive += exclusive;
+        *self.stacks.entry(path).or_default() += exclusive.as_micros() as u64;
+        if let Some(parent) = self.calls.last_mut() {
+            parent.children += elapsed;
+        }
+    }
+
+    /// Stops recording, closing anything still open.
+    pub fn finish(mut self) -> Profile {
+        while !self.statements.is_empty() {
+            self.end_statement();
+        }
+        while !self.calls.is_empty() {
+            self.exit();
+        }
+        let mut functions: Vec<(String, Stats)> = self.functions.into_iter().collect();
+        functions.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(&b.0)));
+        let mut lines: Vec<(usize, Stats)> = self.lines.into_iter().collect();
+        lines.sort_by_key(|l| l.0);
+        let mut stacks: Vec<(String, u64)> = self.stacks.into_iter().collect();
+        stacks.sort();
+        return Profile { functions, lines, stacks };
+    }
+}
+
+impl Hook for Profiler {
+    fn statement(&mut self, s: &Statement, _env: &Arc<Mutex<Environment<'static>>>) -> Result<(), String> {
+        let line = s.position().line;
+        self.lines.entry(line).or_default().calls += 1;
+        let open = Open::new(line, &self.statements);
+        self.statements.push(open);
+        return Ok(());
+    }
+
+    fn end_statement(&mut self) {
+        let Some(open) = self.statements.pop() else { return };
+        let elapsed = open.start.elapsed();
+        let stats = self.lines.entry(open.key).or_default();
+        if open.outermost {
+            stats.inclusive += elapsed;
+        }
+        stats.exclusive += elapsed.saturating_sub(open.children);
+        if let Some(parent) = self.statements.last_mut() {
+            parent.children += elapsed;
+        }
+    }
+
+    fn allocate(&mut self, _bytes: usize) {
+        if let Some(open) = self.calls.last() {
+            self.functions.entry(open.key.clone()).or_default().allocations += 1;
+        }
+        if let Some(open) = self.statements.last() {
+            self.lines.entry(open.key).or_default().allocations += 1;
+        }
+    }
+
+    fn enter_call(&mut self, name: &str, f: &Function<'static>, _env: &Arc<Mutex<Environment<'static>>>) {
+        self.enter(label(name, f));
+    }
+
+    fn exit_call(&mut self) {
+        self.exit();
+    }
+}
+
+/// Names a function by how it was called and the line it was defined on,
+/// so that different functions called through the same name stay apart.
+fn label(name: &str, f: &Function<'static>) -> String {
+    let named = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    return format!("{}:{}", if named { name } else { "<anonymous>" }, f.body.position.line);
+}
+
+/// The statistics from one run, with functions from the most to the least
+/// exclusive time and lines in order.
+#[derive(Debug)]
+pub struct Profile {
+    pub functions: Vec<(String, Stats)>,
+    pub lines: Vec<(usize, Stats)>,
+    pub stacks: Vec<(String, u64)>,
+}
+
+fn millis(d: Duration) -> String {
+    return format!("{:.3}ms", d.as_secs_f64() * 1000.0);
+}
+
+impl Profile {
+    /// Tables of the functions and the lines of `source` that ran.
+    pub fn report(&self, source: &str) -> String {
+        let width = self.functions.iter().map(|f| f.0.len()).max().unwrap_or(0).max(8);
+        let mut report = format!("{:<width$}  {:>8}  {:>12}  {:>12}  {:>11}\n", "function", "calls", "inclusive", "exclusive", "allocations");
+        for (name, stats) in &self.functions {
+            report += &format!(
+                "{:<width$}  {:>8}  {:>12}  {:>12}  {:>11}\n",
+                name, stats.calls, millis(stats.inclusive), millis(stats.exclusive), stats.allocations,
+            );
+        }
+        report += &format!("\n{:>5}  {:>8}  {:>12}  {:>12}  {:>11}  source\n", "line", "hits", "inclusive", "exclusive", "allocations");
+        let lines: Vec<&str> = source.lines().collect();
+        for (line, stats) in &self.lines {
+            let text = lines.get(line - 1).map(|l| l.trim()).unwrap_or("");
+            report += &format!(
+                "{:>5}  {:>8}  {:>12}  {:>12}  {:>11}  {}\n",
+                line, stats.calls, millis(stats.inclusive), millis(stats.exclusive), stats.allocations, text,
+            );
+        }
+        return report;
+    }
+
+    /// Each stack of functions with the microseconds spent in its innermost
+    /// function, one per line, as flame graph tools read them.
+    pub fn collapsed(&self) -> String {
+        return self.stacks.iter().map(|(stack, micros)| format!("{} {}\n", stack, micros)).collect();
+    }
+}
+
+/// Evaluates `program` in `env` while profiling it.
+pub fn profile(program: Program, env: Arc<Mutex<Environment<'static>>>) -> (Object<'static>, Profile) {
+    let profiler = Arc::new(Mutex::new(Profiler::new()));
+    let (result, hook) = eval_with_hook(Node::Program(program), env, Limits::default(), Box::new(Shared(profiler.clone())));
+    drop(hook);
+    return (result, Shared::into_inner(profiler).finish());
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+const PROGRAM: &str = "let fib = fn(n) {
+    if (n < 2) { return n; }
+    fib(n - 1) + fib(n - 2)
+};
+let greet = fn(name) { \"hello \" + name };
+greet(\"you\");
+fib(5)";
+
+#[cfg(test)]
+fn profile_source(source: &str) -> (crate::object::Object<'static>, crate::profiler::Profile) {
+    use std::sync::{Arc, Mutex};
+    use crate::lexer::Lexer;
+    use crate::object::Environment;
+    use crate::parser::Parser;
+
+    let program = Parser::new(Lexer::new(source)).parse_program().unwrap();
+    return crate::profiler::profile(program, Arc::new(Mutex::new(Environment::new())));
+}
+
+#[test]
+fn profile_counts_calls_and_allocations() {
+    use crate::object::Object;
+
+    let (result, profile) = profile_source(PROGRAM);
+    assert_eq!(Object::Integer(5), result);
+
+    let calls: Vec<(&str, u64, u64)> = profile.functions.iter().map(|(name, stats)| (name.as_str(), stats.calls, stats.allocations)).collect();
+    // each call allocates an environment and binds its argument, which count
+    // against the function called
+    for expected in [("<main>", 1, 2), ("fib:1", 15, 30), ("greet:5", 1, 3)] {
+        assert!(calls.contains(&expected), "{:?} not in {:?}", expected, calls);
+    }
+    for (_, stats) in &profile.functions {
+        assert!(stats.inclusive >= stats.exclusive);
+    }
+
+    let hits: Vec<(usize, u64)> = profile.lines.iter().map(|(line, stats)| (*line, stats.calls)).collect();
+    // line 2 counts both the `if` and the `return` inside it
+    assert_eq!(vec![(1, 1), (2, 23), (3, 7), (5, 2), (6, 1), (7, 1)], hits);
+    let (_, main_line) = &profile.lines[5];
+    assert!(main_line.inclusive >= profile.functions.iter().find(|f| f.0 == "fib:1").unwrap().1.inclusive);
+}
+
+#[test]
+fn profile_charges_tail_calls_for_their_frames() {
+    let (_, profile) = profile_source("let f = fn(n) { if (n < 1) { 0 } else { f(n - 1) } };\nf(3);");
+    let calls: Vec<(&str, u64, u64)> = profile.functions.iter().map(|(name, stats)| (name.as_str(), stats.calls, stats.allocations)).collect();
+    assert!(calls.contains(&("f:1", 4, 8)), "{:?}", calls);
+    assert!(calls.contains(&("<main>", 1, 1)), "{:?}", calls);
+}
+
+#[test]
+fn profile_collapsed_stacks() {
+    let (_, profile) = profile_source(PROGRAM);
+    let stacks: Vec<&str> = profile.stacks.iter().map(|(stack, _)| stack.as_str()).collect();
+    assert_eq!(vec![
+        "<main>",
+        "<main>;fib:1",
+        "<main>;fib:1;fib:1",
+        "<main>;fib:1;fib:1;fib:1",
+        "<main>;fib:1;fib:1;fib:1;fib:1",
+        "<main>;fib:1;fib:1;fib:1;fib:1;fib:1",
+        "<main>;greet:5",
+    ], stacks);
+    for line in profile.collapsed().lines() {
+        let (_, micros) = line.rsplit_once(' ').unwrap();
+        assert!(micros.parse::<u64>().is_ok(), "{}", line);
+    }
+
+    // functions called without a name are told apart by where they are
+    let (_, profile) = profile_source("let twice = fn(f) { f(f(1)) };\ntwice(fn(x) { x * 2 })");
+    let names: Vec<&str> = profile.functions.iter().map(|(name, _)| name.as_str()).collect();
+    assert!(names.contains(&"f:2") && names.contains(&"twice:1"), "{:?}", names);
+    let (_, profile) = profile_source("fn(x) { x }(1)");
+    assert!(profile.functions.iter().any(|(name, _)| name == "<anonymous>:1"));
+}
@@ -0,0 +1,131 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use crate::dump;
+use crate::evaluator::snapshot;
+use crate::lexer::Lexer;
+use crate::object::{get_type, lock_env, Environment};
+use crate::parser::parse_source;
+use crate::token::Token;
+use super::transcript::{self, Recorder};
+
+const HELP: &str = "\
+:help           show this message
+:env            list the bindings in the current environment
+:type <expr>    evaluate <expr> and print the type of its value
+:ast <expr>     print the syntax tree for <expr>
+:tokens <expr>  print the tokens the lexer produces for <expr>
+:load <file>    evaluate a file in the current environment
+:reset          discard all bindings and imports
+:save <file>    save the bindings and imports to a snapshot file
+:restore <file> replace the environment with one restored from a snapshot
+:imports        list the imported namespaces
+:record <file>  record the session's inputs and outputs to a transcript
+:stop           stop recording
+:replay <file>  replay a transcript and report outputs that no longer match
+:quit           exit the REPL";
+
+pub enum Flow {
+    Continue,
+    Quit,
+    Record(Recorder),
+    StopRecording,
+}
+
+/// Runs a `:command` line, writing its output to `out`.
+pub fn execute(
+    line: &str,
+    env: &mut Arc<Mutex<Environment<'static>>>,
+    out: &mut dyn Write,
+) -> std::io::Result<Flow> {
+    let line = line.trim().trim_start_matches(':');
+    let (command, arg) = match line.split_once(char::is_whitespace) {
+        Some((command, arg)) => (command, arg.trim()),
+        None => (line, ""),
+    };
+
+    match command {
+        "help" => writeln!(out, "{}", HELP)?,
+        "env" => {
+            let env = lock_env(env);
+            for (name, value) in env.bindings() {
+                writeln!(out, "{}: {} = {}", name, get_type(value), value.inspect())?;
+            }
+        },
+        "type" => {
+            match super::eval_source(arg, env.clone()) {
+                Ok(obj) => writeln!(out, "{}", get_type(&obj))?,
+                Err(e) => writeln!(out, "{}", e)?,
+            }
+        },
+        "ast" => {
+            match parse_source(arg) {
+                Ok(program) => write!(out, "{}", dump::program_sexp(&program))?,
+                Err(e) => writeln!(out, "{}", e)?,
+            }
+        },
+        "tokens" => {
+            let mut l = Lexer::new(arg);
+            loop {
+                let tok = l.next_token();
+                writeln!(out, "{}:{} {:?}", l.token_position.line, l.token_position.column, tok)?;
+                if tok == Token::EOF {
+                    break;
+                }
+            }
+        },
+        "load" => {
+            match std::fs::read_to_string(arg) {
+                Ok(source) => {
+                    match super::eval_source(&source, env.clone()) {
+                        Ok(obj) => writeln!(out, "{}", obj.inspect())?,
+                        Err(e) => writeln!(out, "{}", e)?,
+                    }
+                },
+                Err(e) => writeln!(out, "error reading {}: {}", arg, e)?,
+            }
+        },
+        "reset" => *env = Arc::new(Mutex::new(Environment::new())),
+        "save" => {
+            let snap = snapshot::save(env);
+            match std::fs::write(arg, &snap.source) {
+                Ok(()) => {
+                    for name in snap.skipped.iter() {
+                        writeln!(out, "skipped {}: its value can't be saved", name)?;
+                    }
+                },
+                Err(e) => writeln!(out, "error writing {}: {}", arg, e)?,
+            }
+        },
+        "restore" => {
+            match std::fs::read_to_string(arg) {
+                Ok(source) => match snapshot::restore(&source) {
+                    Ok(restored) => *env = restored,
+                    Err(e) => writeln!(out, "{}", e)?,
+                },
+                Err(e) => writeln!(out, "error reading {}: {}", arg, e)?,
+            }
+        },
+        "imports" => {
+            let env = lock_env(env);
+            for namespace in env.imports.iter() {
+                writeln!(out, "{}", namespace)?;
+            }
+        },
+        "record" => {
+            match Recorder::create(arg) {
+                Ok(recorder) => {
+                    writeln!(out, "recording to {}", arg)?;
+                    return Ok(Flow::Record(recorder));
+                },
+                Err(e) => writeln!(out, "error creating {}: {}", arg, e)?,
+            }
+        },
+        "stop" => return Ok(Flow::StopRecording),
+        "replay" => {
+            transcript::replay_file(arg, out)?;
+        },
+        "quit" | "q" => return Ok(Flow::Quit),
+        _ => writeln!(out, "unknown command :{}, see :help", command)?,
+    }
+    return Ok(Flow::Continue);
+}
@@ -0,0 +1,467 @@
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use super::highlight::highlight;
+use super::terminal::RawMode;
+
+const HISTORY_FILE: &str = ".waiig_rs_history";
+const MAX_HISTORY: usize = 1000;
+
+#[derive(Debug, PartialEq)]
+pub enum ReadLine {
+    Line(String),
+    /// Ctrl-C: the line was abandoned.
+    Interrupted,
+    /// Ctrl-D on an empty line, or the input ran out.
+    Eof,
+}
+
+#[derive(Debug, PartialEq)]
+enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    CtrlC,
+    CtrlD,
+    CtrlG,
+    CtrlK,
+    CtrlL,
+    CtrlR,
+    CtrlU,
+    CtrlW,
+    Eof,
+    Unknown,
+}
+
+fn read_byte(input: &mut impl Read) -> std::io::Result<Option<u8>> {
+    let mut buf = [0u8; 1];
+    return match input.read(&mut buf)? {
+        0 => Ok(None),
+        _ => Ok(Some(buf[0])),
+    };
+}
+
+fn read_escape(input: &mut impl Read) -> std::io::Result<Key> {
+    return Ok(match read_byte(input)? {
+        Some(b'[') => match read_byte(input)? {
+            Some(b'A') => Key::Up,
+            Some(b'B') => Key::Down,
+            Some(b'C') => Key::Right,
+            Some(b'D') => Key::Left,
+            Some(b'H') => Key::Home,
+            Some(b'F') => Key::End,
+            Some(d) if d.is_ascii_digit() => {
+                let mut code = String::from(d as char);
+                loop {
+                    match read_byte(input)? {
+                        Some(b'~') | None => break,
+                        Some(b) => code.push(b as char),
+                    }
+                }
+                match code.as_str() {
+                    "1" | "7" => Key::Home,
+                    "4" | "8" => Key::End,
+                    "3" => Key::Delete,
+                    _ => Key::Unknown,
+                }
+            },
+            _ => Key::Unknown,
+        },
+        Some(b'O') => match read_byte(input)? {
+            Some(b'H') => Key::Home,
+            Some(b'F') => Key::End,
+            _ => Key::Unknown,
+        },
+        _ => Key::Unknown,
+    });
+}
+
+fn read_key(input: &mut impl Read) -> std::io::Result<Key> {
+    let b = match read_byte(input)? {
+        Some(b) => b,
+        None => return Ok(Key::Eof),
+    };
+    return Ok(match b {
+        1 => Key::Home,
+        2 => Key::Left,
+        3 => Key::CtrlC,
+        4 => Key::CtrlD,
+        5 => Key::End,
+        6 => Key::Right,
+        7 => Key::CtrlG,
+        8 | 127 => Key::Backspace,
+        9 => Key::Tab,
+        10 | 13 => Key::Enter,
+        11 => Key::CtrlK,
+        12 => Key::CtrlL,
+        14 => Key::Down,
+        16 => Key::Up,
+        18 => Key::CtrlR,
+        21 => Key::CtrlU,
+        23 => Key::CtrlW,
+        27 => read_escape(input)?,
+        b if b < 32 => Key::Unknown,
+        b if b < 128 => Key::Char(b as char),
+        b => {
+            // the rest of a UTF-8 sequence
+            let len = if b >= 0xf0 { 4 } else if b >= 0xe0 { 3 } else { 2 };
+            let mut bytes = vec![b];
+            for _ in 1..len {
+                match read_byte(input)? {
+                    Some(b) => bytes.push(b),
+                    None => break,
+                }
+            }
+            match String::from_utf8(bytes) {
+                Ok(s) => s.chars().next().map_or(Key::Unknown, Key::Char),
+                Err(_) => Key::Unknown,
+            }
+        },
+    });
+}
+
+fn is_word_char(c: char) -> bool {
+    return c.is_alphanumeric() || c == '_' || c == '.';
+}
+
+fn common_prefix(words: &[&String]) -> String {
+    let mut prefix = words[0].to_string();
+    for word in words.iter().skip(1) {
+        while !word.starts_with(prefix.as_str()) {
+            prefix.pop();
+        }
+    }
+    return prefix;
+}
+
+/// A line editor with history, reverse search and tab completion.
+pub struct Editor {
+    history: Vec<String>,
+    path: Option<PathBuf>,
+    buffer: Vec<char>,
+    cursor: usize,
+    /// Whether input is syntax highlighted as it is typed.
+    pub color: bool,
+}
+
+impl Editor {
+    /// Creates an editor, loading history from `path` if it exists. New
+    /// entries are appended to the same file.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let mut history: Vec<String> = Vec::new();
+        if let Some(path) = &path {
+            if let Ok(contents) = std::fs::read_to_string(path) {
+                history = contents.lines().map(String::from).collect();
+            }
+            if history.len() > MAX_HISTORY {
+                history.drain(..history.len() - MAX_HISTORY);
+                let _ = std::fs::write(path, history.join("\n") + "\n");
+            }
+        }
+        return Self { history, path, buffer: Vec::new(), cursor: 0, color: false };
+    }
+
+    /// `~/.waiig_rs_history`, if the home directory is known.
+    pub fn default_history_path() -> Option<PathBuf> {
+        return std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().map(String::as_str) == Some(line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if let Some(path) = &self.path {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    /// Reads a line from the terminal in raw mode. `words` are the candidates
+    /// for tab completion.
+    pub fn read_line(&mut self, prompt: &str, words: &[String]) -> std::io::Result<ReadLine> {
+        let _raw = RawMode::enable()?;
+        return self.edit(&mut std::io::stdin(), &mut std::io::stdout(), prompt, words);
+    }
+
+    fn refresh(&self, out: &mut impl Write, prompt: &str) -> std::io::Result<()> {
+        let mut line: String = self.buffer.iter().collect();
+        if self.color {
+            line = highlight(&line);
+        }
+        write!(out, "\r{}{}\x1b[K", prompt, line)?;
+        if self.cursor < self.buffer.len() {
+            write!(out, "\x1b[{}D", self.buffer.len() - self.cursor)?;
+        }
+        return out.flush();
+    }
+
+    fn set_buffer(&mut self, line: &str) {
+        self.buffer = line.chars().collect();
+        self.cursor = self.buffer.len();
+    }
+
+    fn complete(&mut self, out: &mut impl Write, words: &[String]) -> std::io::Result<()> {
+        let mut start = self.cursor;
+        while start > 0 && is_word_char(self.buffer[start - 1]) {
+            start -= 1;
+        }
+        let prefix: String = self.buffer[start..self.cursor].iter().collect();
+        if prefix.is_empty() {
+            return Ok(());
+        }
+
+        let mut candidates: Vec<&String> = words.iter().filter(|w| w.starts_with(prefix.as_str())).collect();
+        candidates.sort();
+        candidates.dedup();
+        if candidates.is_empty() {
+            return Ok(());
+        }
+        let completion = common_prefix(&candidates);
+        if completion.len() > prefix.len() {
+            for c in completion[prefix.len()..].chars() {
+                self.buffer.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+        } else if candidates.len() > 1 {
+            let list: Vec<&str> = candidates.iter().map(|c| c.as_str()).collect();
+            write!(out, "\n{}\n", list.join("  "))?;
+        }
+        return Ok(());
+    }
+
+    /// Ctrl-R: searches history backwards for lines containing the typed
+    /// text. Returns the line to submit if the search ended with Enter.
+    fn reverse_search(&mut self, input: &mut impl Read, out: &mut impl Write) -> std::io::Result<Option<String>> {
+        let original: String = self.buffer.iter().collect();
+        let len = self.history.len();
+        let mut query = String::new();
+        let mut found: Option<usize> = None;
+        let mut failed = false;
+        loop {
+            let shown = found.map_or("", |i| self.history[i].as_str());
+            let status = if failed { "failed " } else { "" };
+            write!(out, "\r({}reverse-i-search)`{}': {}\x1b[K", status, query, shown)?;
+            out.flush()?;
+
+            // where to search backwards from, exclusive
+            let from = match read_key(input)? {
+                Key::Char(c) => {
+                    query.push(c);
+                    found.map_or(len, |i| i + 1)
+                },
+                Key::Backspace => {
+                    query.pop();
+                    len
+                },
+                Key::CtrlR => found.unwrap_or(len),
+                Key::CtrlG | Key::CtrlC => {
+                    self.set_buffer(&original);
+                    return Ok(None);
+                },
+                Key::Enter => return Ok(Some(found.map_or(original, |i| self.history[i].clone()))),
+                Key::Eof => return Ok(None),
+                _ => {
+                    if let Some(i) = found {
+                        let line = self.history[i].clone();
+                        self.set_buffer(&line);
+                    }
+                    return Ok(None);
+                },
+            };
+
+            let next = self.history[..from].iter().rposition(|h| h.contains(query.as_str()));
+            failed = next.is_none();
+            if next.is_some() || query.is_empty() {
+                found = next;
+            }
+        }
+    }
+
+    /// The editing loop, reading keys from `input` and drawing on `out`.
+    fn edit(&mut self, input: &mut impl Read, out: &mut impl Write, prompt: &str, words: &[String]) -> std::io::Result<ReadLine> {
+        self.buffer.clear();
+        self.cursor = 0;
+        let mut history_index = self.history.len();
+        let mut pending = String::new();
+        self.refresh(out, prompt)?;
+
+        loop {
+            match read_key(input)? {
+                Key::Char(c) => {
+                    self.buffer.insert(self.cursor, c);
+                    self.cursor += 1;
+                },
+                Key::Enter => {
+                    writeln!(out)?;
+                    return Ok(ReadLine::Line(self.buffer.iter().collect()));
+                },
+                Key::Tab => self.complete(out, words)?,
+                Key::Backspace => {
+                    if self.cursor > 0 {
+                        self.cursor -= 1;
+                        self.buffer.remove(self.cursor);
+                    }
+                },
+                Key::Delete => {
+                    if self.cursor < self.buffer.len() {
+                        self.buffer.remove(self.cursor);
+                    }
+                },
+                Key::Left => self.cursor = self.cursor.saturating_sub(1),
+                Key::Right => self.cursor = usize::min(self.cursor + 1, self.buffer.len()),
+                Key::Home => self.cursor = 0,
+                Key::End => self.cursor = self.buffer.len(),
+                Key::Up => {
+                    if history_index > 0 {
+                        if history_index == self.history.len() {
+                            pending = self.buffer.iter().collect();
+                        }
+                        history_index -= 1;
+                        let line = self.history[history_index].clone();
+                        self.set_buffer(&line);
+                    }
+                },
+                Key::Down => {
+                    if history_index < self.history.len() {
+                        history_index += 1;
+                        let line = match self.history.get(history_index) {
+                            Some(line) => line.clone(),
+                            None => pending.clone(),
+                        };
+                        self.set_buffer(&line);
+                    }
+                },
+                Key::CtrlC => {
+                    writeln!(out, "^C")?;
+                    return Ok(ReadLine::Interrupted);
+                },
+                Key::CtrlD => {
+                    if self.buffer.is_empty() {
+                        writeln!(out)?;
+                        return Ok(ReadLine::Eof);
+                    }
+                    if self.cursor < self.buffer.len() {
+                        self.buffer.remove(self.cursor);
+                    }
+                },
+                Key::CtrlK => self.buffer.truncate(self.cursor),
+                Key::CtrlU => {
+                    self.buffer.drain(..self.cursor);
+                    self.cursor = 0;
+                },
+                Key::CtrlW => {
+                    let end = self.cursor;
+                    while self.cursor > 0 && self.buffer[self.cursor - 1].is_whitespace() {
+                        self.cursor -= 1;
+                    }
+                    while self.cursor > 0 && !self.buffer[self.cursor - 1].is_whitespace() {
+                        self.cursor -= 1;
+                    }
+                    self.buffer.drain(self.cursor..end);
+                },
+                Key::CtrlL => write!(out, "\x1b[H\x1b[2J")?,
+                Key::CtrlR => {
+                    if let Some(line) = self.reverse_search(input, out)? {
+                        writeln!(out)?;
+                        return Ok(ReadLine::Line(line));
+                    }
+                },
+                Key::Eof => {
+                    if self.buffer.is_empty() {
+                        return Ok(ReadLine::Eof);
+                    }
+                    return Ok(ReadLine::Line(self.buffer.iter().collect()));
+                },
+                Key::CtrlG | Key::Unknown => (),
+            }
+            self.refresh(out, prompt)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Editor, ReadLine};
+
+    fn edit(editor: &mut Editor, keys: &str, words: &[String]) -> ReadLine {
+        let mut out: Vec<u8> = Vec::new();
+        return editor.edit(&mut keys.as_bytes(), &mut out, "> ", words).unwrap();
+    }
+
+    #[test]
+    fn cursor_movement() {
+        let mut editor = Editor::new(None);
+        // type "lt x", go home, insert "e", then end and append ";"
+        let keys = "lt x\x1b[H\x1b[Ce\x1b[F;\r";
+        assert_eq!(ReadLine::Line(String::from("let x;")), edit(&mut editor, keys, &[]));
+        // backspace, delete and kill to the start
+        assert_eq!(ReadLine::Line(String::from("bd")), edit(&mut editor, "abc\x08\x1b[D\x1b[3~d\x15bd\x1b[F\x1b[3~\r", &[]));
+        assert_eq!(ReadLine::Line(String::from("one ")), edit(&mut editor, "one two\x17\r", &[]));
+    }
+
+    #[test]
+    fn control_keys() {
+        let mut editor = Editor::new(None);
+        assert_eq!(ReadLine::Interrupted, edit(&mut editor, "abc\x03", &[]));
+        assert_eq!(ReadLine::Eof, edit(&mut editor, "\x04", &[]));
+        assert_eq!(ReadLine::Eof, edit(&mut editor, "", &[]));
+    }
+
+    #[test]
+    fn history_navigation() {
+        let mut editor = Editor::new(None);
+        editor.add_history("first");
+        editor.add_history("second");
+        editor.add_history("second");
+        assert_eq!(ReadLine::Line(String::from("second")), edit(&mut editor, "\x1b[A\r", &[]));
+        assert_eq!(ReadLine::Line(String::from("first")), edit(&mut editor, "\x1b[A\x1b[A\x1b[A\r", &[]));
+        assert_eq!(ReadLine::Line(String::from("draft")), edit(&mut editor, "draft\x1b[A\x1b[B\r", &[]));
+    }
+
+    #[test]
+    fn reverse_search() {
+        let mut editor = Editor::new(None);
+        editor.add_history("let add = fn(x, y) { x + y };");
+        editor.add_history("add(1, 2)");
+        editor.add_history("let sub = fn(x, y) { x - y };");
+        assert_eq!(ReadLine::Line(String::from("add(1, 2)")), edit(&mut editor, "\x12add\r", &[]));
+        assert_eq!(ReadLine::Line(String::from("let add = fn(x, y) { x + y };")), edit(&mut editor, "\x12add\x12\r", &[]));
+        // leaving the search with an arrow key keeps the match for editing
+        assert_eq!(ReadLine::Line(String::from("let sub = fn(x, y) { x - y }")), edit(&mut editor, "\x12sub\x1b[C\x08\r", &[]));
+        assert_eq!(ReadLine::Line(String::from("x")), edit(&mut editor, "x\x12nothing\x07\r", &[]));
+    }
+
+    #[test]
+    fn tab_completion() {
+        let mut editor = Editor::new(None);
+        let words = vec![String::from("counter"), String::from("count_all"), String::from("std.string")];
+        assert_eq!(ReadLine::Line(String::from("counter(")), edit(&mut editor, "counte\t(\r", &words));
+        assert_eq!(ReadLine::Line(String::from("count")), edit(&mut editor, "co\t\r", &words));
+        assert_eq!(ReadLine::Line(String::from("import \"std.string\"")), edit(&mut editor, "import \"std\t\"\r", &words));
+        assert_eq!(ReadLine::Line(String::from("zz")), edit(&mut editor, "zz\t\r", &words));
+    }
+
+    #[test]
+    fn persistent_history() {
+        let path = std::env::temp_dir().join(format!("waiig_rs_history_test_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut editor = Editor::new(Some(path.clone()));
+        editor.add_history("let x = 1;");
+        editor.add_history("x + 1");
+
+        let mut editor = Editor::new(Some(path.clone()));
+        assert_eq!(ReadLine::Line(String::from("let x = 1;")), edit(&mut editor, "\x1b[A\x1b[A\r", &[]));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+use crate::lexer::Lexer;
+use crate::token::Token;
+
+pub const RESET: &str = "\x1b[0m";
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const YELLOW: &str = "\x1b[33m";
+pub const MAGENTA: &str = "\x1b[35m";
+pub const CYAN: &str = "\x1b[36m";
+pub const GREY: &str = "\x1b[90m";
+
+fn token_color(t: &Token) -> Option<&'static str> {
+    return match t {
+        Token::IDENT(_) | Token::EOF => None,
+        Token::INT(_) => Some(YELLOW),
+        Token::STRING(_) => Some(GREEN),
+        Token::FUNCTION | Token::LET | Token::TRUE | Token::FALSE | Token::IF | Token::ELSE
+            | Token::RETURN | Token::IMPORT | Token::TRY | Token::CATCH | Token::FINALLY
+            | Token::THROW => Some(MAGENTA),
+        Token::ILLEGAL => Some(RED),
+        Token::COMMA | Token::DOT | Token::SEMICOLON | Token::LPAREN | Token::RPAREN
+            | Token::LSQUIRLY | Token::RSQUIRLY | Token::LBRACKET | Token::RBRACKET => None,
+        _ => Some(CYAN),
+    };
+}
+
+/// Wraps `s` in `color` if colour is enabled.
+pub fn paint(s: &str, color: &str, enabled: bool) -> String {
+    if !enabled {
+        return s.to_string();
+    }
+    return format!("{}{}{}", color, s, RESET);
+}
+
+/// Colours a single line of input by token kind. Each token's colour runs
+/// until the next token starts.
+pub fn highlight(line: &str) -> String {
+    // the lexer's columns count characters, so map them to byte offsets
+    let offsets: Vec<usize> = line.char_indices().map(|(i, _)| i).collect();
+    let offset = |column: usize| offsets.get(column - 1).copied().unwrap_or(line.len());
+    let mut l = Lexer::new(line);
+    let mut tokens: Vec<(usize, Option<&str>)> = Vec::new();
+    loop {
+        let t = l.next_token();
+        if t == Token::EOF {
+            break;
+        }
+        // a string still being typed is coloured as one
+        let t = if t == Token::ILLEGAL && l.unterminated_string { Token::STRING(String::new()) } else { t };
+        tokens.push((offset(l.token_position.column), token_color(&t)));
+    }
+    for comment in l.comments.iter() {
+        tokens.push((offset(comment.position.column), Some(GREY)));
+    }
+    tokens.sort_by_key(|(start, _)| *start);
+
+    let mut out = String::new();
+    let first = tokens.first().map(|(start, _)| *start).unwrap_or(line.len());
+    out += &line[..first];
+    for i in 0..tokens.len() {
+        let (start, color) = &tokens[i];
+        let end = tokens.get(i + 1).map(|(next, _)| *next).unwrap_or(line.len());
+        let segment = &line[*start..end];
+        match color {
+            Some(color) => out += &paint(segment, color, true),
+            None => out += segment,
+        }
+    }
+    return out;
+}
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use crate::evaluator::CancelHandle;
+
+// Ctrl-C cancels the evaluation in progress; at the prompt it exits as it
+// always has.
+static HANDLE: OnceLock<CancelHandle> = OnceLock::new();
+static EVALUATING: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+mod sys {
+    pub const SIGINT: i32 = 2;
+
+    extern "C" {
+        pub fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        pub fn _exit(status: i32) -> !;
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_sigint(_: i32) {
+    if EVALUATING.load(Ordering::SeqCst) {
+        if let Some(handle) = HANDLE.get() {
+            handle.cancel();
+            return;
+        }
+    }
+    unsafe { sys::_exit(130) };
+}
+
+/// Installs the SIGINT handler.
+pub fn install() {
+    HANDLE.get_or_init(CancelHandle::new);
+    #[cfg(unix)]
+    unsafe {
+        sys::signal(sys::SIGINT, on_sigint);
+    }
+}
+
+/// The handle Ctrl-C cancels, once the handler has been installed.
+pub fn handle() -> Option<CancelHandle> {
+    return HANDLE.get().cloned();
+}
+
+/// Marks the start of an evaluation that Ctrl-C should interrupt.
+pub fn begin() {
+    if let Some(handle) = HANDLE.get() {
+        handle.reset();
+    }
+    EVALUATING.store(true, Ordering::SeqCst);
+}
+
+pub fn end() {
+    EVALUATING.store(false, Ordering::SeqCst);
+}
@@ -1,22 +1,151 @@
 use std::sync::{Arc, Mutex};
 use std::io::{stdout, stdin, Write};
 use crate::lexer::Lexer;
-use crate::parser::Parser;
-use crate::evaluator::eval;
+use crate::parser::parse_source;
+use crate::evaluator::{eval_with_limits, Limits, NAMESPACES};
+use crate::object::{Environment, Object};
+use crate::token::{self, Token};
+use editor::{Editor, ReadLine};
+
+mod commands;
+mod editor;
+mod highlight;
+mod interrupt;
+pub mod pretty;
+mod terminal;
+mod transcript;
+#[cfg(test)]
+mod tests;
+
+/// Parses and evaluates `source` in `env`. Ctrl-C interrupts the evaluation.
+/// Nothing is evaluated if `source` fails to parse.
+fn eval_source(source: &str, env: Arc<Mutex<Environment<'static>>>) -> Result<Object<'static>, String> {
+    let program = parse_source(source)?;
+    let limits = Limits { cancel: interrupt::handle(), ..Limits::default() };
+    interrupt::begin();
+    let obj = eval_with_limits(crate::ast::Node::Program(program), env, limits);
+    interrupt::end();
+    return Ok(obj);
+}
+
+/// Whether `source` can be evaluated as is, or is still missing closing
+/// brackets or the end of a string literal.
+fn is_complete(source: &str) -> bool {
+    let mut l = Lexer::new(source);
+    let mut depth: i32 = 0;
+    loop {
+        match l.next_token() {
+            Token::LPAREN | Token::LSQUIRLY | Token::LBRACKET => depth += 1,
+            Token::RPAREN | Token::RSQUIRLY | Token::RBRACKET => depth -= 1,
+            Token::EOF => break,
+            _ => (),
+        }
+    }
+    // too many closing brackets will never balance, so let the parser report it
+    return !l.unterminated_string && depth <= 0;
+}
+
+/// Candidates for tab completion: bound names, keywords and namespaces.
+fn completion_words(env: &Arc<Mutex<Environment<'static>>>) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    if let Ok(env) = env.lock() {
+        env.bindings().iter().for_each(|(name, _)| words.push(name.to_string()));
+    }
+    token::keywords().iter().for_each(|k| words.push(k.to_string()));
+    NAMESPACES.iter().for_each(|n| words.push(n.to_string()));
+    return words;
+}
+
+/// Prints an evaluation result, or parse errors to stderr.
+fn print_result(result: Result<Object<'static>, String>, color: bool) {
+    match result {
+        Ok(obj) => println!("{}", pretty::format_result(&obj, color)),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+/// Replays the transcript at `path`, printing any outputs that no longer
+/// match. Returns whether they all did.
+pub fn replay(path: &str) -> std::io::Result<bool> {
+    return transcript::replay_file(path, &mut stdout());
+}
 
 pub fn run() -> std::io::Result<()>{
-    let env = Arc::new(Mutex::new(crate::object::Environment::new()));
+    let mut env = Arc::new(Mutex::new(Environment::new()));
+    interrupt::install();
+    let color = terminal::stdout_is_tty();
+    let mut editor = if terminal::stdin_is_tty() {
+        let mut editor = Editor::new(Editor::default_history_path());
+        editor.color = color;
+        Some(editor)
+    } else {
+        None
+    };
+    let mut recorder: Option<transcript::Recorder> = None;
+    let mut input = String::new();
     loop {
-        print!("> ");
-        let _ = stdout().flush();
-        let mut input = String::new();
-        stdin().read_line(&mut input)?;
+        let prompt = if input.is_empty() { "> " } else { "... " };
+        let mut line = String::new();
+        match &mut editor {
+            Some(editor) => match editor.read_line(prompt, &completion_words(&env))? {
+                ReadLine::Line(l) => {
+                    editor.add_history(&l);
+                    line = l + "\n";
+                },
+                // Ctrl-C abandons the line along with any pending input
+                ReadLine::Interrupted => {
+                    input.clear();
+                    continue;
+                },
+                ReadLine::Eof => return Ok(()),
+            },
+            // piped input gets no prompts and ends at EOF
+            None => {
+                if stdin().read_line(&mut line)? == 0 {
+                    if !input.trim().is_empty() {
+                        let result = eval_source(&input, env.clone());
+                        if let Some(recorder) = &mut recorder {
+                            recorder.record(&input, &transcript::output(&result))?;
+                        }
+                        print_result(result, color);
+                    }
+                    return Ok(());
+                }
+            },
+        }
 
-        let l = Lexer::new(input.as_str());
-        let mut p = Parser::new(l);
-        let program = p.parse_program().expect("error parsing program");
-        let obj = eval(crate::ast::Node::Program(program), env.clone());
+        if input.is_empty() && line.trim_start().starts_with(':') {
+            let mut out: Vec<u8> = Vec::new();
+            let flow = commands::execute(&line, &mut env, &mut out)?;
+            stdout().write_all(&out)?;
+            match flow {
+                commands::Flow::Continue => {
+                    if let Some(recorder) = &mut recorder {
+                        if !line.trim_start().starts_with(":replay") {
+                            recorder.record(&line, &String::from_utf8_lossy(&out))?;
+                        }
+                    }
+                },
+                commands::Flow::Quit => return Ok(()),
+                commands::Flow::Record(r) => recorder = Some(r),
+                commands::Flow::StopRecording => recorder = None,
+            }
+            continue;
+        }
+        if !input.is_empty() && line.trim() == ":cancel" {
+            input.clear();
+            continue;
+        }
 
-        println!("{}", obj.inspect());
+        input += &line;
+        if !is_complete(&input) {
+            continue;
+        }
+        let result = eval_source(&input, env.clone());
+        if let Some(recorder) = &mut recorder {
+            recorder.record(&input, &transcript::output(&result))?;
+        }
+        print_result(result, color);
+        input.clear();
     }
 }
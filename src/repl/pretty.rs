@@ -0,0 +1,21 @@
+use crate::object::Object;
+use super::highlight::{paint, CYAN, GREEN, MAGENTA, RED, YELLOW};
+
+/// Formats an evaluation result for display: strings are quoted, functions
+/// show their parameters and errors are red. `color` turns on ANSI colours.
+pub fn format_result(obj: &Object, color: bool) -> String {
+    return match obj {
+        Object::Integer(i) => paint(&i.to_string(), YELLOW, color),
+        Object::Boolean(b) => paint(&b.to_string(), MAGENTA, color),
+        Object::String(s) => paint(&format!("\"{}\"", s), GREEN, color),
+        Object::ReturnValue(r) => format_result(r, color),
+        Object::Error(e) | Object::Exception(e) => paint(&e.inspect(), RED, color),
+        Object::Null => paint("null", MAGENTA, color),
+        Object::Function(f) => {
+            let params: Vec<String> = f.parameters.iter().map(|p| p.value.clone()).collect();
+            paint(&format!("fn({})", params.join(", ")), CYAN, color)
+        },
+        Object::Builtin(_) => paint("builtin fn", CYAN, color),
+        Object::TailCall(..) => obj.inspect(),
+    };
+}
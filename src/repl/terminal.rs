@@ -0,0 +1,95 @@
+// Just enough termios to switch the terminal in and out of raw mode. Only
+// Linux is supported; elsewhere the REPL falls back to reading whole lines.
+
+#[cfg(target_os = "linux")]
+mod sys {
+    pub const STDIN: i32 = 0;
+    pub const STDOUT: i32 = 1;
+    pub const TCSANOW: i32 = 0;
+
+    pub const ISIG: u32 = 0o1;
+    pub const ICANON: u32 = 0o2;
+    pub const ECHO: u32 = 0o10;
+    pub const IEXTEN: u32 = 0o100000;
+    pub const ICRNL: u32 = 0o400;
+    pub const IXON: u32 = 0o2000;
+    pub const VTIME: usize = 5;
+    pub const VMIN: usize = 6;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct Termios {
+        pub c_iflag: u32,
+        pub c_oflag: u32,
+        pub c_cflag: u32,
+        pub c_lflag: u32,
+        pub c_line: u8,
+        pub c_cc: [u8; 32],
+        pub c_ispeed: u32,
+        pub c_ospeed: u32,
+    }
+
+    extern "C" {
+        pub fn isatty(fd: i32) -> i32;
+        pub fn tcgetattr(fd: i32, termios: *mut Termios) -> i32;
+        pub fn tcsetattr(fd: i32, action: i32, termios: *const Termios) -> i32;
+    }
+}
+
+/// Whether stdin is an interactive terminal that raw mode can be used on.
+pub fn stdin_is_tty() -> bool {
+    #[cfg(target_os = "linux")]
+    return unsafe { sys::isatty(sys::STDIN) == 1 };
+    #[cfg(not(target_os = "linux"))]
+    return false;
+}
+
+/// Whether stdout is a terminal, and so can be written colour codes.
+pub fn stdout_is_tty() -> bool {
+    #[cfg(target_os = "linux")]
+    return unsafe { sys::isatty(sys::STDOUT) == 1 };
+    #[cfg(not(target_os = "linux"))]
+    return false;
+}
+
+/// Puts the terminal in raw mode until dropped. Output processing is left on
+/// so `\n` still starts a new line.
+pub struct RawMode {
+    #[cfg(target_os = "linux")]
+    original: sys::Termios,
+}
+
+impl RawMode {
+    #[cfg(target_os = "linux")]
+    pub fn enable() -> std::io::Result<Self> {
+        let mut original = std::mem::MaybeUninit::<sys::Termios>::uninit();
+        if unsafe { sys::tcgetattr(sys::STDIN, original.as_mut_ptr()) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let original = unsafe { original.assume_init() };
+
+        let mut raw = original;
+        raw.c_iflag &= !(sys::ICRNL | sys::IXON);
+        raw.c_lflag &= !(sys::ECHO | sys::ICANON | sys::ISIG | sys::IEXTEN);
+        raw.c_cc[sys::VMIN] = 1;
+        raw.c_cc[sys::VTIME] = 0;
+        if unsafe { sys::tcsetattr(sys::STDIN, sys::TCSANOW, &raw) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        return Ok(Self { original });
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn enable() -> std::io::Result<Self> {
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "raw mode is only supported on Linux"));
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        unsafe {
+            sys::tcsetattr(sys::STDIN, sys::TCSANOW, &self.original);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex};
+use crate::object::Environment;
+use crate::repl::commands::{execute, Flow};
+
+fn run_command(line: &str, env: &mut Arc<Mutex<Environment<'static>>>) -> String {
+    let mut out: Vec<u8> = Vec::new();
+    execute(line, env, &mut out).unwrap();
+    return String::from_utf8(out).unwrap();
+}
+
+#[test]
+fn env_command() {
+    let mut env = Arc::new(Mutex::new(Environment::new()));
+    crate::repl::eval_source("let b = \"two\"; let a = 1;", env.clone()).unwrap();
+    assert_eq!("a: INTEGER = 1\nb: STRING = two\n", run_command(":env", &mut env));
+}
+
+#[test]
+fn type_command() {
+    let mut env = Arc::new(Mutex::new(Environment::new()));
+    assert_eq!("BOOLEAN\n", run_command(":type 1 < 2", &mut env));
+    assert_eq!("FUNCTION\n", run_command(":type fn(x) { x }", &mut env));
+}
+
+#[test]
+fn tokens_command() {
+    let mut env = Arc::new(Mutex::new(Environment::new()));
+    assert_eq!("1:1 INT(\"1\")\n1:3 PLUS\n1:5 INT(\"2\")\n1:6 EOF\n", run_command(":tokens 1 + 2", &mut env));
+}
+
+#[test]
+fn ast_command() {
+    let mut env = Arc::new(Mutex::new(Environment::new()));
+    assert_eq!("(program\n  (expr 1:1-1:6 (infix 1:1-1:6 + (int 1:1-1:2 1) (int 1:5-1:6 2))))\n", run_command(":ast 1 + 2", &mut env));
+}
+
+#[test]
+fn reset_and_imports_commands() {
+    let mut env = Arc::new(Mutex::new(Environment::new()));
+    crate::repl::eval_source("import \"std.string\"; let x = 1;", env.clone()).unwrap();
+    assert_eq!("std.string\n", run_command(":imports", &mut env));
+    run_command(":reset", &mut env);
+    assert_eq!("", run_command(":imports", &mut env));
+    assert_eq!("", run_command(":env", &mut env));
+}
+
+#[test]
+fn quit_and_unknown_commands() {
+    let mut env = Arc::new(Mutex::new(Environment::new()));
+    let mut out: Vec<u8> = Vec::new();
+    assert!(matches!(execute(":quit", &mut env, &mut out).unwrap(), Flow::Quit));
+    assert_eq!("unknown command :nope, see :help\n", run_command(":nope", &mut env));
+}
+
+#[test]
+fn is_complete() {
+    use crate::repl::is_complete;
+
+    assert!(is_complete("let x = 5;"));
+    assert!(is_complete("let f = fn(x) {\n  x * 2\n};"));
+    assert!(!is_complete("let f = fn(x) {"));
+    assert!(!is_complete("let f = fn(x) {\n  if (x > 1) {"));
+    assert!(!is_complete("add(1,"));
+    assert!(!is_complete("[1, 2"));
+    assert!(!is_complete("let s = \"multi\nline"));
+    assert!(is_complete("let s = \"{ not a bracket\";"));
+    assert!(is_complete("}"));
+}
+
+#[test]
+fn parse_errors() {
+    use crate::repl::eval_source;
+
+    let env = Arc::new(Mutex::new(Environment::new()));
+    let err = eval_source("let x = 1;\nlet y = ;", env.clone()).unwrap_err();
+    assert_eq!("let y = ;\n        ^ no prefix parse function for ; found", err);
+    // nothing is evaluated when the input doesn't parse
+    assert!(env.lock().unwrap().bindings().is_empty());
+
+    let err = eval_source("1 +\n", env.clone()).unwrap_err();
+    assert_eq!("1 +\n   ^ unexpected end of input", err);
+}
+
+#[test]
+fn highlight() {
+    use crate::repl::highlight::highlight;
+
+    assert_eq!(
+        "\x1b[35mlet \x1b[0mx \x1b[36m= \x1b[0m\x1b[33m5\x1b[0m;",
+        highlight("let x = 5;"),
+    );
+    assert_eq!("  \x1b[32m\"abc\x1b[0m", highlight("  \"abc"));
+    assert_eq!("", highlight(""));
+    assert_eq!("\x1b[33m1 \x1b[0m\x1b[90m// one\x1b[0m", highlight("1 // one"));
+    // columns past a multi-byte character still line up with the text
+    assert_eq!(
+        "\x1b[32m\"é\" \x1b[0m\x1b[36m+ \x1b[0m\x1b[33m1 \x1b[0m\x1b[90m// é\x1b[0m",
+        highlight("\"é\" + 1 // é"),
+    );
+}
+
+#[test]
+fn format_result() {
+    use crate::repl::pretty::format_result;
+    use crate::repl::eval_source;
+
+    let env = Arc::new(Mutex::new(Environment::new()));
+    let result = |source: &str| format_result(&eval_source(source, env.clone()).unwrap(), false);
+    assert_eq!("5", result("5"));
+    assert_eq!("\"hi\"", result("\"hi\""));
+    assert_eq!("fn(x, y)", result("fn(x, y) { x + y }"));
+    assert_eq!("null", result("if (false) { 1 }"));
+    assert_eq!("TypeError: type mismatch: STRING + INTEGER (line 1, column 1)", result("\"a\" + 1"));
+    assert_eq!("\x1b[31mZeroDivisionError: division by zero: 1 / 0 (line 1, column 1)\x1b[0m",
+        format_result(&eval_source("1 / 0", env.clone()).unwrap(), true));
+}
+
+#[test]
+fn parse_transcript() {
+    use crate::repl::transcript::{parse, Entry};
+
+    let transcript = "> let f = fn(x) {\n...   x * 2\n... };\nfn(x)\n> :env\nf: FUNCTION = fn(x) {(x * 2)}\n>\nnull\n";
+    assert_eq!(vec![
+        Entry { line: 1, input: String::from("let f = fn(x) {\n  x * 2\n};\n"), output: String::from("fn(x)") },
+        Entry { line: 5, input: String::from(":env\n"), output: String::from("f: FUNCTION = fn(x) {(x * 2)}") },
+        Entry { line: 7, input: String::from("\n"), output: String::from("null") },
+    ], parse(transcript));
+}
+
+#[test]
+fn record_and_replay() {
+    use crate::repl::transcript::{output, replay, Recorder};
+    use crate::repl::eval_source;
+
+    let path = std::env::temp_dir().join(format!("waiig_rs_transcript_test_{}", std::process::id()));
+    let mut recorder = Recorder::create(path.to_str().unwrap()).unwrap();
+    let env = Arc::new(Mutex::new(Environment::new()));
+    for input in ["let x = 5;\n", "let double = fn(a) {\n  a * 2\n};\n", "double(x)\n", "\"a\" + 1\n"] {
+        recorder.record(input, &output(&eval_source(input, env.clone()))).unwrap();
+    }
+    let transcript = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let (replayed, mismatches) = replay(&transcript).unwrap();
+    assert_eq!(4, replayed);
+    assert!(mismatches.is_empty());
+
+    let (_, mismatches) = replay(&transcript.replace("\n10\n", "\n11\n")).unwrap();
+    assert_eq!(1, mismatches.len());
+    assert_eq!("double(x)", mismatches[0].input);
+    assert_eq!("11", mismatches[0].expected);
+    assert_eq!("10", mismatches[0].actual);
+}
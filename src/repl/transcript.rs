@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use crate::object::{Environment, Object};
+use super::commands::{self, Flow};
+
+// A transcript reads like the session it came from: each input follows a
+// `> ` prompt, with `... ` on its continuation lines, and everything up to
+// the next prompt is the output it produced.
+
+/// Appends the inputs and outputs of a REPL session to a transcript file.
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    pub fn create(path: &str) -> std::io::Result<Self> {
+        return Ok(Self { file: File::create(path)? });
+    }
+
+    pub fn record(&mut self, input: &str, output: &str) -> std::io::Result<()> {
+        for (i, line) in input.trim_end_matches('\n').split('\n').enumerate() {
+            let prompt = if i == 0 { "> " } else { "... " };
+            writeln!(self.file, "{}{}", prompt, line)?;
+        }
+        if !output.is_empty() {
+            writeln!(self.file, "{}", output.trim_end_matches('\n'))?;
+        }
+        return self.file.flush();
+    }
+}
+
+/// What the REPL prints for an evaluation, without colour.
+pub fn output(result: &Result<Object<'static>, String>) -> String {
+    return match result {
+        Ok(obj) => super::pretty::format_result(obj, false),
+        Err(e) => e.clone(),
+    };
+}
+
+/// One input from a transcript and the output recorded for it.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    /// The transcript line the input starts on.
+    pub line: usize,
+    pub input: String,
+    pub output: String,
+}
+
+pub fn parse(transcript: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut output: Vec<&str> = Vec::new();
+    for (i, line) in transcript.lines().enumerate() {
+        if let Some(input) = line.strip_prefix("> ").or(if line == ">" { Some("") } else { None }) {
+            if let Some(entry) = entries.last_mut() {
+                entry.output = output.join("\n");
+            }
+            output.clear();
+            entries.push(Entry { line: i + 1, input: format!("{}\n", input), output: String::new() });
+            continue;
+        }
+        let continuation = line.strip_prefix("... ").or(if line == "..." { Some("") } else { None });
+        match (continuation, entries.last_mut()) {
+            (Some(rest), Some(entry)) if output.is_empty() => {
+                entry.input += rest;
+                entry.input += "\n";
+            },
+            _ => output.push(line),
+        }
+    }
+    if let Some(entry) = entries.last_mut() {
+        entry.output = output.join("\n");
+    }
+    return entries;
+}
+
+/// An input whose output no longer matches the transcript.
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+    pub line: usize,
+    pub input: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Replays a transcript in a fresh environment, returning the number of
+/// inputs replayed and every input whose output differs from the recording.
+pub fn replay(transcript: &str) -> std::io::Result<(usize, Vec<Mismatch>)> {
+    let mut env = Arc::new(Mutex::new(Environment::new()));
+    let entries = parse(transcript);
+    let mut mismatches: Vec<Mismatch> = Vec::new();
+    let mut replayed = 0;
+    for entry in entries.iter() {
+        let actual = if entry.input.trim_start().starts_with(':') {
+            let mut out: Vec<u8> = Vec::new();
+            if let Flow::Quit = commands::execute(&entry.input, &mut env, &mut out)? {
+                break;
+            }
+            String::from_utf8_lossy(&out).trim_end_matches('\n').to_string()
+        } else {
+            output(&super::eval_source(&entry.input, env.clone()))
+        };
+        replayed += 1;
+        if actual != entry.output {
+            mismatches.push(Mismatch {
+                line: entry.line,
+                input: entry.input.trim_end().to_string(),
+                expected: entry.output.clone(),
+                actual,
+            });
+        }
+    }
+    return Ok((replayed, mismatches));
+}
+
+/// Replays the transcript at `path`, writing a report of any mismatches to
+/// `out`. Returns whether every output matched.
+pub fn replay_file(path: &str, out: &mut dyn Write) -> std::io::Result<bool> {
+    let transcript = match std::fs::read_to_string(path) {
+        Ok(transcript) => transcript,
+        Err(e) => {
+            writeln!(out, "error reading {}: {}", path, e)?;
+            return Ok(false);
+        },
+    };
+    let (replayed, mismatches) = replay(&transcript)?;
+    for m in mismatches.iter() {
+        writeln!(out, "{}:{}: > {}", path, m.line, m.input)?;
+        writeln!(out, "expected: {}", m.expected)?;
+        writeln!(out, "     got: {}", m.actual)?;
+    }
+    writeln!(out, "replayed {} inputs, {} mismatched", replayed, mismatches.len())?;
+    return Ok(mismatches.is_empty());
+}
@@ -0,0 +1,87 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use crate::ast::Node;
+use crate::coverage::Coverage;
+use crate::evaluator::{eval, eval_with_hook, Hook, Limits, Shared};
+use crate::object::{Environment, Object};
+use crate::parser::parse_source;
+use crate::profiler::Profiler;
+use crate::repl::pretty::format_result;
+
+#[cfg(test)]
+mod tests;
+
+/// What `run` records besides the program's value.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Print a profile and write collapsed stacks to `<file>.folded`.
+    pub profile: bool,
+    /// Write LCOV coverage to this path.
+    pub coverage: Option<String>,
+}
+
+fn percent(hit: usize, found: usize) -> f64 {
+    return if found == 0 { 100.0 } else { hit as f64 * 100.0 / found as f64 };
+}
+
+/// Runs the program at `path`, printing its value unless it's null, then
+/// whatever `options` ask for. Returns false if the program can't be read
+/// or parsed, or fails.
+pub fn run_file(path: &str, options: &Options, out: &mut dyn Write) -> std::io::Result<bool> {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error reading {}: {}", path, e);
+            return Ok(false);
+        },
+    };
+    let program = match parse_source(&source) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(false);
+        },
+    };
+
+    let env = Arc::new(Mutex::new(Environment::new()));
+    let profiler = options.profile.then(|| Arc::new(Mutex::new(Profiler::new())));
+    let coverage = options.coverage.as_ref().map(|_| Arc::new(Mutex::new(Coverage::new(&program))));
+    let mut hooks: Vec<Box<dyn Hook + Send>> = Vec::new();
+    if let Some(profiler) = &profiler {
+        hooks.push(Box::new(Shared(profiler.clone())));
+    }
+    if let Some(coverage) = &coverage {
+        hooks.push(Box::new(Shared(coverage.clone())));
+    }
+    let result = if hooks.is_empty() {
+        eval(Node::Program(program), env)
+    } else {
+        eval_with_hook(Node::Program(program), env, Limits::default(), Box::new(hooks)).0
+    };
+
+    match &result {
+        Object::Error(_) => eprintln!("{}", format_result(&result, false)),
+        Object::Null => {},
+        _ => writeln!(out, "{}", format_result(&result, false))?,
+    }
+    if let Some(profiler) = profiler {
+        let profile = Shared::into_inner(profiler).finish();
+        let folded = format!("{}.folded", path);
+        std::fs::write(&folded, profile.collapsed())?;
+        write!(out, "{}", profile.report(&source))?;
+        writeln!(out, "\ncollapsed stacks written to {}", folded)?;
+    }
+    if let (Some(coverage), Some(lcov)) = (coverage, &options.coverage) {
+        let coverage = Shared::into_inner(coverage);
+        std::fs::write(lcov, coverage.lcov(path))?;
+        let ((lines_hit, lines_found), (branches_hit, branches_found)) = coverage.summary();
+        writeln!(
+            out,
+            "lines: {}/{} ({:.1}%), branches: {}/{} ({:.1}%); coverage written to {}",
+            lines_hit, lines_found, percent(lines_hit, lines_found),
+            branches_hit, branches_found, percent(branches_hit, branches_found),
+            lcov,
+        )?;
+    }
+    return Ok(!matches!(result, Object::Error(_)));
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+const PROGRAM: &str = "let fib = fn(n) {
+    if (n < 2) { return n; }
+    fib(n - 1) + fib(n - 2)
+};
+fib(5)";
+
+/// Writes `source` to a temporary file named after `name`, returning its path.
+#[cfg(test)]
+fn script(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(format!("waiig_run_{}_{}.mk", std::process::id(), name));
+    std::fs::write(&path, source).unwrap();
+    return path.to_str().unwrap().to_string();
+}
+
+#[test]
+fn run_prints_the_value() {
+    use crate::run::{run_file, Options};
+
+    let path = script("value", PROGRAM);
+    let mut out: Vec<u8> = Vec::new();
+    assert!(run_file(&path, &Options::default(), &mut out).unwrap());
+    assert_eq!("5\n", String::from_utf8(out).unwrap());
+
+    std::fs::write(&path, "if (false) { 1 }").unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    assert!(run_file(&path, &Options::default(), &mut out).unwrap());
+    assert!(out.is_empty());
+
+    std::fs::write(&path, "1 + true").unwrap();
+    assert!(!run_file(&path, &Options::default(), &mut Vec::new()).unwrap());
+    std::fs::write(&path, "let = 1").unwrap();
+    assert!(!run_file(&path, &Options::default(), &mut Vec::new()).unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert!(!run_file(&path, &Options::default(), &mut Vec::new()).unwrap());
+}
+
+#[test]
+fn run_with_profile_and_coverage() {
+    use crate::run::{run_file, Options};
+
+    let path = script("profile", PROGRAM);
+    let lcov = format!("{}.info", path);
+    let options = Options { profile: true, coverage: Some(lcov.clone()) };
+    let mut out: Vec<u8> = Vec::new();
+    assert!(run_file(&path, &options, &mut out).unwrap());
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!("5", lines[0]);
+    assert!(lines[1].starts_with("function") && lines[1].ends_with("allocations"), "{}", out);
+    assert!(out.contains("  fib(n - 1) + fib(n - 2)\n"), "{}", out);
+
+    let folded = format!("{}.folded", path);
+    assert!(out.contains(&format!("collapsed stacks written to {}\n", folded)), "{}", out);
+    assert!(std::fs::read_to_string(&folded).unwrap().starts_with("<main> "));
+
+    assert!(out.ends_with(&format!("lines: 4/4 (100.0%), branches: 2/2 (100.0%); coverage written to {}\n", lcov)), "{}", out);
+    let info = std::fs::read_to_string(&lcov).unwrap();
+    assert!(info.starts_with(&format!("TN:\nSF:{}\n", path)), "{}", info);
+
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(folded).unwrap();
+    std::fs::remove_file(lcov).unwrap();
+}
@@ -0,0 +1,136 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::ast::{Expression, Node, Program, Statement};
+use crate::evaluator::eval;
+use crate::object::{Environment, Object, RuntimeError};
+use crate::parser::parse_source;
+
+#[cfg(test)]
+mod tests;
+
+/// The prefix that marks a top-level function as a test.
+pub const PREFIX: &str = "test_";
+
+/// How one test went. A test fails if it raises an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub name: String,
+    pub error: Option<RuntimeError>,
+}
+
+/// The names of the tests in `program`: functions bound by top-level `let`s
+/// whose names start with `test_`, in the order they're defined.
+pub fn discover(program: &Program) -> Vec<String> {
+    return program.statements.iter().filter_map(|s| match s {
+        Statement::LetStatement(ls) if ls.name.value.starts_with(PREFIX) => match &ls.value {
+            Some(Expression::FunctionLiteral(_)) => Some(ls.name.value.clone()),
+            _ => None,
+        },
+        _ => None,
+    }).collect();
+}
+
+/// Runs each test in `program`. Every test gets a fresh environment that
+/// the whole program is run in first, so tests can't see each other's
+/// bindings. If the program itself fails, so does every test.
+pub fn run_tests(program: &Program) -> Vec<Outcome> {
+    return discover(program).into_iter().map(|name| {
+        let env = Arc::new(Mutex::new(Environment::new()));
+        let mut result = eval(Node::Program(program.clone()), env.clone());
+        if !matches!(result, Object::Error(_)) {
+            let call = parse_source(&format!("{}()", name)).expect("test names are identifiers");
+            result = eval(Node::Program(call), env);
+        }
+        let error = match result {
+            Object::Error(err) => Some(err),
+            _ => None,
+        };
+        Outcome { name, error }
+    }).collect();
+}
+
+/// The `.mk` files at `path`: the file itself, or those in the directory
+/// and its subdirectories, sorted.
+pub fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(path)?.map(|e| e.map(|e| e.path())).collect::<Result<_, _>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect_files(&entry, files)?;
+        } else if entry.extension().is_some_and(|e| e == "mk") {
+            files.push(entry);
+        }
+    }
+    return Ok(());
+}
+
+/// Runs the tests in the `.mk` files at each of `paths`, printing each
+/// result, then the failures with their errors and a summary. Returns
+/// whether every test passed and every file could be read and parsed.
+pub fn test_paths(paths: &[&str], out: &mut dyn Write) -> std::io::Result<bool> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        if let Err(e) = collect_files(Path::new(path), &mut files) {
+            eprintln!("error reading {}: {}", path, e);
+            return Ok(false);
+        }
+    }
+
+    let mut ok = true;
+    let (mut passed, mut failed) = (0, 0);
+    let mut failures: Vec<String> = Vec::new();
+    for file in files {
+        let path = file.display().to_string();
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("error reading {}: {}", path, e);
+                ok = false;
+                continue;
+            },
+        };
+        let program = match parse_source(&source) {
+            Ok(program) => program,
+            Err(e) => {
+                writeln!(out, "{}: failed to parse", path)?;
+                failures.push(format!("---- {} ----\n{}", path, e));
+                ok = false;
+                continue;
+            },
+        };
+        let outcomes = run_tests(&program);
+        if outcomes.is_empty() {
+            continue;
+        }
+        writeln!(out, "running {} test{} from {}", outcomes.len(), if outcomes.len() == 1 { "" } else { "s" }, path)?;
+        for outcome in outcomes {
+            match outcome.error {
+                None => {
+                    writeln!(out, "test {} ... ok", outcome.name)?;
+                    passed += 1;
+                },
+                Some(err) => {
+                    writeln!(out, "test {} ... FAILED", outcome.name)?;
+                    let at = err.position.map_or(path.clone(), |p| format!("{}:{}:{}", path, p.line, p.column));
+                    failures.push(format!("---- {} at {} ----\n{}: {}", outcome.name, at, err.kind.as_str(), err.message));
+                    failed += 1;
+                },
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        writeln!(out, "\nfailures:")?;
+        for failure in &failures {
+            writeln!(out, "\n{}", failure)?;
+        }
+    }
+    let ok = ok && failed == 0;
+    writeln!(out, "\ntest result: {}. {} passed; {} failed", if ok { "ok" } else { "FAILED" }, passed, failed)?;
+    return Ok(ok);
+}
@@ -0,0 +1,133 @@
+#[cfg(test)]
+const TESTS: &str = "let add = fn(a, b) { a + b };
+let test_add = fn() {
+    import \"std.test\";
+    assert_eq(add(1, 2), 3);
+};
+let helper = fn() { 1 };
+let test_wrong = fn() {
+    import \"std.test\";
+    assert_eq(add(1, 2), 4);
+};
+let test_not_a_function = 1;
+let test_error = fn() {
+    import \"std.test\";
+    let e = assert_error(fn() { 1 / 0 });
+    assert_eq(e.kind, \"ZeroDivisionError\");
+};";
+
+#[cfg(test)]
+fn parse(source: &str) -> crate::ast::Program {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    let mut p = Parser::new(Lexer::new(source));
+    return p.parse_program().unwrap();
+}
+
+#[test]
+fn discover_finds_test_functions() {
+    use crate::testing::discover;
+
+    assert_eq!(vec!["test_add", "test_wrong", "test_error"], discover(&parse(TESTS)));
+    // only top-level functions count
+    assert!(discover(&parse("let f = fn() { let test_inner = fn() { 1 }; test_inner() };")).is_empty());
+}
+
+#[test]
+fn run_tests_outcomes() {
+    use crate::object::ErrorKind;
+    use crate::testing::run_tests;
+
+    let outcomes = run_tests(&parse(TESTS));
+    let results: Vec<(&str, bool)> = outcomes.iter().map(|o| (o.name.as_str(), o.error.is_none())).collect();
+    assert_eq!(vec![("test_add", true), ("test_wrong", false), ("test_error", true)], results);
+    let err = outcomes[1].error.as_ref().unwrap();
+    assert_eq!(ErrorKind::AssertionError, err.kind);
+    assert_eq!("values differ (- expected, + actual):\n- 4\n+ 3", err.message);
+    assert_eq!(9, err.position.unwrap().line);
+
+    // if the program fails, every test does
+    let outcomes = run_tests(&parse("let test_a = fn() { 1 }; let test_b = fn() { 2 }; 1 / 0;"));
+    assert_eq!(2, outcomes.len());
+    assert!(outcomes.iter().all(|o| o.error.as_ref().unwrap().kind == ErrorKind::ZeroDivisionError));
+}
+
+#[test]
+fn assertion_messages() {
+    use std::sync::{Arc, Mutex};
+    use crate::evaluator::eval;
+    use crate::object::*;
+
+    let tests = [
+        ("assert(1 < 2)", "null"),
+        ("assert(1 > 2)", "AssertionError: assertion failed: got false"),
+        ("assert_eq(\"a\" + \"b\", \"ab\")", "null"),
+        ("assert_eq(1, \"1\")", "AssertionError: values differ (- expected, + actual):\n  1 (expected STRING, got INTEGER)"),
+        ("assert_error(fn() { throw \"x\" }).message", "x"),
+        ("assert_error(fn() { 1 })", "AssertionError: expected an error, got 1"),
+        ("assert_eq(1)", "TypeError: wrong number of arguments to assert_eq: expected 2, got 1"),
+    ];
+    for (input, expected) in tests {
+        let program = parse(&format!("import \"std.test\"; {}", input));
+        let env = Arc::new(Mutex::new(Environment::new()));
+        let actual = match eval(crate::ast::Node::Program(program), env) {
+            Object::Error(err) => format!("{}: {}", err.kind.as_str(), err.message),
+            Object::String(s) => s,
+            obj => obj.inspect(),
+        };
+        assert_eq!(expected, actual, "{}", input);
+    }
+}
+
+#[test]
+fn test_paths_output() {
+    use crate::testing::test_paths;
+
+    let dir = std::env::temp_dir().join(format!("waiig_test_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("nested")).unwrap();
+    std::fs::write(dir.join("a.mk"), TESTS).unwrap();
+    std::fs::write(dir.join("nested").join("b.mk"), "let test_ok = fn() { import \"std.test\"; assert(true) };").unwrap();
+    std::fs::write(dir.join("lib.mk"), "let helper = fn() { 1 };").unwrap();
+    std::fs::write(dir.join("notes.txt"), "let test_x = fn() { 1 / 0 };").unwrap();
+
+    let mut out: Vec<u8> = Vec::new();
+    assert!(!test_paths(&[dir.to_str().unwrap()], &mut out).unwrap());
+    let a = dir.join("a.mk").display().to_string();
+    let b = dir.join("nested").join("b.mk").display().to_string();
+    let expected = format!("running 3 tests from {a}
+test test_add ... ok
+test test_wrong ... FAILED
+test test_error ... ok
+running 1 test from {b}
+test test_ok ... ok
+
+failures:
+
+---- test_wrong at {a}:9:5 ----
+AssertionError: values differ (- expected, + actual):
+- 4
++ 3
+
+test result: FAILED. 3 passed; 1 failed
+");
+    assert_eq!(expected, String::from_utf8(out).unwrap());
+
+    let mut out: Vec<u8> = Vec::new();
+    assert!(test_paths(&[b.as_str()], &mut out).unwrap());
+    assert!(String::from_utf8(out).unwrap().ends_with("test result: ok. 1 passed; 0 failed\n"));
+
+    std::fs::write(dir.join("lib.mk"), "let = 1").unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    assert!(!test_paths(&[dir.join("lib.mk").to_str().unwrap()], &mut out).unwrap());
+    assert!(String::from_utf8(out).unwrap().contains("failed to parse"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn check_ignores_unused_tests() {
+    use crate::check::check_source;
+
+    let found: Vec<&str> = check_source(TESTS).iter().map(|d| d.code).collect();
+    assert_eq!(vec!["unused-binding"], found);
+}
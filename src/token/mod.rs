@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Token {
     IDENT(String),
@@ -15,10 +15,15 @@ pub enum Token {
     ELSE,
     RETURN,
     IMPORT,
+    TRY,
+    CATCH,
+    FINALLY,
+    THROW,
 
     ILLEGAL,
     EOF,
     COMMA,
+    DOT,
     SEMICOLON,
     LPAREN,
     RPAREN,
@@ -41,7 +46,22 @@ pub enum Token {
     NEQ,
 }
 
-pub fn lookup_ident(ident: String) -> Token {
+/// A 1-based line and column in the source text.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A `//` comment, which runs to the end of its line.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comment {
+    pub position: Position,
+    /// The comment's text, including the leading `//`.
+    pub text: String,
+}
+
+fn keyword_table() -> HashMap<&'static str, Token> {
     let mut keywords: HashMap<&str, Token> = HashMap::new();
 
     keywords.insert("let", Token::LET);
@@ -52,8 +72,23 @@ pub fn lookup_ident(ident: String) -> Token {
     keywords.insert("else", Token::ELSE);
     keywords.insert("return", Token::RETURN);
     keywords.insert("import", Token::IMPORT);
+    keywords.insert("try", Token::TRY);
+    keywords.insert("catch", Token::CATCH);
+    keywords.insert("finally", Token::FINALLY);
+    keywords.insert("throw", Token::THROW);
 
-    return match keywords.get(ident.as_str()) {
+    return keywords;
+}
+
+/// Every keyword `lookup_ident` recognises, sorted.
+pub fn keywords() -> Vec<&'static str> {
+    let mut keywords: Vec<&str> = keyword_table().into_keys().collect();
+    keywords.sort();
+    return keywords;
+}
+
+pub fn lookup_ident(ident: String) -> Token {
+    return match keyword_table().get(ident.as_str()) {
         Some(x) => x.clone(),
         None => Token::IDENT(ident)
     }
@@ -72,9 +107,14 @@ pub fn get_literal(token: &Token) -> String {
         Token::ELSE => String::from("else"),
         Token::RETURN => String::from("return"),
         Token::IMPORT => String::from("import"),
+        Token::TRY => String::from("try"),
+        Token::CATCH => String::from("catch"),
+        Token::FINALLY => String::from("finally"),
+        Token::THROW => String::from("throw"),
         Token::ILLEGAL => String::from("ILLEGAL"),
         Token::EOF => String::from("\0"),
         Token::COMMA => String::from(","),
+        Token::DOT => String::from("."),
         Token::SEMICOLON => String::from(";"),
         Token::LPAREN => String::from("("),
         Token::RPAREN => String::from(")"),
@@ -0,0 +1,2 @@
+// result: true
+true == true
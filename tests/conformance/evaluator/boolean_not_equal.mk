@@ -0,0 +1,2 @@
+// result: false
+true != true
@@ -0,0 +1,2 @@
+// result: false
+false == true
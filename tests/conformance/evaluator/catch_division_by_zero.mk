@@ -0,0 +1,2 @@
+// result: ZeroDivisionError
+try { 10 / 0 } catch (e) { e.kind }
@@ -0,0 +1,2 @@
+// result: TypeError
+try { 5 + true } catch (e) { e.kind }
@@ -0,0 +1,2 @@
+// result: unknown identifier: foo
+try { foo } catch (e) { e.message }
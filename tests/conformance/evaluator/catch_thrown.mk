@@ -0,0 +1,2 @@
+// result: bad input (Error)
+try { throw "bad input"; } catch (e) { e.message + " (" + e.kind + ")" }
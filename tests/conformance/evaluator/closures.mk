@@ -0,0 +1,5 @@
+// result: 5
+let adder = fn(x) { fn(y) { x + y } };
+let add2 = adder(2);
+let x = 40;
+add2(3)
@@ -0,0 +1,2 @@
+// result: 2
+6 / 3
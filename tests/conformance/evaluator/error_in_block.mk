@@ -0,0 +1,2 @@
+// error: TypeError: unknown operator: BOOLEAN + BOOLEAN (line 2, column 15)
+if (10 > 1) { return true + false; }
@@ -0,0 +1,4 @@
+// error: TypeError: type mismatch: INTEGER + BOOLEAN (line 3, column 1)
+let x = 1;
+let y = x +
+  true;
@@ -0,0 +1,2 @@
+// stdout: fn(a, b)
+fn(a, b) { a + b }
@@ -0,0 +1,2 @@
+// result: false
+9 > 9
@@ -0,0 +1,2 @@
+// result: false
+if (0) { true } else { false }
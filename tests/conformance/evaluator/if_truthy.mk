@@ -0,0 +1,2 @@
+// result: true
+if (1) { true } else { false }
@@ -0,0 +1,2 @@
+// result: null
+if (false) { 1 }
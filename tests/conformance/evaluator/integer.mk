@@ -0,0 +1,2 @@
+// result: 5
+5
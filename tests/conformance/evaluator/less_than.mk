@@ -0,0 +1,2 @@
+// result: false
+6 < 3
@@ -0,0 +1,2 @@
+// result: -10
+-10
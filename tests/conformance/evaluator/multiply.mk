@@ -0,0 +1,2 @@
+// result: 18
+3 * 6
@@ -0,0 +1,4 @@
+// result: 0
+let even = fn(n) { if (n == 0) { 1 } else { odd(n - 1) } };
+let odd = fn(n) { if (n == 0) { 0 } else { even(n - 1) } };
+even(10001)
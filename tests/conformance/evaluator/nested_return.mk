@@ -0,0 +1,7 @@
+// result: 10
+if (10 > 1) {
+    if (10 > 1) {
+        return 10;
+    }
+    return 1;
+}
@@ -0,0 +1,2 @@
+// result: true
+4 != 9
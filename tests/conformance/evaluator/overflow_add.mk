@@ -0,0 +1,2 @@
+// error: OverflowError: integer overflow: 2147483647 + 1 (line 2, column 1)
+2147483647 + 1
@@ -0,0 +1,2 @@
+// error: OverflowError: integer overflow: 65536 * 65536 (line 2, column 1)
+65536 * 65536
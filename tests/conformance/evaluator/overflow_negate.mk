@@ -0,0 +1,3 @@
+// error: OverflowError: integer overflow: -(-2147483648) (line 3, column 1)
+let min = -2147483647 - 1;
+-min
@@ -0,0 +1,2 @@
+// result: OverflowError
+try { 2 ** 31 } catch (e) { e.kind }
@@ -0,0 +1,2 @@
+// result: 1024
+2 ** 10
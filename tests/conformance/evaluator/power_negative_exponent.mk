@@ -0,0 +1,2 @@
+// error: ValueError: negative exponent: 2 ** -1 (line 2, column 1)
+2 ** -1
@@ -0,0 +1,2 @@
+// result: ZeroDivisionError
+try { try { 1 / 0 } catch (e) { throw e; } } catch (outer) { outer.kind }
@@ -0,0 +1,3 @@
+// result: 5
+return 5;
+6
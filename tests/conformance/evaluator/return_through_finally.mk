@@ -0,0 +1,3 @@
+// result: body
+let f = fn() { try { return "body"; } finally { 1 } };
+f()
@@ -0,0 +1,3 @@
+// result: hello world!
+// stdout: "hello world!"
+"hello " + "world!";
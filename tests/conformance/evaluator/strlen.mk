@@ -0,0 +1,3 @@
+// result: 5
+import "std.string";
+strlen("hello")
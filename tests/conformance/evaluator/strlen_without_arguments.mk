@@ -0,0 +1,3 @@
+// error: TypeError: wrong number of arguments to strlen: expected 1, got 0 (line 3, column 1)
+import "std.string";
+strlen()
@@ -0,0 +1,2 @@
+// result: 0
+9 - 9
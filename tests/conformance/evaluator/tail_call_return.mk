@@ -0,0 +1,3 @@
+// result: 10000
+let count = fn(n, acc) { if (n == 0) { return acc; } return count(n - 1, acc + 1); };
+count(10000, 0)
@@ -0,0 +1,2 @@
+// result: ok
+try { "ok" } catch (e) { "caught" }
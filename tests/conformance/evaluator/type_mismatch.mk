@@ -0,0 +1,2 @@
+// error: TypeError: type mismatch: INTEGER + BOOLEAN (line 2, column 1)
+5 + true;
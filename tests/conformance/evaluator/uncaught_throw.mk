@@ -0,0 +1,2 @@
+// error: Error: oops (line 2, column 18)
+let x = 0; try { throw "oops"; } finally { 1 }
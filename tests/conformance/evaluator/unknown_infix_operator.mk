@@ -0,0 +1,2 @@
+// error: TypeError: unknown operator: BOOLEAN + BOOLEAN (line 2, column 4)
+5; true + false; 5
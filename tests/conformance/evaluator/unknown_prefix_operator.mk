@@ -0,0 +1,2 @@
+// error: TypeError: unknown operator: -BOOLEAN (line 2, column 1)
+-true
@@ -0,0 +1,3 @@
+// parse error: no prefix parse function for ; found (line 3, column 9)
+let x = 5;
+let y = ;
@@ -0,0 +1,24 @@
+// ast: (program
+// ast:   (expr 21:1-21:9 (fn 21:1-21:8 (params) (block 21:6-21:8)))
+// ast:   (expr 22:1-22:21
+// ast:     (fn 22:1-22:20
+// ast:       (params x y)
+// ast:       (block 22:10-22:20
+// ast:         (expr 22:12-22:18
+// ast:           (infix 22:12-22:18 == (ident 22:12-22:13 x) (ident 22:17-22:18 y))))))
+// ast:   (expr 23:1-23:24
+// ast:     (call 23:1-23:23
+// ast:       (ident 23:1-23:12 my_function)
+// ast:       (infix 23:13-23:18 + (int 23:13-23:14 2) (int 23:17-23:18 3))
+// ast:       (int 23:20-23:22 17)))
+// ast:   (expr 24:1-24:14
+// ast:     (call 24:1-24:13
+// ast:       (call 24:1-24:10
+// ast:         (ident 24:1-24:4 add)
+// ast:         (ident 24:5-24:6 a)
+// ast:         (ident 24:8-24:9 b))
+// ast:       (ident 24:11-24:12 c))))
+fn() {};
+fn(x, y) { x == y };
+my_function(2 + 3, 17);
+add(a, b)(c);
@@ -0,0 +1,12 @@
+// ast: (program
+// ast:   (expr 11:1-11:18
+// ast:     (if 11:1-11:17
+// ast:       (infix 11:5-11:10 < (ident 11:5-11:6 x) (ident 11:9-11:10 y))
+// ast:       (block 11:12-11:17 (expr 11:14-11:15 (ident 11:14-11:15 x)))))
+// ast:   (expr 12:1-12:29
+// ast:     (if 12:1-12:28
+// ast:       (infix 12:5-12:10 < (ident 12:5-12:6 x) (ident 12:9-12:10 y))
+// ast:       (block 12:12-12:17 (expr 12:14-12:15 (ident 12:14-12:15 x)))
+// ast:       (block 12:23-12:28 (expr 12:25-12:26 (ident 12:25-12:26 y))))))
+if (x < y) { x };
+if (x < y) { x } else { y };
@@ -0,0 +1,3 @@
+// ast: (program
+// ast:   (import 3:1-3:21 "std.string"))
+import "std.string";
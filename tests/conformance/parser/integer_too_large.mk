@@ -0,0 +1,3 @@
+// parse error: integer literal 2147483648 is too large (line 3, column 1)
+2147483647;
+2147483648;
@@ -0,0 +1,7 @@
+// ast: (program
+// ast:   (let 5:1-5:11 x (int 5:9-5:10 5))
+// ast:   (let 6:1-6:14 y (bool 6:9-6:13 true))
+// ast:   (let 7:1-7:16 foobar (ident 7:14-7:15 y)))
+let x = 5;
+let y = true;
+let foobar = y;
@@ -0,0 +1,11 @@
+// ast: (program
+// ast:   (expr 7:1-7:8 (ident 7:1-7:7 foobar))
+// ast:   (expr 8:1-8:3 (int 8:1-8:2 5))
+// ast:   (expr 9:1-9:10 (string 9:1-9:9 "foobar"))
+// ast:   (expr 10:1-10:6 (bool 10:1-10:5 true))
+// ast:   (expr 11:1-11:7 (bool 11:1-11:6 false)))
+foobar;
+5;
+"foobar";
+true;
+false;
@@ -0,0 +1,3 @@
+// parse error: expected next token to be IDENT(""), got ASSIGN instead (line 3, column 5)
+// parse error: no prefix parse function for = found (line 3, column 5)
+let = 5;
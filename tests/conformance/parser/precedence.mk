@@ -0,0 +1,62 @@
+// infix operators of the same precedence group to the left, and
+// prefix operators bind tighter than any infix operator
+// ast: (program
+// ast:   (expr 50:1-50:7 (infix 50:1-50:6 + (int 50:1-50:2 5) (int 50:5-50:6 5)))
+// ast:   (expr 51:1-51:15
+// ast:     (infix 51:1-51:14 != (bool 51:1-51:5 true) (bool 51:9-51:14 false)))
+// ast:   (expr 52:1-52:11
+// ast:     (infix 52:1-52:10 +
+// ast:       (infix 52:1-52:6 + (ident 52:1-52:2 a) (ident 52:5-52:6 b))
+// ast:       (ident 52:9-52:10 c)))
+// ast:   (expr 53:1-53:11
+// ast:     (infix 53:1-53:10 +
+// ast:       (ident 53:1-53:2 a)
+// ast:       (infix 53:5-53:10 * (ident 53:5-53:6 b) (ident 53:9-53:10 c))))
+// ast:   (expr 54:1-54:13
+// ast:     (infix 54:1-54:12 *
+// ast:       (infix 54:1-54:8 + (ident 54:2-54:3 a) (ident 54:6-54:7 b))
+// ast:       (ident 54:11-54:12 c)))
+// ast:   (expr 55:1-55:13
+// ast:     (infix 55:1-55:12 -
+// ast:       (infix 55:1-55:8 + (int 55:2-55:3 5) (int 55:6-55:7 5))
+// ast:       (int 55:11-55:12 5)))
+// ast:   (expr 56:1-56:8
+// ast:     (infix 56:1-56:7 *
+// ast:       (prefix 56:1-56:3 - (ident 56:2-56:3 a))
+// ast:       (ident 56:6-56:7 b)))
+// ast:   (expr 57:1-57:11
+// ast:     (infix 57:1-57:10 /
+// ast:       (infix 57:1-57:6 * (ident 57:1-57:2 a) (ident 57:5-57:6 b))
+// ast:       (ident 57:9-57:10 c)))
+// ast:   (expr 58:1-58:15
+// ast:     (infix 58:1-58:14 -
+// ast:       (infix 58:1-58:10 +
+// ast:         (ident 58:1-58:2 a)
+// ast:         (infix 58:5-58:10 / (ident 58:5-58:6 b) (ident 58:9-58:10 c)))
+// ast:       (ident 58:13-58:14 d)))
+// ast:   (expr 59:1-59:15
+// ast:     (infix 59:1-59:14 ==
+// ast:       (infix 59:1-59:6 < (int 59:1-59:2 1) (int 59:5-59:6 2))
+// ast:       (bool 59:10-59:14 true)))
+// ast:   (expr 60:1-60:8 (infix 60:1-60:7 ** (int 60:1-60:2 2) (int 60:6-60:7 3)))
+// ast:   (expr 61:1-61:13
+// ast:     (infix 61:1-61:12 **
+// ast:       (infix 61:1-61:7 ** (int 61:1-61:2 2) (int 61:6-61:7 3))
+// ast:       (int 61:11-61:12 2)))
+// ast:   (expr 62:1-62:9
+// ast:     (infix 62:1-62:8 **
+// ast:       (prefix 62:1-62:3 - (int 62:2-62:3 2))
+// ast:       (int 62:7-62:8 2))))
+5 + 5;
+true != false;
+a + b + c;
+a + b * c;
+(a + b) * c;
+(5 + 5) - 5;
+-a * b;
+a * b / c;
+a + b / c - d;
+1 < 2 == true;
+2 ** 3;
+2 ** 3 ** 2;
+-2 ** 2;
@@ -0,0 +1,7 @@
+// ast: (program
+// ast:   (expr 5:1-5:4 (prefix 5:1-5:3 - (int 5:2-5:3 5)))
+// ast:   (expr 6:1-6:7 (prefix 6:1-6:6 ! (bool 6:2-6:6 true)))
+// ast:   (expr 7:1-7:5 (prefix 7:1-7:4 ! (prefix 7:2-7:4 - (ident 7:3-7:4 a)))))
+-5;
+!true;
+!-a;
@@ -0,0 +1,7 @@
+// ast: (program
+// ast:   (return 5:1-5:10 (int 5:8-5:9 5))
+// ast:   (return 6:1-6:15 (infix 6:8-6:14 * (int 6:8-6:10 17) (int 6:13-6:14 3)))
+// ast:   (return 7:1-7:15 (int 7:8-7:14 993322)))
+return 5;
+return 17 * 3;
+return 993322;
@@ -0,0 +1,19 @@
+// ast: (program
+// ast:   (expr 17:1-17:63
+// ast:     (try 17:1-17:62
+// ast:       (block 17:5-17:16
+// ast:         (expr 17:7-17:14 (call 17:7-17:14 (ident 17:7-17:12 risky))))
+// ast:       (catch e
+// ast:         (block 17:27-17:40
+// ast:           (expr 17:29-17:38 (member 17:29-17:38 (ident 17:29-17:30 e) message))))
+// ast:       (finally
+// ast:         (block 17:49-17:62
+// ast:           (expr 17:51-17:60 (call 17:51-17:60 (ident 17:51-17:58 cleanup)))))))
+// ast:   (expr 18:1-18:25
+// ast:     (try 18:1-18:24
+// ast:       (block 18:5-18:10 (expr 18:7-18:8 (int 18:7-18:8 1)))
+// ast:       (finally (block 18:19-18:24 (expr 18:21-18:22 (int 18:21-18:22 2))))))
+// ast:   (throw 19:1-19:13 (string 19:7-19:12 "bad")))
+try { risky() } catch (e) { e.message } finally { cleanup() };
+try { 1 } finally { 2 };
+throw "bad";
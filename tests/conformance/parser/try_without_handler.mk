@@ -0,0 +1,2 @@
+// parse error: try expression requires a catch or finally block (line 2, column 9)
+try { 1 }